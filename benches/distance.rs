@@ -0,0 +1,34 @@
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use find_peaks::PeakFinder;
+
+/// `n` samples with a peak every 20 samples, of pseudo-random heights.
+fn data(n: usize) -> Vec<f64> {
+    let mut state: u64 = 1;
+    (0..n)
+        .map(|i| {
+            if i % 20 == 10 {
+                state = state.wrapping_mul(6364136223846793005).wrapping_add(1);
+                1. + (state >> 33) as f64 / (1u64 << 31) as f64
+            } else {
+                0.
+            }
+        })
+        .collect()
+}
+
+fn distance(c: &mut Criterion) {
+    let mut group = c.benchmark_group("min_distance");
+    group.sample_size(10);
+
+    // up to 1M samples with 50k peaks
+    for &n in &[62_500, 250_000, 1_000_000] {
+        let y = data(n);
+        group.bench_with_input(BenchmarkId::from_parameter(n), &y, |b, y| {
+            b.iter(|| PeakFinder::new(y).with_min_distance(100).find_peaks())
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, distance);
+criterion_main!(benches);
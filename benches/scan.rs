@@ -0,0 +1,41 @@
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use find_peaks::PeakFinder;
+
+/// `n` samples of a smooth signal with a peak every 1000 samples, plus a little noise on every
+/// 16th sample.
+fn data(n: usize) -> Vec<f32> {
+    let mut state: u64 = 1;
+    (0..n)
+        .map(|i| {
+            let v = (i as f32 * std::f32::consts::TAU / 1000.).sin();
+            if i % 16 == 0 {
+                state = state.wrapping_mul(6364136223846793005).wrapping_add(1);
+                v + (state >> 40) as f32 / (1u64 << 24) as f32 * 1e-3
+            } else {
+                v
+            }
+        })
+        .collect()
+}
+
+fn scan(c: &mut Criterion) {
+    let mut group = c.benchmark_group("local_maxima");
+    group.sample_size(10);
+
+    let y = data(16_000_000);
+    // the finders are set up once, as that allocates the x-values
+    let scalar = PeakFinder::new(&y);
+    let mut simd = PeakFinder::new(&y);
+    simd.with_simd();
+
+    group.bench_with_input(BenchmarkId::new("scalar", y.len()), &scalar, |b, fp| {
+        b.iter(|| fp.local_maxima())
+    });
+    group.bench_with_input(BenchmarkId::new("simd", y.len()), &simd, |b, fp| {
+        b.iter(|| fp.local_maxima())
+    });
+    group.finish();
+}
+
+criterion_group!(benches, scan);
+criterion_main!(benches);
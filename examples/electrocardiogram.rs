@@ -16,9 +16,8 @@ fn main_(py: Python) -> PyResult<()> {
     let misc = PyModule::import(py, "scipy.misc")?;
     let data: Vec<f64> = misc.call0("electrocardiogram")?.extract()?;
 
-    let mut fp = PeakFinder::new(&data);
-    fp.with_min_prominence(1000. / 400.);
-    let peaks = fp.find_peaks();
+    // sampled at 360 Hz, in millivolts
+    let peaks = PeakFinder::new(&data).ecg_preset(360.).find_peaks();
 
     let x: Vec<usize> = peaks.iter().map(|x| x.middle_position()).collect();
     let y: Vec<f64> = x.iter().map(|x| data[*x]).collect();
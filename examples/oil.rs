@@ -1,9 +1,8 @@
-use find_peaks::PeakFinder;
+use find_peaks::{PeakFinder, SortOrder};
 
 use pyo3::prelude::*;
 use pyo3::types::IntoPyDict;
 use pyo3::types::PyTuple;
-use std::collections::HashSet;
 
 fn main() -> Result<(), ()> {
     Python::with_gil(|py| {
@@ -30,16 +29,11 @@ fn main_(py: Python) -> PyResult<()> {
     let prom = 1.;
     for dist in [1, 6, 11, 15, 20, 30].iter() {
         let mut fp = PeakFinder::new(&data);
-        fp.with_min_prominence(prom).with_min_distance(*dist);
+        fp.with_min_prominence(prom)
+            .with_min_distance(*dist)
+            .with_sort_order(SortOrder::Position);
         let peaks = fp.find_peaks();
 
-        let mut pos = peaks
-            .iter()
-            .map(|x| x.middle_position())
-            .collect::<Vec<_>>();
-        pos.sort_unstable();
-        //println!("{}   {:?}", dist, pos);
-
         let x: Vec<usize> = peaks.iter().map(|x| x.middle_position()).collect();
         // let y: Vec<f64> = x.iter().map(|x| data[*x]).collect();
 
@@ -55,9 +49,20 @@ fn main_(py: Python) -> PyResult<()> {
         let scipy_x: Vec<usize> = scipy_tuple.get_item(0).extract()?;
         println!("dist {}\nscipy: {:?}\nfp_rs: {:?}\n", dist, scipy_x, x);
 
-        let scipy_x_set: HashSet<usize> = scipy_x.iter().cloned().collect();
-        let x_set: HashSet<usize> = x.iter().cloned().collect();
-        assert_eq!(scipy_x_set, x_set);
+        // scipy sorts its peaks by position as well
+        assert_eq!(scipy_x, x);
+
+        // prominences computed by persistence should agree with scipy's
+        let scipy_props: &PyAny = scipy_tuple.get_item(1);
+        let scipy_proms: Vec<f64> = scipy_props.get_item("prominences")?.extract()?;
+        let pers_peaks = fp.find_peaks_persistence();
+        for (i, prom) in scipy_x.iter().zip(scipy_proms.iter()) {
+            let p = pers_peaks
+                .iter()
+                .find(|p| p.middle_position() == *i)
+                .expect("Peak not found by persistence!");
+            assert!((p.prominence.unwrap() - prom).abs() < 1e-9);
+        }
     }
 
     // let plt = PyModule::import(py, "matplotlib.pyplot")?;
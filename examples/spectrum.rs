@@ -20,7 +20,7 @@ fn main() {
 
     let mut fp = PeakFinder::new(&data);
     fp.with_min_prominence(200.);
-    fp.with_min_height(0.);
+    fp.with_compute_all(true);
 
     let peaks = fp.find_peaks();
     for p in peaks {
@@ -0,0 +1,192 @@
+//! Continuous wavelet transform peak detection, following scipy's `find_peaks_cwt`.
+
+/// Ricker ("Mexican hat") wavelet sampled at `points` points with width parameter `a`.
+fn ricker(points: f64, a: f64) -> Vec<f64> {
+    let amplitude = 2. / ((3. * a).sqrt() * std::f64::consts::PI.powf(0.25));
+    let wsq = a * a;
+    let center = (points - 1.) / 2.;
+
+    (0..points.ceil() as usize)
+        .map(|i| {
+            let x = i as f64 - center;
+            let x2 = x * x;
+            amplitude * (1. - x2 / wsq) * (-x2 / (2. * wsq)).exp()
+        })
+        .collect()
+}
+
+/// Convolution of `data` with `kernel`, cut to the length of `data` around the center of the
+/// full convolution.
+fn convolve_same(data: &[f64], kernel: &[f64]) -> Vec<f64> {
+    let offset = (kernel.len() - 1) / 2;
+
+    (0..data.len())
+        .map(|i| {
+            let k = i + offset;
+            let j_min = (k + 1).saturating_sub(kernel.len());
+            let j_max = k.min(data.len() - 1);
+            (j_min..=j_max).map(|j| data[j] * kernel[k - j]).sum()
+        })
+        .collect()
+}
+
+/// One row of the transform per width.
+fn cwt(data: &[f64], widths: &[f64]) -> Vec<Vec<f64>> {
+    widths
+        .iter()
+        .map(|&width| {
+            let points = (10. * width).min(data.len() as f64);
+            let wavelet: Vec<f64> = ricker(points, width).into_iter().rev().collect();
+            convolve_same(data, &wavelet)
+        })
+        .collect()
+}
+
+/// Columns of the strict relative maxima of `row`, excluding its ends.
+fn relative_maxima(row: &[f64]) -> Vec<usize> {
+    (1..row.len().saturating_sub(1))
+        .filter(|&i| row[i] > row[i - 1] && row[i] > row[i + 1])
+        .collect()
+}
+
+/// Ridge line through the transform: `(row, column)` points and the number of rows since it
+/// was last extended.
+struct RidgeLine {
+    points: Vec<(usize, usize)>,
+    gap: usize,
+}
+
+/// Connect the relative maxima of the rows, starting from the widest row with any maxima and
+/// going towards the narrowest.
+fn identify_ridge_lines(
+    matrix: &[Vec<f64>],
+    max_distances: &[f64],
+    gap_thresh: f64,
+) -> Vec<Vec<(usize, usize)>> {
+    let maxima: Vec<Vec<usize>> = matrix.iter().map(|row| relative_maxima(row)).collect();
+
+    let start_row = match maxima.iter().rposition(|m| !m.is_empty()) {
+        Some(row) => row,
+        None => return Vec::new(),
+    };
+
+    let mut ridge_lines: Vec<RidgeLine> = maxima[start_row]
+        .iter()
+        .map(|&col| RidgeLine {
+            points: vec![(start_row, col)],
+            gap: 0,
+        })
+        .collect();
+    let mut final_lines = Vec::new();
+
+    for row in (0..start_row).rev() {
+        for line in &mut ridge_lines {
+            line.gap += 1;
+        }
+
+        // only the lines existing before this row can be extended
+        let prev_cols: Vec<usize> = ridge_lines
+            .iter()
+            .map(|line| line.points.last().unwrap().1)
+            .collect();
+
+        for &col in &maxima[row] {
+            let closest = prev_cols
+                .iter()
+                .enumerate()
+                .map(|(i, &c)| (i, c.abs_diff(col)))
+                .min_by_key(|&(_, d)| d);
+
+            match closest {
+                Some((i, d)) if d as f64 <= max_distances[row] => {
+                    ridge_lines[i].points.push((row, col));
+                    ridge_lines[i].gap = 0;
+                }
+                _ => ridge_lines.push(RidgeLine {
+                    points: vec![(row, col)],
+                    gap: 0,
+                }),
+            }
+        }
+
+        for i in (0..ridge_lines.len()).rev() {
+            if ridge_lines[i].gap as f64 > gap_thresh {
+                final_lines.push(ridge_lines.remove(i));
+            }
+        }
+    }
+
+    final_lines
+        .into_iter()
+        .chain(ridge_lines)
+        .map(|line| {
+            let mut points = line.points;
+            points.sort_by_key(|&(row, _)| row);
+            points
+        })
+        .collect()
+}
+
+/// Linearly interpolated `per`-th percentile of `values`.
+fn score_at_percentile(values: &[f64], per: f64) -> f64 {
+    let mut sorted = values.to_vec();
+    sorted.sort_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
+
+    let idx = per / 100. * (sorted.len() - 1) as f64;
+    let lo = idx.floor() as usize;
+    let hi = idx.ceil() as usize;
+    sorted[lo] + (sorted[hi] - sorted[lo]) * (idx - lo as f64)
+}
+
+/// Keep the ridge lines spanning at least a quarter of the rows and whose value at the
+/// narrowest width is at least `min_snr` times the local noise level of that row.
+fn filter_ridge_lines(
+    matrix: &[Vec<f64>],
+    ridge_lines: Vec<Vec<(usize, usize)>>,
+    min_snr: f64,
+    noise_perc: f64,
+) -> Vec<Vec<(usize, usize)>> {
+    let num_points = matrix[0].len();
+    let min_length = (matrix.len() as f64 / 4.).ceil() as usize;
+    let window_size = (num_points as f64 / 20.).ceil() as usize;
+    let (hf_window, odd) = (window_size / 2, window_size % 2);
+
+    let row_one = &matrix[0];
+    let noises: Vec<f64> = (0..num_points)
+        .map(|i| {
+            let start = i.saturating_sub(hf_window);
+            let end = (i + hf_window + odd).min(num_points);
+            score_at_percentile(&row_one[start..end], noise_perc)
+        })
+        .collect();
+
+    ridge_lines
+        .into_iter()
+        .filter(|line| {
+            if line.len() < min_length {
+                return false;
+            }
+            let (row, col) = line[0];
+            (matrix[row][col] / noises[col]).abs() >= min_snr
+        })
+        .collect()
+}
+
+/// Sorted indices of the peaks found on ridge lines of the Ricker wavelet transform of `data`
+/// at `widths`, with scipy's default parameters.
+pub(crate) fn find_peaks_cwt(data: &[f64], widths: &[f64]) -> Vec<usize> {
+    if data.is_empty() || widths.is_empty() {
+        return Vec::new();
+    }
+
+    let gap_thresh = widths[0].ceil();
+    let max_distances: Vec<f64> = widths.iter().map(|w| w / 4.).collect();
+
+    let matrix = cwt(data, widths);
+    let ridge_lines = identify_ridge_lines(&matrix, &max_distances, gap_thresh);
+    let filtered = filter_ridge_lines(&matrix, ridge_lines, 1., 10.);
+
+    let mut locs: Vec<usize> = filtered.iter().map(|line| line[0].1).collect();
+    locs.sort_unstable();
+    locs
+}
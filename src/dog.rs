@@ -0,0 +1,57 @@
+//! Difference-of-Gaussians band-pass filter, with scipy's `gaussian_filter1d` kernels.
+
+/// Normalized Gaussian kernel of standard deviation `sigma`, truncated at four standard
+/// deviations.
+fn gaussian_kernel(sigma: f64) -> Vec<f64> {
+    let radius = (4. * sigma + 0.5) as i64;
+    let weights: Vec<f64> = (-radius..=radius)
+        .map(|x| (-0.5 * (x * x) as f64 / (sigma * sigma)).exp())
+        .collect();
+    let sum: f64 = weights.iter().sum();
+    weights.into_iter().map(|w| w / sum).collect()
+}
+
+/// Index into data of length `n` for an index `i` outside of it: reflected about the edges
+/// (`d c b a | a b c d | d c b a`), or wrapped around for circular data.
+fn extend_index(i: i64, n: i64, circular: bool) -> usize {
+    if circular {
+        return i.rem_euclid(n) as usize;
+    }
+    let period = 2 * n;
+    let i = i.rem_euclid(period);
+    (if i < n { i } else { period - i - 1 }) as usize
+}
+
+/// Convolution of `data` with the Gaussian of `sigma`, of the same length as `data`.
+fn gaussian_filter(data: &[f64], sigma: f64, circular: bool) -> Vec<f64> {
+    let kernel = gaussian_kernel(sigma);
+    let radius = (kernel.len() / 2) as i64;
+    let n = data.len() as i64;
+
+    (0..n)
+        .map(|i| {
+            kernel
+                .iter()
+                .zip(i - radius..)
+                .map(|(w, j)| w * data[extend_index(j, n, circular)])
+                .sum()
+        })
+        .collect()
+}
+
+/// `data` smoothed with the Gaussian of `sigma1` minus `data` smoothed with the Gaussian of
+/// `sigma2`.
+pub(crate) fn difference_of_gaussians(
+    data: &[f64],
+    sigma1: f64,
+    sigma2: f64,
+    circular: bool,
+) -> Vec<f64> {
+    if data.is_empty() {
+        return Vec::new();
+    }
+
+    let narrow = gaussian_filter(data, sigma1, circular);
+    let wide = gaussian_filter(data, sigma2, circular);
+    narrow.into_iter().zip(wide).map(|(a, b)| a - b).collect()
+}
@@ -1,20 +1,75 @@
 use core::ops::Range;
 use std::borrow::Cow;
+use std::collections::{BinaryHeap, VecDeque};
+use std::fmt::Display;
+use std::io::{self, Write};
+use std::sync::Arc;
+
+mod cwt;
+mod dog;
 
 /// Struct containing the information of a found peak.
 ///
 /// Some values can be `None`s -- you have to specify at least one of the corresponding bounds in
-/// `PeakFinder`. If you don't, `find_peaks` skips their calculation.
+/// `PeakFinder`. If you don't, `find_peaks` skips their calculation. More properties may be
+/// added, so peaks can only be made by the finder.
 #[derive(Debug, PartialEq, Clone)]
+#[non_exhaustive]
 pub struct Peak<T> {
     /// range indices the peak spans
     pub position: Range<usize>,
-    /// absolute value of difference to the nearest neighbour to the left
+    /// absolute value of difference to the nearest neighbour to the left (zero for a left edge
+    /// peak)
     pub left_diff: T,
-    /// absolute value of difference to the nearest neighbour to the right
+    /// absolute value of difference to the nearest neighbour to the right (zero for a right edge
+    /// peak)
     pub right_diff: T,
     pub height: Option<T>,
     pub prominence: Option<T>,
+    /// index of the lower of the two valleys found by the prominence computation, the lowest
+    /// sample between the peak and the nearest higher samples (within the prominence window)
+    pub valley_index: Option<usize>,
+    /// value of the sample at `valley_index`
+    pub valley_value: Option<T>,
+    /// height of the peak above the valley on its left, `None` without a valley there (at the
+    /// left edge) or unless the prominence is measured from the local valleys (not set by
+    /// `PeakFinder::find_peaks_persistence`); the prominence is the smaller of
+    /// `left_prominence` and `right_prominence`
+    pub left_prominence: Option<T>,
+    /// height of the peak above the valley on its right, see `left_prominence`
+    pub right_prominence: Option<T>,
+    /// ratio of the left to the right half width at half prominence (`1.` for a symmetric peak),
+    /// see `PeakFinder::with_symmetry`
+    pub symmetry: Option<f64>,
+    /// distance from the middle of the peak to the half-prominence crossing on the left, in
+    /// x-units, see `PeakFinder::with_widths`
+    pub left_width: Option<f64>,
+    /// distance from the middle of the peak to the half-prominence crossing on the right, in
+    /// x-units, see `PeakFinder::with_widths`
+    pub right_width: Option<f64>,
+    /// prominence per x-unit of the full width at half prominence, `prominence / (left_width +
+    /// right_width)`, see `PeakFinder::with_sharpness`
+    pub sharpness: Option<f64>,
+    /// user metadata, see `PeakFinder::with_labeler`
+    pub label: Option<String>,
+    /// whether the peak is a shoulder on a flank rather than a local maximum, see
+    /// `PeakFinder::find_shoulders`
+    pub is_shoulder: bool,
+    /// whether the peak is at the saturation level, see `PeakFinder::with_saturation_level`
+    pub clipped: bool,
+    /// whether the valley search of the prominence reached an edge of the data without finding
+    /// a higher sample, so that the prominence may be cut short by the end of the record (not
+    /// set by `PeakFinder::find_peaks_persistence`)
+    pub bounded_by_edge: bool,
+    /// whether a valley search of the prominence was cut short by
+    /// `PeakFinder::with_prominence_max_scan`, so that the prominence is a lower bound
+    pub prominence_capped: bool,
+    /// weighted combination of the normalized height, prominence and sharpness, see
+    /// `PeakFinder::with_score`
+    pub score: Option<f64>,
+    /// 0-based rank among the returned peaks by prominence, or by height if not all of them
+    /// have a prominence, see `PeakFinder::with_rank`
+    pub rank: Option<usize>,
 }
 
 impl<T> Peak<T> {
@@ -25,6 +80,21 @@ impl<T> Peak<T> {
             right_diff,
             height: None,
             prominence: None,
+            valley_index: None,
+            valley_value: None,
+            left_prominence: None,
+            right_prominence: None,
+            symmetry: None,
+            left_width: None,
+            right_width: None,
+            sharpness: None,
+            label: None,
+            is_shoulder: false,
+            clipped: false,
+            bounded_by_edge: false,
+            prominence_capped: false,
+            score: None,
+            rank: None,
         }
     }
     fn add_height(&mut self, h: T) {
@@ -34,640 +104,11566 @@ impl<T> Peak<T> {
         self.prominence = Some(p);
     }
 
-    /// Get the middle index of a peak (plateau). For an even plateau size the function rounds down.
+    /// Get the middle index of a peak (plateau). For an even plateau size this is the right one of
+    /// the two middle indices, `center(PlateauCenter::Ceil)`.
     pub fn middle_position(&self) -> usize {
         (self.position.start + self.position.end) / 2
     }
-}
-
-#[derive(Debug, Clone)]
-struct Limits<T> {
-    pub lower: Option<T>,
-    pub upper: Option<T>,
-}
 
-impl<T> Limits<T>
-where
-    T: PartialOrd,
-{
-    pub fn empty() -> Self {
-        Self {
-            lower: None,
-            upper: None,
+    /// Get the center index of a peak (plateau) chosen by `mode`, see `PlateauCenter`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use find_peaks::{PeakFinder, PlateauCenter};
+    /// let y = [0., 1., 1., 1., 1., 0.];
+    ///
+    /// let p = &PeakFinder::new(&y).find_peaks()[0];
+    ///
+    /// assert_eq!(p.center(PlateauCenter::Floor), 2);
+    /// assert_eq!(p.center(PlateauCenter::Ceil), 3);
+    /// assert_eq!(p.center(PlateauCenter::Left), 1);
+    /// assert_eq!(p.center(PlateauCenter::Right), 4);
+    /// ```
+    pub fn center(&self, mode: PlateauCenter) -> usize {
+        let (first, last) = (self.first_index(), self.last_index());
+        match mode {
+            PlateauCenter::Floor => (first + last) / 2,
+            PlateauCenter::Ceil => (first + last).div_ceil(2),
+            PlateauCenter::Left => first,
+            PlateauCenter::Right => last,
         }
     }
 
-    pub fn is_empty(&self) -> bool {
-        self.lower.is_none() && self.upper.is_none()
-    }
-
-    pub fn is_inside(&self, v: &T) -> bool {
-        (self.lower.is_none() || v.ge(self.lower.as_ref().unwrap()))
-            && (self.upper.is_none() || v.le(self.upper.as_ref().unwrap()))
+    /// Get the first index of a peak (plateau).
+    pub fn first_index(&self) -> usize {
+        self.position.start
     }
-}
-
-/// Setup for the peak filtering.
-///
-/// Change the settings by using the methods for specifing the lower and upper bounds.
-#[derive(Clone)]
-pub struct PeakFinder<'a, T, S>
-where
-    [S]: ToOwned,
-{
-    y_data: &'a [T],
-    x_data: Cow<'a, [S]>,
-    height: Limits<T>,
-    prominence: Limits<T>,
-    difference: Limits<T>,
-    plateau_size: Limits<usize>,
-    distance: Limits<S>,
-    zero: Option<T>,
-}
 
-impl<'a, T> PeakFinder<'a, T, usize>
-where
-    T: Clone + std::ops::Sub<Output = T> + PartialOrd,
-{
-    /// Initialize with a data slice.
-    pub fn new(y_data: &'a [T]) -> Self {
-        let x: Vec<usize> = (0..y_data.len()).collect();
-        if y_data.is_empty() {
-            Self {
-                y_data,
-                x_data: Cow::from(x),
-                height: Limits::empty(),
-                prominence: Limits::empty(),
-                difference: Limits::empty(),
-                plateau_size: Limits::empty(),
-                distance: Limits::empty(),
-                zero: None,
-            }
-        } else {
-            let zero = Some(y_data[0].clone() - y_data[0].clone());
-            Self {
-                y_data,
-                x_data: Cow::from(x),
-                height: Limits::empty(),
-                prominence: Limits::empty(),
-                difference: Limits {
-                    lower: zero.clone(),
-                    upper: None,
-                },
-                plateau_size: Limits::empty(),
-                distance: Limits::empty(),
-                zero,
-            }
-        }
+    /// Get the last index of a peak (plateau) -- unlike `position.end`, this index is part of the
+    /// peak.
+    pub fn last_index(&self) -> usize {
+        self.position.end - 1
     }
-}
 
-impl<'a, T, S> PeakFinder<'a, T, S>
-where
-    T: Clone + std::ops::Sub<Output = T> + PartialOrd,
-    S: Clone + std::ops::Sub<Output = S> + PartialOrd,
-    [S]: ToOwned,
-{
-    pub fn new_with_x(y_data: &'a [T], x_data: &'a [S]) -> Self {
-        if y_data.is_empty() {
-            Self {
-                y_data,
-                x_data: Cow::from(x_data),
-                height: Limits::empty(),
-                prominence: Limits::empty(),
-                difference: Limits::empty(),
-                plateau_size: Limits::empty(),
-                distance: Limits::empty(),
-                zero: None,
-            }
-        } else {
-            let zero = Some(y_data[0].clone() - y_data[0].clone());
-            Self {
-                y_data,
-                x_data: Cow::from(x_data),
-                height: Limits::empty(),
-                prominence: Limits::empty(),
-                difference: Limits {
-                    lower: zero.clone(),
-                    upper: None,
-                },
-                plateau_size: Limits::empty(),
-                distance: Limits::empty(),
-                zero,
-            }
-        }
+    /// Whether index `i` is part of the peak (plateau).
+    pub fn contains(&self, i: usize) -> bool {
+        self.position.contains(&i)
     }
 
-    // TODO unsigned subtraction may fail
-    fn get_local_maxima(&self) -> impl Iterator<Item = Peak<T>> + '_ {
-        let zero = self.zero.clone().unwrap();
-
-        let mut it = self.y_data.iter().cloned().enumerate();
-        let (_i, zeroth) = it.next().unwrap();
-        let (_i, first) = it.next().unwrap();
-
-        let mut back_diff = first.clone() - zeroth;
-        let mut prev = first;
-
-        let limit = &self.difference;
-
-        let mut start: Option<usize> = None;
-
-        it.filter_map(move |(i, y)| {
-            let ahead_diff = prev.clone() - y.clone(); // positive for downward slope
-            let ahead_inside = limit.is_inside(&ahead_diff);
-            let back_inside = limit.is_inside(&back_diff);
-
-            let res = if back_inside && ahead_diff == zero {
-                if start.is_none() {
-                    start = Some(i - 1);
-                }
-                None
-            } else {
-                let r = if ahead_inside && back_inside {
-                    Some(Peak::new(
-                        start.unwrap_or(i - 1)..i,
-                        back_diff.clone(),
-                        ahead_diff.clone(),
-                    ))
-                } else {
-                    None
-                };
-
-                start = None;
-                back_diff = zero.clone() - ahead_diff;
-
-                r
-            };
-            prev = y.clone();
-
-            res
-        })
+    /// Whether the middle position of the peak is within `r`, see `peaks_in_range`.
+    pub fn in_range(&self, r: &Range<usize>) -> bool {
+        r.contains(&self.middle_position())
     }
 
-    fn filter_plateau<'b, I>(&'b self, peaks: I) -> impl Iterator<Item = Peak<T>> + 'b
+    /// The value of the peak (plateau) in `y_data` (the data the peak was found in),
+    /// `y_data[position.start]`. The local maxima span runs of equal samples, so every sample
+    /// of a plateau has this value; only merged peaks (see `PeakFinder::with_merge_distance` and
+    /// `PeakFinder::with_merge_plateau_tolerance`) span different samples, of which this is the
+    /// first one.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use find_peaks::PeakFinder;
+    /// let y = [0, 1, 3, 3, 3, 0];
+    ///
+    /// let ps = PeakFinder::new(&y).find_peaks();
+    ///
+    /// assert_eq!(ps[0].position, 2..5);
+    /// assert_eq!(ps[0].plateau_value(&y), 3);
+    /// ```
+    pub fn plateau_value(&self, y_data: &[T]) -> T
     where
-        I: Iterator<Item = Peak<T>> + 'b,
+        T: Clone,
     {
-        let limit = &self.plateau_size;
-        let empty = limit.is_empty();
-
-        peaks.filter_map(move |p| {
-            if empty {
-                // do nothing
-                Some(p)
-            } else if limit.is_inside(&p.position.len()) {
-                Some(p)
-            } else {
-                None
-            }
-        })
+        y_data[self.position.start].clone()
     }
 
-    fn filter_height<'b, I>(&'b self, peaks: I) -> impl Iterator<Item = Peak<T>> + 'b
-    where
-        I: Iterator<Item = Peak<T>> + 'b,
-    {
-        let limit = &self.height;
-        let empty = limit.is_empty();
-
-        peaks.filter_map(move |mut p| {
-            if empty {
-                // do nothing
-                Some(p)
-            } else {
-                let y = self.y_data[p.position.start].clone();
+    /// The complex sample at the middle position of a peak found by
+    /// `PeakFinder::from_complex` in `data` (requires the `num-complex` feature). The samples
+    /// of a plateau have the same magnitude but may differ in phase.
+    #[cfg(feature = "num-complex")]
+    pub fn complex_value<'d>(
+        &self,
+        data: &'d [num_complex::Complex<T>],
+    ) -> &'d num_complex::Complex<T> {
+        &data[self.middle_position() % data.len()]
+    }
 
-                if limit.is_inside(&y) {
-                    p.add_height(y);
-                    Some(p)
-                } else {
-                    None
-                }
-            }
-        })
+    /// The samples of the peak (plateau) in `y_data` (the data the peak was found in),
+    /// `&y_data[position.clone()]`. `position` is half-open, so its `end` is the first sample
+    /// after the plateau, not the last one of it. For merged peaks the slice spans the merged
+    /// samples and the valleys between them.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use find_peaks::PeakFinder;
+    /// let y = [1, 2, 3, 3, 3, 0];
+    ///
+    /// let ps = PeakFinder::new(&y).find_peaks();
+    ///
+    /// assert_eq!(ps[0].position, 2..5);
+    /// assert_eq!(ps[0].plateau_slice(&y), &[3, 3, 3]);
+    /// ```
+    pub fn plateau_slice<'d>(&self, y_data: &'d [T]) -> &'d [T] {
+        &y_data[self.position.clone()]
     }
 
-    fn filter_prominence<'b, I>(&'b self, peaks: I) -> impl Iterator<Item = Peak<T>> + 'b
+    /// The middle position and the height of the peak, `(middle_position(), height)`, as for
+    /// plotting. The height is `None` unless it was computed (see `Peak`).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use find_peaks::PeakFinder;
+    /// let y = [1., 2., 3., 0., 5., 0.];
+    ///
+    /// let ps = PeakFinder::new(&y).find_peaks();
+    ///
+    /// assert_eq!(ps[0].as_tuple(), (4, Some(5.)));
+    /// let points: Vec<(usize, Option<f64>)> = ps.into_iter().map(Into::into).collect();
+    /// assert_eq!(points, vec![(4, Some(5.)), (2, Some(3.))]);
+    /// ```
+    pub fn as_tuple(&self) -> (usize, Option<T>)
     where
-        I: Iterator<Item = Peak<T>> + 'b,
+        T: Clone,
     {
-        let limit = &self.prominence;
-        let empty = limit.is_empty();
-
-        peaks.filter_map(move |mut p| {
-            if empty {
-                // do nothing
-                Some(p)
-            } else {
-                let prom = self.calc_prominence(&p);
-
-                if limit.is_inside(&prom) {
-                    p.add_prominence(prom);
-                    Some(p)
-                } else {
-                    None
-                }
-            }
-        })
+        (self.middle_position(), self.height.clone())
     }
+}
 
-    fn filter_distance(&self, mut peaks: Vec<Peak<T>>) -> Vec<Peak<T>> {
-        {
-            for p in &mut peaks {
-                let y = self.y_data[p.position.start].clone();
-                p.add_height(y);
-            }
-
-            peaks.sort_unstable_by(|a, b| {
-                b.height
-                    .partial_cmp(&a.height)
-                    .unwrap_or(std::cmp::Ordering::Equal)
-            });
-        }
-
-        let limit = &self.distance;
-        if limit.is_empty() {
-            return peaks;
-        }
-
-        let x_data = &self.x_data;
-
-        let mut filtered = Vec::with_capacity(peaks.len());
-
-        while peaks.len() > 1 {
-            filtered.push(peaks[0].clone());
-            let x_i = x_data[peaks[0].middle_position()].clone();
-
-            peaks = peaks[1..]
-                .iter()
-                .filter(|&p| {
-                    let x = x_data[p.middle_position()].clone();
-
-                    // done without abs because of trait bounds
-                    let dist = if x_i > x {
-                        x_i.clone() - x.clone()
-                    } else {
-                        x.clone() - x_i.clone()
-                    };
-
-                    limit.is_inside(&dist)
-                })
-                .cloned()
-                .collect();
-        }
-        filtered.extend(peaks);
-
-        filtered.shrink_to_fit();
-        filtered
+impl<T> From<Peak<T>> for (usize, Option<T>) {
+    /// `(middle_position(), height)`, see `Peak::as_tuple`.
+    fn from(peak: Peak<T>) -> Self {
+        (peak.middle_position(), peak.height)
     }
+}
 
-    fn calc_prominence(&self, p: &Peak<T>) -> T {
-        let i_left = p.position.start;
-        let i_right = p.position.end - 1;
-
-        let data = &self.y_data;
-
-        //debug_assert_eq!(data[i_right], data[i_left]);
-
-        let from_peak_right = data.iter().skip(i_right + 1);
-        let from_peak_left = data.iter().rev().skip(data.len() - i_left);
-
-        let left_valley_y = from_peak_left
-            .take_while(|&x| x <= &data[i_left])
-            .min_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
-        let right_valley_y = from_peak_right
-            .take_while(|&x| x <= &data[i_left])
-            .min_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
+impl<T: Clone + Into<f64>> Peak<T> {
+    /// Intensity weighted center of the peak span, `sum(i * y[i]) / sum(y[i])` over the indices
+    /// `i` of `position` in `y_data` (the data the peak was found in). This is a better line
+    /// center than `middle_position` for spans of different samples, e.g. merged peaks (see
+    /// `PeakFinder::with_merge_distance`).
+    ///
+    /// Falls back to the geometric center if all the samples are equal (as on a plateau) or
+    /// their sum is zero.
+    pub fn centroid(&self, y_data: &[T]) -> f64 {
+        let x: Vec<f64> = self.position.clone().map(|i| i as f64).collect();
+        self.weighted_center(y_data, &x)
+    }
 
-        let peak_height = data[i_left].clone();
-        match (left_valley_y, right_valley_y) {
-            (None, None) => self.zero.clone().unwrap(),
-            (Some(v), None) => peak_height - v.clone(),
-            (None, Some(v)) => peak_height - v.clone(),
-            (Some(v1), Some(v2)) => peak_height - (if v1.ge(v2) { v1 } else { v2 }).clone(),
-        }
-        .clone()
+    /// Like `centroid`, but the center is the weighted mean of the x-values `x_data[i]`.
+    pub fn centroid_x<S: AsF64>(&self, y_data: &[T], x_data: &[S]) -> f64 {
+        let x: Vec<f64> = x_data[self.position.clone()]
+            .iter()
+            .map(S::as_f64)
+            .collect();
+        self.weighted_center(y_data, &x)
     }
 
-    /// Outputs a vector of `Peak<_>` structures containing peaks that matched the criteria
-    /// specified in `PeakFinder<_>`.
-    ///
-    /// Output will **not** contain some properties (for example, height, prominence) unless you
-    /// specified at least on of the corresponding bounds in `PeakFinder<_>` -- the calculation of
-    /// the property is skipped.
+    /// The x-values where the data descends through the absolute `level` on both sides of the
+    /// peak, linearly interpolated between the samples of `y_data` and `x_data` (the data the
+    /// peak was found in) around the crossings. A side that does not come down to `level`
+    /// before the end of the data is clamped to the x-value of the boundary sample.
     ///
-    /// Peaks are sorted by their height.
+    /// `None` if the peak is lower than `level`.
     ///
     /// # Examples
     ///
     /// ```
     /// use find_peaks::PeakFinder;
-    /// let y = [1., 2., 3., 0., 5., 0.];
+    /// let x = [0., 1., 2., 3., 4.];
+    /// let y = [0., 2., 4., 3., 3.];
     ///
-    /// let ps = PeakFinder::new(&y)
-    ///            .with_min_height(0.)
-    ///            .with_min_prominence(1.)
-    ///            .find_peaks();
+    /// let ps = PeakFinder::new_with_x(&y, &x).find_peaks();
     ///
-    /// assert_eq!(
-    ///    ps.iter().map(|x| x.middle_position()).collect::<Vec<_>>(),
-    ///    vec![4, 2]
-    /// );
+    /// assert_eq!(ps[0].width_at_height(&y, &x, 3.), Some((1.5, 3.)));
+    /// // the right side does not come down to 2.
+    /// assert_eq!(ps[0].width_at_height(&y, &x, 2.), Some((1., 4.)));
+    /// assert_eq!(ps[0].width_at_height(&y, &x, 5.), None);
     /// ```
-    pub fn find_peaks(&self) -> Vec<Peak<T>> {
-        // there can be no peaks with less than 3 data points
-        if [0, 1, 2].contains(&self.y_data.len()) {
-            return Vec::new();
+    pub fn width_at_height<S: AsF64>(
+        &self,
+        y_data: &[T],
+        x_data: &[S],
+        level: T,
+    ) -> Option<(f64, f64)> {
+        let level = to_f64(&level);
+        let y = |i: usize| to_f64(&y_data[i]);
+        if y(self.position.start) < level {
+            return None;
         }
 
-        let it = self
-            .filter_prominence(self.filter_height(self.filter_plateau(self.get_local_maxima())));
+        // x of the level on the line from sample `below` (at most `level`) to `above`
+        let cross = |below: usize, above: usize| {
+            let (xb, xa) = (x_data[below].as_f64(), x_data[above].as_f64());
+            if y(above) == y(below) {
+                xa
+            } else {
+                xb + (level - y(below)) / (y(above) - y(below)) * (xa - xb)
+            }
+        };
 
-        let peaks: Vec<Peak<T>> = it.collect();
+        let left = match (0..self.position.start).rev().find(|&i| y(i) <= level) {
+            Some(l) => cross(l, l + 1),
+            None => x_data[0].as_f64(),
+        };
+        let right = match (self.position.end..y_data.len()).find(|&i| y(i) <= level) {
+            Some(r) => cross(r, r - 1),
+            None => x_data[y_data.len() - 1].as_f64(),
+        };
+        Some((left, right))
+    }
 
-        if peaks.is_empty() {
-            peaks
+    fn weighted_center(&self, y_data: &[T], x: &[f64]) -> f64 {
+        let y: Vec<f64> = y_data[self.position.clone()].iter().map(to_f64).collect();
+        let total: f64 = y.iter().sum();
+
+        if total == 0. || y.iter().all(|&v| v == y[0]) {
+            (x[0] + x[x.len() - 1]) / 2.
         } else {
-            self.filter_distance(peaks)
+            x.iter().zip(&y).map(|(x, y)| x * y).sum::<f64>() / total
         }
     }
+}
 
-    pub fn with_min_height(&mut self, h: T) -> &mut Self {
-        self.height.lower = Some(h);
-        self
-    }
+/// Column names written by `write_peaks_csv`, see `Peak::write_csv_row`.
+const CSV_HEADER: &str = "start,end,center,height,prominence";
 
-    pub fn with_max_height(&mut self, h: T) -> &mut Self {
-        self.height.upper = Some(h);
-        self
-    }
+impl<T: Display> Peak<T> {
+    /// Write the peak as a CSV row: the start and (exclusive) end of `position`, the middle
+    /// position, the height and the prominence. Fields that were not computed are empty.
+    pub fn write_csv_row(&self, w: &mut impl Write) -> io::Result<()> {
+        let show = |v: &Option<T>| v.as_ref().map(|v| v.to_string()).unwrap_or_default();
 
-    pub fn with_min_prominence(&mut self, prominence: T) -> &mut Self {
-        let zero = prominence.clone() - prominence.clone();
-        assert!(zero.le(&prominence), "Prominence must be positive!");
+        writeln!(
+            w,
+            "{},{},{},{},{}",
+            self.position.start,
+            self.position.end,
+            self.middle_position(),
+            show(&self.height),
+            show(&self.prominence)
+        )
+    }
+}
 
-        self.prominence.lower = Some(prominence);
-        self
+/// Write `peaks` as CSV: a header row and one row per peak (see `Peak::write_csv_row`).
+///
+/// # Examples
+///
+/// ```
+/// use find_peaks::{write_peaks_csv, PeakFinder};
+/// let y = [1., 2., 3., 0., 5., 0.];
+///
+/// let ps = PeakFinder::new(&y).with_min_prominence(1.).find_peaks();
+///
+/// let mut out = Vec::new();
+/// write_peaks_csv(&ps, &mut out).unwrap();
+/// assert_eq!(
+///     String::from_utf8(out).unwrap(),
+///     "start,end,center,height,prominence\n4,5,4,5,5\n2,3,2,3,2\n"
+/// );
+/// ```
+pub fn write_peaks_csv<T: Display>(peaks: &[Peak<T>], w: &mut impl Write) -> io::Result<()> {
+    writeln!(w, "{}", CSV_HEADER)?;
+    for p in peaks {
+        p.write_csv_row(w)?;
     }
+    Ok(())
+}
 
-    pub fn with_max_prominence(&mut self, prominence: T) -> &mut Self {
-        let zero = prominence.clone() - prominence.clone();
-        assert!(zero.le(&prominence), "Prominence must be positive!");
+/// Peaks as parallel vectors, one per field of `Peak`, for columnar processing of many peaks.
+/// `centers` are the middle positions, the other vectors are the fields of the same name.
+///
+/// # Examples
+///
+/// ```
+/// use find_peaks::{Peak, PeakColumns, PeakFinder};
+/// let y = [1., 2., 3., 0., 5., 0.];
+///
+/// let ps = PeakFinder::new(&y).find_peaks();
+/// let columns = PeakColumns::from(ps.clone());
+///
+/// assert_eq!(columns.centers, vec![4, 2]);
+/// assert_eq!(columns.heights, vec![Some(5.), Some(3.)]);
+/// assert_eq!(Vec::<Peak<f64>>::from(columns), ps);
+/// ```
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct PeakColumns<T> {
+    pub positions: Vec<Range<usize>>,
+    pub centers: Vec<usize>,
+    pub left_diffs: Vec<T>,
+    pub right_diffs: Vec<T>,
+    pub heights: Vec<Option<T>>,
+    pub prominences: Vec<Option<T>>,
+    pub valley_indices: Vec<Option<usize>>,
+    pub valley_values: Vec<Option<T>>,
+    pub left_prominences: Vec<Option<T>>,
+    pub right_prominences: Vec<Option<T>>,
+    pub symmetries: Vec<Option<f64>>,
+    pub left_widths: Vec<Option<f64>>,
+    pub right_widths: Vec<Option<f64>>,
+    pub sharpness: Vec<Option<f64>>,
+    pub labels: Vec<Option<String>>,
+    pub is_shoulder: Vec<bool>,
+    pub clipped: Vec<bool>,
+    pub bounded_by_edge: Vec<bool>,
+    pub prominence_capped: Vec<bool>,
+    pub scores: Vec<Option<f64>>,
+    pub ranks: Vec<Option<usize>>,
+}
 
-        self.prominence.upper = Some(prominence);
-        self
+impl<T> PeakColumns<T> {
+    /// Number of peaks.
+    pub fn len(&self) -> usize {
+        self.positions.len()
     }
 
-    pub fn with_min_difference(&mut self, difference: T) -> &mut Self {
-        let zero = difference.clone() - difference.clone();
-        assert!(zero.le(&difference), "Difference must be positive!");
-
-        self.difference.lower = Some(difference);
-        self
+    pub fn is_empty(&self) -> bool {
+        self.positions.is_empty()
     }
+}
 
-    pub fn with_max_difference(&mut self, difference: T) -> &mut Self {
-        let zero = difference.clone() - difference.clone();
-        assert!(zero.le(&difference), "Difference must be positive!");
+impl<T> From<Vec<Peak<T>>> for PeakColumns<T> {
+    fn from(peaks: Vec<Peak<T>>) -> Self {
+        let n = peaks.len();
+        let mut columns = PeakColumns {
+            positions: Vec::with_capacity(n),
+            centers: Vec::with_capacity(n),
+            left_diffs: Vec::with_capacity(n),
+            right_diffs: Vec::with_capacity(n),
+            heights: Vec::with_capacity(n),
+            prominences: Vec::with_capacity(n),
+            valley_indices: Vec::with_capacity(n),
+            valley_values: Vec::with_capacity(n),
+            left_prominences: Vec::with_capacity(n),
+            right_prominences: Vec::with_capacity(n),
+            symmetries: Vec::with_capacity(n),
+            left_widths: Vec::with_capacity(n),
+            right_widths: Vec::with_capacity(n),
+            sharpness: Vec::with_capacity(n),
+            labels: Vec::with_capacity(n),
+            is_shoulder: Vec::with_capacity(n),
+            clipped: Vec::with_capacity(n),
+            bounded_by_edge: Vec::with_capacity(n),
+            prominence_capped: Vec::with_capacity(n),
+            scores: Vec::with_capacity(n),
+            ranks: Vec::with_capacity(n),
+        };
 
-        self.difference.upper = Some(difference);
-        self
+        for p in peaks {
+            columns.centers.push(p.middle_position());
+            columns.positions.push(p.position);
+            columns.left_diffs.push(p.left_diff);
+            columns.right_diffs.push(p.right_diff);
+            columns.heights.push(p.height);
+            columns.prominences.push(p.prominence);
+            columns.valley_indices.push(p.valley_index);
+            columns.valley_values.push(p.valley_value);
+            columns.left_prominences.push(p.left_prominence);
+            columns.right_prominences.push(p.right_prominence);
+            columns.symmetries.push(p.symmetry);
+            columns.left_widths.push(p.left_width);
+            columns.right_widths.push(p.right_width);
+            columns.sharpness.push(p.sharpness);
+            columns.labels.push(p.label);
+            columns.is_shoulder.push(p.is_shoulder);
+            columns.clipped.push(p.clipped);
+            columns.bounded_by_edge.push(p.bounded_by_edge);
+            columns.prominence_capped.push(p.prominence_capped);
+            columns.scores.push(p.score);
+            columns.ranks.push(p.rank);
+        }
+        columns
     }
+}
 
-    pub fn with_min_plateau_size(&mut self, size: usize) -> &mut Self {
-        self.plateau_size.lower = Some(size);
-        self
-    }
+impl<T> From<PeakColumns<T>> for Vec<Peak<T>> {
+    /// The `centers` are not read, they follow from the positions. The columns have to be of
+    /// the same length.
+    fn from(columns: PeakColumns<T>) -> Self {
+        let n = columns.len();
+        let lengths = [
+            columns.left_diffs.len(),
+            columns.right_diffs.len(),
+            columns.heights.len(),
+            columns.prominences.len(),
+            columns.valley_indices.len(),
+            columns.valley_values.len(),
+            columns.left_prominences.len(),
+            columns.right_prominences.len(),
+            columns.symmetries.len(),
+            columns.left_widths.len(),
+            columns.right_widths.len(),
+            columns.sharpness.len(),
+            columns.labels.len(),
+            columns.is_shoulder.len(),
+            columns.clipped.len(),
+            columns.bounded_by_edge.len(),
+            columns.prominence_capped.len(),
+            columns.scores.len(),
+            columns.ranks.len(),
+        ];
+        assert!(
+            lengths.iter().all(|&len| len == n),
+            "The columns must be of the same length!"
+        );
 
-    pub fn with_max_plateau_size(&mut self, size: usize) -> &mut Self {
-        self.plateau_size.upper = Some(size);
-        self
+        let mut left_diffs = columns.left_diffs.into_iter();
+        let mut right_diffs = columns.right_diffs.into_iter();
+        let mut heights = columns.heights.into_iter();
+        let mut prominences = columns.prominences.into_iter();
+        let mut valley_indices = columns.valley_indices.into_iter();
+        let mut valley_values = columns.valley_values.into_iter();
+        let mut left_prominences = columns.left_prominences.into_iter();
+        let mut right_prominences = columns.right_prominences.into_iter();
+        let mut symmetries = columns.symmetries.into_iter();
+        let mut left_widths = columns.left_widths.into_iter();
+        let mut right_widths = columns.right_widths.into_iter();
+        let mut sharpness = columns.sharpness.into_iter();
+        let mut labels = columns.labels.into_iter();
+        let mut is_shoulder = columns.is_shoulder.into_iter();
+        let mut clipped = columns.clipped.into_iter();
+        let mut bounded_by_edge = columns.bounded_by_edge.into_iter();
+        let mut prominence_capped = columns.prominence_capped.into_iter();
+        let mut scores = columns.scores.into_iter();
+        let mut ranks = columns.ranks.into_iter();
+
+        // all the columns have `n` elements
+        columns
+            .positions
+            .into_iter()
+            .map(|position| Peak {
+                position,
+                left_diff: left_diffs.next().unwrap(),
+                right_diff: right_diffs.next().unwrap(),
+                height: heights.next().unwrap(),
+                prominence: prominences.next().unwrap(),
+                valley_index: valley_indices.next().unwrap(),
+                valley_value: valley_values.next().unwrap(),
+                left_prominence: left_prominences.next().unwrap(),
+                right_prominence: right_prominences.next().unwrap(),
+                symmetry: symmetries.next().unwrap(),
+                left_width: left_widths.next().unwrap(),
+                right_width: right_widths.next().unwrap(),
+                sharpness: sharpness.next().unwrap(),
+                label: labels.next().unwrap(),
+                is_shoulder: is_shoulder.next().unwrap(),
+                clipped: clipped.next().unwrap(),
+                bounded_by_edge: bounded_by_edge.next().unwrap(),
+                prominence_capped: prominence_capped.next().unwrap(),
+                score: scores.next().unwrap(),
+                rank: ranks.next().unwrap(),
+            })
+            .collect()
     }
+}
 
-    pub fn with_min_distance(&mut self, distance: S) -> &mut Self {
-        let zero = distance.clone() - distance.clone();
-        assert!(zero.le(&distance), "Distance must be positive!");
+/// Peaks of `y` with a prominence of at least `min_prominence`, a shortcut for
+/// `PeakFinder::new(y).with_min_prominence(min_prominence).find_peaks()`.
+///
+/// # Examples
+///
+/// ```
+/// use find_peaks::find_peaks_by_prominence;
+/// let y = [1., 2., 3., 0., 5., 0.];
+///
+/// let ps = find_peaks_by_prominence(&y, 3.);
+///
+/// assert_eq!(ps.len(), 1);
+/// assert_eq!(ps[0].prominence, Some(5.));
+/// ```
+pub fn find_peaks_by_prominence<T>(y: &[T], min_prominence: T) -> Vec<Peak<T>>
+where
+    T: Clone + std::ops::Sub<Output = T> + PartialOrd,
+{
+    PeakFinder::new(y)
+        .with_min_prominence(min_prominence)
+        .find_peaks()
+}
 
-        self.distance.lower = Some(distance);
+/// Peaks of `y` with a height of at least `min_height`, a shortcut for
+/// `PeakFinder::new(y).with_min_height(min_height).find_peaks()`.
+pub fn find_peaks_by_height<T>(y: &[T], min_height: T) -> Vec<Peak<T>>
+where
+    T: Clone + std::ops::Sub<Output = T> + PartialOrd,
+{
+    PeakFinder::new(y).with_min_height(min_height).find_peaks()
+}
+
+/// Peak finding as a method of the data, for one-off calls.
+///
+/// The trait has to be imported, so its methods do not collide with others of the same name.
+///
+/// # Examples
+///
+/// ```
+/// use find_peaks::{PeakExt, PeakFinder};
+/// let y = [1., 2., 3., 0., 5., 0.];
+///
+/// assert_eq!(y.peaks(4.).len(), 1);
+///
+/// let config = PeakFinder::new(&y).with_min_height(2.5).config();
+/// assert_eq!(y.peaks_with(&config).len(), 2);
+/// ```
+pub trait PeakExt<T> {
+    /// The peaks with a prominence of at least `min_prominence`, like
+    /// `find_peaks_by_prominence`.
+    fn peaks(&self, min_prominence: T) -> Vec<Peak<T>>;
+
+    /// The peaks found with the settings of `config`.
+    fn peaks_with(&self, config: &PeakConfig<T, usize>) -> Vec<Peak<T>>;
+}
+
+impl<T> PeakExt<T> for [T]
+where
+    T: Clone + std::ops::Sub<Output = T> + PartialOrd,
+{
+    fn peaks(&self, min_prominence: T) -> Vec<Peak<T>> {
+        find_peaks_by_prominence(self, min_prominence)
+    }
+
+    fn peaks_with(&self, config: &PeakConfig<T, usize>) -> Vec<Peak<T>> {
+        PeakFinder::with_config(self, config.clone()).find_peaks()
+    }
+}
+
+/// The peaks of each of `signals` found with the settings of `config`, computed in parallel on
+/// the rayon thread pool. The result is in the order of `signals`.
+///
+/// # Examples
+///
+/// ```
+/// use find_peaks::{find_peaks_batch, PeakFinder};
+/// let y1 = [1., 2., 3., 0., 5., 0.];
+/// let y2 = [0., 4., 1., 2., 0.];
+///
+/// let config = PeakFinder::new(&y1).with_min_prominence(1.5).config();
+/// let ps = find_peaks_batch(&[&y1, &y2], &config);
+///
+/// assert_eq!(ps.iter().map(|p| p.len()).collect::<Vec<_>>(), vec![2, 1]);
+/// ```
+#[cfg(feature = "rayon")]
+pub fn find_peaks_batch<T>(signals: &[&[T]], config: &PeakConfig<T, usize>) -> Vec<Vec<Peak<T>>>
+where
+    T: Clone + std::ops::Sub<Output = T> + PartialOrd + Send + Sync,
+{
+    use rayon::prelude::*;
+
+    signals
+        .par_iter()
+        .map(|y| PeakFinder::with_config(y, config.clone()).find_peaks())
+        .collect()
+}
+
+/// The peak of `peaks` whose middle position is closest to the index `target`; of two equally
+/// close peaks the one with the greater height (a computed height beats `None`). `None` if
+/// `peaks` is empty.
+///
+/// The peaks need not be sorted, they are scanned once.
+///
+/// # Examples
+///
+/// ```
+/// use find_peaks::{nearest_peak, PeakFinder};
+/// let y = [1., 2., 3., 0., 5., 0.];
+///
+/// let ps = PeakFinder::new(&y).find_peaks();
+///
+/// assert_eq!(nearest_peak(&ps, 1).unwrap().middle_position(), 2);
+/// // equally close to both, the higher one wins
+/// assert_eq!(nearest_peak(&ps, 3).unwrap().middle_position(), 4);
+/// ```
+pub fn nearest_peak<T: PartialOrd>(peaks: &[Peak<T>], target: usize) -> Option<&Peak<T>> {
+    nearest_by(
+        peaks,
+        |p| p.middle_position().abs_diff(target),
+        |p| p.height.as_ref(),
+    )
+}
+
+/// The peaks of `peaks` whose middle position is within `r` (see `Peak::in_range`), e.g. the
+/// peaks shown when zooming into the samples `r`. The peaks have to be sorted by position (see
+/// `SortOrder::Position`); the ends of the range are found by binary search, in `O(log n)`.
+///
+/// # Examples
+///
+/// ```
+/// use find_peaks::{peaks_in_range, PeakFinder, SortOrder};
+/// let y = [0., 1., 0., 2., 2., 0., 3., 0., 4., 0.];
+///
+/// let ps = PeakFinder::new(&y).with_sort_order(SortOrder::Position).find_peaks();
+///
+/// // the middle position of the plateau at 3..5 is 4
+/// let middles: Vec<usize> = peaks_in_range(&ps, &(4..8)).map(|p| p.middle_position()).collect();
+/// assert_eq!(middles, vec![4, 6]);
+/// ```
+pub fn peaks_in_range<'p, T>(
+    peaks: &'p [Peak<T>],
+    r: &Range<usize>,
+) -> impl Iterator<Item = &'p Peak<T>> {
+    debug_assert!(
+        peaks
+            .windows(2)
+            .all(|w| w[0].position.start < w[1].position.start),
+        "The peaks must be sorted by position!"
+    );
+
+    let start = peaks.partition_point(|p| p.middle_position() < r.start);
+    let end = start + peaks[start..].partition_point(|p| p.middle_position() < r.end);
+    peaks[start..end].iter()
+}
+
+/// Re-locate `peaks` found on data decimated by `factor` (sample `j` of it being sample
+/// `j * factor` of `full_y`, as with `step_by(factor)`) in the full resolution data `full_y`.
+///
+/// Each peak is moved to the highest sample of `full_y` within `search_radius` samples of its
+/// upsampled middle position (the leftmost one of equally high samples), and its position
+/// covers the plateau of that sample within the window. The differences are taken to the
+/// samples just outside the position (zero at an edge of the data, or if the neighbour is
+/// higher, at the edge of the window) and the height is the sample; the prominence and the
+/// symmetry are not recomputed, the label is kept.
+///
+/// With a `factor` of `1` this snaps peaks found on a smoothed copy of the data to the
+/// maxima of the raw data, which removes the position bias of the smoothing.
+///
+/// # Examples
+///
+/// ```
+/// use find_peaks::{refine_positions, PeakFinder};
+/// let y = [0., 1., 2., 4., 3., 1., 0., 1., 0., 0.];
+/// let decimated: Vec<f64> = y.iter().step_by(2).copied().collect(); // [0., 2., 3., 0., 0.]
+///
+/// let ps = PeakFinder::new(&decimated).find_peaks();
+/// let refined = refine_positions(&y, &ps, 2, 1);
+///
+/// assert_eq!(refined[0].position, 3..4);
+/// assert_eq!(refined[0].height, Some(4.));
+/// ```
+///
+/// Detecting on smoothed data and locating on the raw data:
+///
+/// ```
+/// use find_peaks::{refine_positions, PeakFinder};
+/// let raw = [0., 1., 5., 2., 4., 1., 0.];
+/// let smoothed: Vec<f64> = (0..raw.len())
+///     .map(|i| raw[i.saturating_sub(1)..(i + 2).min(raw.len())].iter().sum::<f64>() / 3.)
+///     .collect();
+///
+/// let ps = PeakFinder::new(&smoothed).find_peaks();
+/// assert_eq!(ps[0].middle_position(), 3);
+///
+/// let refined = refine_positions(&raw, &ps, 1, 1);
+/// assert_eq!(refined[0].middle_position(), 2);
+/// assert_eq!(refined[0].height, Some(5.));
+/// ```
+pub fn refine_positions<T>(
+    full_y: &[T],
+    peaks: &[Peak<T>],
+    factor: usize,
+    search_radius: usize,
+) -> Vec<Peak<T>>
+where
+    T: Clone + std::ops::Sub<Output = T> + PartialOrd,
+{
+    assert!(factor > 0, "The decimation factor must be positive!");
+    if full_y.is_empty() {
+        return Vec::new();
+    }
+    let last = full_y.len() - 1;
+
+    peaks
+        .iter()
+        .map(|p| {
+            let center = (p.middle_position() * factor).min(last);
+            let window =
+                center.saturating_sub(search_radius)..(center + search_radius).min(last) + 1;
+
+            let top = window.clone().fold(
+                window.start,
+                |m, i| if full_y[i] > full_y[m] { i } else { m },
+            );
+            let y = &full_y[top];
+            let start = (window.start..top)
+                .rev()
+                .take_while(|&i| &full_y[i] == y)
+                .last()
+                .unwrap_or(top);
+            let end = (top..window.end).take_while(|&i| &full_y[i] == y).count() + top;
+
+            let diff = |neighbour: Option<usize>| match neighbour {
+                Some(i) if &full_y[i] <= y => y.clone() - full_y[i].clone(),
+                _ => y.clone() - y.clone(),
+            };
+
+            Peak {
+                height: Some(y.clone()),
+                label: p.label.clone(),
+                ..Peak::new(
+                    start..end,
+                    diff(start.checked_sub(1)),
+                    diff(Some(end).filter(|&i| i <= last)),
+                )
+            }
+        })
+        .collect()
+}
+
+/// Pair `peaks` found on data with the x-values `old_x` with their centers on the parallel axis
+/// `new_x`, e.g. a calibrated one. The center of a peak is the midpoint of its position, so the
+/// value of `new_x` is interpolated halfway between two samples for a plateau of even length.
+/// The peaks are cloned unchanged and their positions still index the data.
+///
+/// # Examples
+///
+/// ```
+/// use find_peaks::{remap_positions, PeakFinder};
+/// let y = [0., 2., 2., 0., 3., 0.];
+/// let old_x = [0., 1., 2., 3., 4., 5.];
+/// let new_x = [400., 410., 430., 460., 500., 550.];
+///
+/// let ps = PeakFinder::new_with_x(&y, &old_x).find_peaks();
+/// let remapped = remap_positions(&ps, &old_x, &new_x);
+///
+/// assert_eq!(remapped[0].0, 500.);
+/// assert_eq!(remapped[1].0, 420.);
+/// assert_eq!(remapped[1].1.position, 1..3);
+/// ```
+pub fn remap_positions<T: Clone, S: AsF64>(
+    peaks: &[Peak<T>],
+    old_x: &[S],
+    new_x: &[S],
+) -> Vec<(f64, Peak<T>)> {
+    assert_eq!(
+        old_x.len(),
+        new_x.len(),
+        "The x-axes must be of the same length!"
+    );
+
+    peaks
+        .iter()
+        .map(|p| {
+            let (first, last) = (p.position.start, p.position.end - 1);
+            let center = if (last - first) % 2 == 0 {
+                new_x[(first + last) / 2].as_f64()
+            } else {
+                let lo = (first + last) / 2;
+                (new_x[lo].as_f64() + new_x[lo + 1].as_f64()) / 2.
+            };
+            (center, p.clone())
+        })
+        .collect()
+}
+
+/// The element of `peaks` with the smallest `distance`, ties going to the greatest `height`.
+fn nearest_by<'p, T, D, H, FD, FH>(
+    peaks: &'p [Peak<T>],
+    distance: FD,
+    height: FH,
+) -> Option<&'p Peak<T>>
+where
+    D: PartialOrd,
+    H: PartialOrd,
+    FD: Fn(&Peak<T>) -> D,
+    FH: Fn(&'p Peak<T>) -> H,
+{
+    let mut best: Option<(&Peak<T>, D)> = None;
+    for p in peaks {
+        let d = distance(p);
+        let better = match &best {
+            None => true,
+            Some((b, bd)) => d < *bd || (d == *bd && height(p) > height(b)),
+        };
+        if better {
+            best = Some((p, d));
+        }
+    }
+    best.map(|(p, _)| p)
+}
+
+/// Index chosen as the center of a plateau by `Peak::center`. All of them agree for an odd
+/// plateau size except `Left` and `Right`.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum PlateauCenter {
+    /// the left one of the two middle indices of an even plateau (as scipy's `find_peaks`)
+    Floor,
+    /// the right one of the two middle indices of an even plateau (as `Peak::middle_position`)
+    Ceil,
+    /// the first index of the plateau
+    Left,
+    /// the last index of the plateau
+    Right,
+}
+
+/// Rough shape of a peak, see `PeakFinder::classify`.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum PeakShape {
+    Sharp,
+    Broad,
+    /// a shoulder on the flank of another peak, see `PeakFinder::find_shoulders`
+    Shoulder,
+    Noise,
+}
+
+/// Thresholds of `PeakFinder::classify`.
+#[derive(Debug, PartialEq, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ClassificationThresholds {
+    /// a peak with a prominence below this fraction of its (absolute) height is noise
+    /// (default: `0.05`)
+    pub noise_prominence_ratio: f64,
+    /// a peak at most this wide at half prominence, in samples, is sharp (default: `5.`)
+    pub max_sharp_width: f64,
+}
+
+impl Default for ClassificationThresholds {
+    fn default() -> Self {
+        Self {
+            noise_prominence_ratio: 0.05,
+            max_sharp_width: 5.,
+        }
+    }
+}
+
+/// Weights of the normalized peak properties in `Peak::score`, see `PeakFinder::with_score`.
+#[derive(Debug, PartialEq, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ScoreWeights {
+    /// weight of the height above the minimum of the data (default: `1.`)
+    pub height: f64,
+    /// weight of the prominence (default: `1.`)
+    pub prominence: f64,
+    /// weight of the inverse width at half prominence (default: `1.`)
+    pub sharpness: f64,
+}
+
+impl Default for ScoreWeights {
+    fn default() -> Self {
+        Self {
+            height: 1.,
+            prominence: 1.,
+            sharpness: 1.,
+        }
+    }
+}
+
+/// Order of the peaks returned by `PeakFinder`, see `PeakFinder::with_sort_order`.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum SortOrder {
+    /// the highest peak first
+    Height,
+    /// the leftmost peak first, as returned by scipy's `find_peaks`
+    Position,
+}
+
+/// Peak property deciding which of two peaks too close to each other is kept, see
+/// `PeakFinder::with_distance_priority`.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum DistancePriority {
+    /// the higher peak is kept, as in scipy
+    Height,
+    /// the more prominent peak is kept
+    Prominence,
+}
+
+/// Order of equally high peaks, see `PeakFinder::with_tie_break`.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum TieBreak {
+    /// the leftmost peak first
+    LeftmostFirst,
+    /// the rightmost peak first
+    RightmostFirst,
+}
+
+/// Direction the data are scanned in, see `PeakFinder::with_scan_direction`.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Direction {
+    /// from the first sample to the last one
+    Forward,
+    /// from the last sample to the first one
+    Reverse,
+}
+
+/// Level the peak heights are measured from, see `PeakFinder::with_height_reference`.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum HeightRef {
+    /// the sample value itself
+    Absolute,
+    /// above the lowest sample of the data
+    AboveGlobalMin,
+    /// above the mean of the data
+    AboveMean,
+}
+
+/// Whether an `Extremum` is a local maximum or a local minimum.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum ExtremumKind {
+    Maximum,
+    Minimum,
+}
+
+/// Direction of the data on one side of a peak, see `PeakFinder::slopes`.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Slope {
+    Rising,
+    Flat,
+    Falling,
+}
+
+/// A local maximum or minimum, see `PeakFinder::find_extrema`.
+///
+/// For a minimum, `left_diff` and `right_diff` of `peak` are the (absolute) differences to the
+/// higher neighbours.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Extremum<T> {
+    pub peak: Peak<T>,
+    pub kind: ExtremumKind,
+}
+
+/// Number of peaks left after each stage of `PeakFinder::find_peaks_with_stats`, in the order
+/// of the stages. A stage without its setting removes nothing. More stages may be added.
+#[derive(Debug, PartialEq, Eq, Clone, Default)]
+#[non_exhaustive]
+pub struct DetectionStats {
+    /// local maxima (and edge peaks if enabled) with any neighbour differences
+    pub local_maxima: usize,
+    /// the candidates within the difference bounds, after plateaus are merged and split
+    pub after_difference: usize,
+    /// see `PeakFinder::with_exclusion_mask`
+    pub after_mask: usize,
+    /// see `PeakFinder::with_side_thresholds`, `PeakFinder::with_left_difference_range` and
+    /// `PeakFinder::with_min_difference_relative`
+    pub after_sides: usize,
+    /// see `PeakFinder::with_min_rise_run`
+    pub after_runs: usize,
+    pub after_plateau_size: usize,
+    /// see `PeakFinder::with_x_range`
+    pub after_x_range: usize,
+    pub after_width_x: usize,
+    /// see `PeakFinder::with_local_window`
+    pub after_local_window: usize,
+    /// see `PeakFinder::with_min_local_contrast`
+    pub after_local_contrast: usize,
+    /// see `PeakFinder::with_drop_clipped`
+    pub after_clipped: usize,
+    /// see `PeakFinder::with_apex_height_range`
+    pub after_apex_height: usize,
+    pub after_height: usize,
+    pub after_prominence: usize,
+    /// see `PeakFinder::with_min_prominence_ratio`
+    pub after_prominence_ratio: usize,
+    /// after the distance bounds, which come before the prominence bounds with
+    /// `PeakFinder::with_distance_first`
+    pub after_distance: usize,
+    /// the peaks removed by the distance bounds themselves, not merged by
+    /// `PeakFinder::with_merge_distance` or dropped by a `PeakFinder::with_filter` filter: zero
+    /// if the distance bounds changed nothing
+    pub suppressed_by_distance: usize,
+    /// after a filter run after the distance bounds, see `PeakFinder::with_filter`
+    pub after_filter: usize,
+    /// the number of returned peaks, see `PeakFinder::with_max_peaks`
+    pub after_max_peaks: usize,
+}
+
+/// Highest and lowest sample of the data, see `PeakFinder::find_peaks_with_extrema`.
+#[derive(Debug, PartialEq, Clone)]
+pub struct GlobalExtrema<T> {
+    /// index of the first highest sample
+    pub max_index: usize,
+    pub max: T,
+    /// index of the first lowest sample
+    pub min_index: usize,
+    pub min: T,
+}
+
+/// The global extrema of `values`, tracked by the scans of `PeakFinder::run` as they visit
+/// the samples, see `PeakFinder::find_peaks_with_extrema`.
+struct ExtremaScan<'v, T> {
+    values: &'v [T],
+    extrema: Option<GlobalExtrema<T>>,
+}
+
+impl<'v, T: PartialOrd + Clone> ExtremaScan<'v, T> {
+    fn new(values: &'v [T]) -> Self {
+        ExtremaScan {
+            values,
+            extrema: None,
+        }
+    }
+
+    /// Take the sample `i` into account; of equal samples the first one is kept, so the samples
+    /// may be visited in any order (and more than once). Incomparable samples are skipped.
+    fn visit(&mut self, i: usize) {
+        let y = &self.values[i];
+        if y.partial_cmp(y).is_none() {
+            return;
+        }
+        match &mut self.extrema {
+            None => {
+                self.extrema = Some(GlobalExtrema {
+                    max_index: i,
+                    max: y.clone(),
+                    min_index: i,
+                    min: y.clone(),
+                })
+            }
+            Some(e) => {
+                if *y > e.max || (*y == e.max && i < e.max_index) {
+                    e.max_index = i;
+                    e.max = y.clone();
+                }
+                if *y < e.min || (*y == e.min && i < e.min_index) {
+                    e.min_index = i;
+                    e.min = y.clone();
+                }
+            }
+        }
+    }
+}
+
+/// Expected work of `PeakFinder::find_peaks` with the current settings, see
+/// `PeakFinder::estimate_cost`.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct CostEstimate {
+    /// number of local maxima (and edge peaks if enabled), the candidates the bounds filter
+    pub candidates: usize,
+    /// whether the distance bounds may compare every candidate with every higher kept one,
+    /// taking time quadratic in `candidates`
+    pub quadratic_risk: bool,
+}
+
+/// Inclusive lower and upper bound, `None` if not set.
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Limits<T> {
+    pub lower: Option<T>,
+    pub upper: Option<T>,
+}
+
+impl<T> Limits<T>
+where
+    T: PartialOrd,
+{
+    pub fn empty() -> Self {
+        Self {
+            lower: None,
+            upper: None,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.lower.is_none() && self.upper.is_none()
+    }
+
+    pub fn is_inside(&self, v: &T) -> bool {
+        (self.lower.is_none() || v.ge(self.lower.as_ref().unwrap()))
+            && (self.upper.is_none() || v.le(self.upper.as_ref().unwrap()))
+    }
+
+    /// Whether the lower bound is greater than the upper bound, so that nothing is inside.
+    pub fn is_inverted(&self) -> bool {
+        match (&self.lower, &self.upper) {
+            (Some(lower), Some(upper)) => lower > upper,
+            _ => false,
+        }
+    }
+}
+
+/// What the prominence of a peak is measured from, see `PeakFinder::with_prominence_reference`.
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ProminenceRef<T> {
+    /// the higher of the valleys on both sides of the peak (the topographic prominence)
+    LocalValleys,
+    /// the minimum of the data
+    GlobalMin,
+    /// a fixed floor; a peak below it has zero prominence
+    Fixed(T),
+}
+
+/// Errors of `PeakFinder::try_find_peaks`: invalid settings or data (see
+/// `PeakFinder::validate`) or too few peaks found, and of `PeakFinder::try_new_with_x` and
+/// `PeakFinder::try_new_with_reference`.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum PeakError {
+    /// the lower bound of the named property is greater than its upper bound
+    InvertedLimits(&'static str),
+    /// fewer peaks than required by `PeakFinder::with_min_peaks` were found
+    TooFewPeaks { found: usize, expected: usize },
+    /// the x-values are used for distances but are not monotonic, the first sample breaking the
+    /// order is at `index`
+    NonMonotonicX { index: usize },
+    /// an x-value used for distances is not comparable to itself (NaN), the first one is at
+    /// `index`
+    UnorderedX { index: usize },
+    /// the numbers of y-values and x-values differ
+    LengthMismatch { y: usize, x: usize },
+    /// the numbers of y-values and reference values differ
+    ReferenceLengthMismatch { y: usize, reference: usize },
+}
+
+impl std::fmt::Display for PeakError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PeakError::InvertedLimits(property) => write!(
+                f,
+                "The minimum {} is greater than the maximum {}!",
+                property, property
+            ),
+            PeakError::TooFewPeaks { found, expected } => write!(
+                f,
+                "Found {} peaks, but at least {} are required!",
+                found, expected
+            ),
+            PeakError::NonMonotonicX { index } => {
+                write!(f, "The x-values are not monotonic at index {}!", index)
+            }
+            PeakError::UnorderedX { index } => {
+                write!(f, "The x-value at index {} is not comparable (NaN)!", index)
+            }
+            PeakError::LengthMismatch { y, x } => {
+                write!(f, "There are {} y-values, but {} x-values!", y, x)
+            }
+            PeakError::ReferenceLengthMismatch { y, reference } => write!(
+                f,
+                "There are {} y-values, but {} reference values!",
+                y, reference
+            ),
+        }
+    }
+}
+
+impl std::error::Error for PeakError {}
+
+/// Snapshot of the settings of a `PeakFinder`, to reuse them for different data.
+///
+/// The settings computed from the data when they are set (`with_height_reference`,
+/// `with_auto_prominence`) or referring to it (`with_exclusion_mask`), `with_simd` and the
+/// closures (`with_labeler`, `with_filter`, `with_distance_metric`, `with_base_fn`) are not part
+/// of the config and have to be set on each finder. The settings computed in `f64` need the
+/// conversions of the finder the config was taken from, which are not serialized, see
+/// `with_f64_conversions`. More settings may be added, so a config can only be made by
+/// `PeakFinder::config` (or deserialized).
+///
+/// # Examples
+///
+/// ```
+/// use find_peaks::PeakFinder;
+/// let y = [1., 2., 3., 0., 5., 0.];
+/// let y2 = [0., 4., 1., 2., 0.];
+///
+/// let config = PeakFinder::new(&y).with_min_prominence(1.5).config();
+/// let ps = PeakFinder::with_config(&y2, config).find_peaks();
+///
+/// assert_eq!(ps.len(), 1);
+/// ```
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
+pub struct PeakConfig<T, S> {
+    pub height: Limits<T>,
+    pub prominence: Limits<T>,
+    /// the lower bound defaults to zero, which excludes nothing, see
+    /// `PeakFinder::with_min_difference`
+    pub difference: Limits<T>,
+    pub side_thresholds: (Option<T>, Option<T>),
+    pub left_difference: Limits<T>,
+    pub right_difference: Limits<T>,
+    pub min_runs: (Option<usize>, Option<usize>),
+    pub run_plateaus: bool,
+    pub plateau_size: Limits<usize>,
+    pub width_x: Limits<S>,
+    pub x_range: Limits<S>,
+    pub distance: Limits<S>,
+    pub edge_peaks: bool,
+    pub prominence_window: Option<usize>,
+    pub prominence_max_scan: Option<usize>,
+    pub local_baseline: Option<usize>,
+    pub prominence_reference: ProminenceRef<T>,
+    pub merge_distance: Option<S>,
+    pub plateau_tolerance: Option<T>,
+    pub hysteresis: Option<(T, T)>,
+    pub min_sample_gap: Option<usize>,
+    pub local_window: Option<usize>,
+    pub saturation_level: Option<T>,
+    pub drop_clipped: bool,
+    pub compute_all: bool,
+    pub rank: bool,
+    pub max_peaks: Option<usize>,
+    pub target_peak_count: Option<usize>,
+    pub expected_peaks: Option<usize>,
+    pub sort_order: SortOrder,
+    pub distance_priority: DistancePriority,
+    pub distance_first: bool,
+    pub tie_break: TieBreak,
+    pub direction: Direction,
+    pub abs: bool,
+    pub min_peaks: usize,
+    pub classification_thresholds: ClassificationThresholds,
+    pub split_plateaus: Option<f64>,
+    pub local_contrast: Option<(usize, f64)>,
+    pub min_relative_prominence: Option<f64>,
+    pub min_relative_difference: Option<f64>,
+    pub min_prominence_ratio: Option<f64>,
+    pub dog: Option<(f64, f64)>,
+    pub log_amplitude: Option<f64>,
+    pub resample: Option<usize>,
+    pub score_weights: Option<ScoreWeights>,
+    pub apex_height: Limits<f64>,
+    pub symmetry: bool,
+    pub widths: bool,
+    pub sharpness: bool,
+    pub min_distance_fraction: Option<f64>,
+    /// see `PeakFinder::with_circular`, the period is computed from the x-values of the finder
+    pub circular: bool,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    conversions: F64Conversions<T, S>,
+}
+
+impl<T, S> PeakConfig<T, S>
+where
+    T: Clone + Into<f64> + FromF64,
+    S: AsF64 + FromF64,
+{
+    /// Add the conversions to and from `f64` that the settings computed in `f64` need, e.g.
+    /// to a deserialized config. A config taken from a finder has those of the finder.
+    pub fn with_f64_conversions(mut self) -> Self {
+        self.conversions = F64Conversions {
+            as_f64: Some(to_f64),
+            from_f64: Some(T::from_f64),
+            x_as_f64: Some(S::as_f64),
+            x_from_f64: Some(S::from_f64),
+        };
         self
     }
+}
 
-    pub fn with_max_distance(&mut self, distance: S) -> &mut Self {
-        let zero = distance.clone() - distance.clone();
-        assert!(zero.le(&distance), "Distance must be positive!");
+/// The conversions to and from `f64` of a `PeakFinder`, set by the settings that need them.
+#[derive(Debug, Clone)]
+struct F64Conversions<T, S> {
+    as_f64: Option<fn(&T) -> f64>,
+    from_f64: Option<fn(f64) -> T>,
+    x_as_f64: Option<fn(&S) -> f64>,
+    x_from_f64: Option<fn(f64) -> S>,
+}
+
+impl<T, S> Default for F64Conversions<T, S> {
+    fn default() -> Self {
+        F64Conversions {
+            as_f64: None,
+            from_f64: None,
+            x_as_f64: None,
+            x_from_f64: None,
+        }
+    }
+}
+
+// the conversions follow from the types, only whether they are there differs
+impl<T, S> PartialEq for F64Conversions<T, S> {
+    fn eq(&self, other: &Self) -> bool {
+        self.as_f64.is_some() == other.as_f64.is_some()
+            && self.from_f64.is_some() == other.from_f64.is_some()
+            && self.x_as_f64.is_some() == other.x_as_f64.is_some()
+            && self.x_from_f64.is_some() == other.x_from_f64.is_some()
+    }
+}
+
+fn to_f64<T: Clone + Into<f64>>(v: &T) -> f64 {
+    v.clone().into()
+}
+
+/// Lossy conversion of x-values to `f64`, for the settings relative to the span of `x_data`
+/// (`Into<f64>` is not implemented for `usize`, the type of the default x-values).
+pub trait AsF64 {
+    fn as_f64(&self) -> f64;
+}
+
+macro_rules! impl_as_f64 {
+    ($($t:ty),*) => {
+        $(impl AsF64 for $t {
+            fn as_f64(&self) -> f64 {
+                *self as f64
+            }
+        })*
+    };
+}
+
+impl_as_f64!(u8, u16, u32, u64, usize, i8, i16, i32, i64, isize, f32, f64);
+
+/// Lossy conversion from `f64`, for the settings computed in `f64` that are applied to the
+/// data (integers are truncated towards zero).
+pub trait FromF64 {
+    fn from_f64(v: f64) -> Self;
+}
+
+macro_rules! impl_from_f64 {
+    ($($t:ty),*) => {
+        $(impl FromF64 for $t {
+            fn from_f64(v: f64) -> Self {
+                v as $t
+            }
+        })*
+    };
+}
+
+impl_from_f64!(u8, u16, u32, u64, usize, i8, i16, i32, i64, isize, f32, f64);
+
+/// For every index in `queries` (sorted, counted along `data`), the minimum of the samples
+/// between it and the nearest preceding sample that is higher than the sample at the index, or
+/// `None` if there are no samples in between, and whether there is no such higher sample.
+/// Masked samples (the `bool` of `data`) are barriers like the start of the data.
+///
+/// The stack holds the samples that are not (yet) shadowed by a higher later sample, each with
+/// the minimum of the shadowed samples before it, so every sample is pushed and popped once.
+fn valley_minima<'d, T, I>(data: I, queries: &[usize]) -> Vec<(Valley<'d, T>, bool)>
+where
+    T: PartialOrd + 'd,
+    I: Iterator<Item = (usize, &'d T, bool)>,
+{
+    let mut stack: Vec<((usize, &T), Valley<T>)> = Vec::new();
+    let mut minima = Vec::with_capacity(queries.len());
+    let mut queries = queries.iter().peekable();
+
+    for (j, (i, y, masked)) in data.enumerate() {
+        if masked {
+            stack.clear();
+            while queries.peek() == Some(&&j) {
+                minima.push((None, true));
+                queries.next();
+            }
+            continue;
+        }
+
+        let mut valley: Valley<T> = None;
+        while let Some(&(v, shadowed)) = stack.last() {
+            if v.1 <= y {
+                stack.pop();
+                valley = lower_valley(lower_valley(valley, Some(v)), shadowed);
+            } else {
+                break;
+            }
+        }
+
+        // the samples left on the stack are the higher ones
+        while queries.peek() == Some(&&j) {
+            minima.push((valley, stack.is_empty()));
+            queries.next();
+        }
+        stack.push(((i, y), valley));
+    }
+
+    minima
+}
+
+/// Which peaks, given in height order by their middle positions `middles`, are kept by the
+/// distance bounds: going from the highest peak, a peak is kept if it is `apart` from and
+/// `near` to all the higher kept peaks.
+///
+/// The lower bounds (`apart`) have to grow monotonically with the positions, so that a kept
+/// peak only removes a run of peaks on each side, found by walking along the position order.
+/// Removed peaks are skipped by path compressed links, so every peak is removed once and the
+/// selection is O(n log n) for sorting the positions. For the upper bound (`near`) it is
+/// enough to check the leftmost and the rightmost kept peak.
+fn select_by_distance<A, N>(middles: &[usize], apart: A, near: N) -> Vec<bool>
+where
+    A: Fn(usize, usize) -> bool,
+    N: Fn(usize, usize) -> bool,
+{
+    let n = middles.len();
+    let mut by_position: Vec<usize> = (0..n).collect();
+    by_position.sort_by_key(|&k| middles[k]);
+    let mut rank = vec![0; n];
+    for (r, &k) in by_position.iter().enumerate() {
+        rank[k] = r;
+    }
+
+    // `next[r]` links to the nearest remaining rank from `r` on (`n` if none), `prev[r + 1]`
+    // to the nearest remaining rank up to `r` shifted by one (0 if none)
+    let mut next: Vec<usize> = (0..=n).collect();
+    let mut prev: Vec<usize> = (0..=n).collect();
+    let mut keep: Vec<Option<bool>> = vec![None; n];
+    let mut extremes: Option<(usize, usize)> = None;
+
+    let remove = |r: usize, next: &mut [usize], prev: &mut [usize]| {
+        next[r] = r + 1;
+        prev[r + 1] = r;
+    };
+
+    for k in 0..n {
+        if keep[k].is_some() {
+            continue;
+        }
+        let i = middles[k];
+
+        if !extremes.is_none_or(|(lo, hi)| near(lo, i) && near(hi, i)) {
+            keep[k] = Some(false);
+            remove(rank[k], &mut next, &mut prev);
+            continue;
+        }
+        keep[k] = Some(true);
+        extremes = Some(extremes.map_or((i, i), |(lo, hi)| (lo.min(i), hi.max(i))));
+
+        let mut r = find_root(&mut next, rank[k] + 1);
+        while r < n && !apart(i, middles[by_position[r]]) {
+            keep[by_position[r]] = Some(false);
+            remove(r, &mut next, &mut prev);
+            r = find_root(&mut next, r + 1);
+        }
+
+        let mut s = find_root(&mut prev, rank[k]);
+        while s > 0 && !apart(i, middles[by_position[s - 1]]) {
+            keep[by_position[s - 1]] = Some(false);
+            remove(s - 1, &mut next, &mut prev);
+            s = find_root(&mut prev, s - 1);
+        }
+    }
+
+    keep.into_iter().map(|k| k == Some(true)).collect()
+}
+
+/// Distances (in samples) from the middle of the peak at `position` to its half-prominence
+/// `crossings` on the left and on the right, `None` if either is not crossed before an edge of
+/// the data.
+fn half_widths(
+    position: &Range<usize>,
+    (left, right): (Option<f64>, Option<f64>),
+) -> Option<(f64, f64)> {
+    let middle = (position.start + position.end - 1) as f64 / 2.;
+    Some((middle - left?, right? - middle))
+}
+
+/// Direction of the data from `from` to `to` and the absolute value of their difference, which
+/// never goes below zero for unsigned types. Incomparable values (NaN) are `Slope::Flat`.
+fn difference<T>(from: &T, to: &T) -> (Slope, T)
+where
+    T: Clone + std::ops::Sub<Output = T> + PartialOrd,
+{
+    if to > from {
+        (Slope::Rising, to.clone() - from.clone())
+    } else if to < from {
+        (Slope::Falling, from.clone() - to.clone())
+    } else {
+        (Slope::Flat, to.clone() - from.clone())
+    }
+}
+
+/// Order of `a` and `b`, the greater first and the values not comparable to themselves (NaN)
+/// last, so that sorting by it is a total order for floats.
+fn cmp_descending<T: PartialOrd>(a: &T, b: &T) -> std::cmp::Ordering {
+    let unordered = |v: &T| v.partial_cmp(v).is_none();
+    unordered(a)
+        .cmp(&unordered(b))
+        .then_with(|| b.partial_cmp(a).unwrap_or(std::cmp::Ordering::Equal))
+}
+
+/// Prominence of a peak and its valleys, see `PeakFinder::calc_prominence_valleys`.
+struct ValleySearch<T> {
+    prominence: T,
+    /// the peak height above the left and the right valley
+    sides: (Option<T>, Option<T>),
+    /// index of the lower valley
+    valley: Option<usize>,
+    bounded: bool,
+    capped: bool,
+}
+
+impl<T> ValleySearch<T> {
+    /// Prominence without valleys, e.g. above a baseline.
+    fn without_valleys(prominence: T) -> Self {
+        Self {
+            prominence,
+            sides: (None, None),
+            valley: None,
+            bounded: false,
+            capped: false,
+        }
+    }
+}
+
+/// Peak in the heap of `PeakFinder::with_max_peaks`, ordered by `cmp`.
+struct HeapEntry<'c, T> {
+    peak: Peak<T>,
+    cmp: &'c dyn Fn(&Peak<T>, &Peak<T>) -> std::cmp::Ordering,
+}
+
+impl<T> PartialEq for HeapEntry<'_, T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == std::cmp::Ordering::Equal
+    }
+}
+
+impl<T> Eq for HeapEntry<'_, T> {}
+
+impl<T> PartialOrd for HeapEntry<'_, T> {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<T> Ord for HeapEntry<'_, T> {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        (self.cmp)(&self.peak, &other.peak)
+    }
+}
+
+/// The index and the value of a prominence valley, `None` if there is none.
+type Valley<'d, T> = Option<(usize, &'d T)>;
+
+/// The lower of two valleys, `a` if they are equally low.
+fn lower_valley<'d, T: PartialOrd>(a: Valley<'d, T>, b: Valley<'d, T>) -> Valley<'d, T> {
+    match (a, b) {
+        (Some(a), Some(b)) => Some(if b.1 < a.1 { b } else { a }),
+        (a, None) => a,
+        (None, b) => b,
+    }
+}
+
+/// Connected interval `lo..=hi` of samples for `get_persistent_maxima`, with the minima on
+/// either side of its highest sample `birth`.
+#[derive(Clone)]
+struct Component<T> {
+    birth: usize,
+    lo: usize,
+    hi: usize,
+    left_min: T,
+    right_min: T,
+}
+
+fn find_root(parent: &mut [usize], mut i: usize) -> usize {
+    while parent[i] != i {
+        parent[i] = parent[parent[i]];
+        i = parent[i];
+    }
+    i
+}
+
+/// Function computing the label of a peak, see `PeakFinder::with_labeler`.
+///
+/// It is `'static` so that the finder does not keep its data borrowed until it is dropped.
+type Labeler<T> = Arc<dyn Fn(&Peak<T>) -> String + Send + Sync>;
+
+/// User predicate keeping a peak of the data, see `PeakFinder::with_filter`.
+type PeakFilter<T> = Arc<dyn Fn(&Peak<T>, &[T]) -> bool + Send + Sync>;
+
+/// User distance between x-values, see `PeakFinder::with_distance_metric`.
+type DistanceMetric<S> = Arc<dyn Fn(&S, &S) -> S + Send + Sync>;
+
+/// User base level of the prominence at an index of the data, see `PeakFinder::with_base_fn`.
+type BaseFn<T> = Arc<dyn Fn(usize, &[T]) -> T + Send + Sync>;
+
+/// Trend of the steps from `y[i - 1]` to `y[i]` for the `LANES` indices `i` from the given one:
+/// `Less` if all of them rise, `Greater` if all of them fall and `None` otherwise (or if the
+/// zero is not one that a strict step can never equal). See `PeakFinder::with_simd`.
+type ChunkTrend<T> = fn(&[T], usize, &T) -> Option<std::cmp::Ordering>;
+
+macro_rules! simd_chunk_trend {
+    ($name:ident, $t:ty, $v:ty, $lanes:expr) => {
+        #[cfg(feature = "simd")]
+        fn $name(data: &[$t], i: usize, zero: &$t) -> Option<std::cmp::Ordering> {
+            use std::convert::TryInto;
+            use wide::{CmpGt, CmpLt};
+
+            if !(*zero == 0. || zero.is_nan()) {
+                return None;
+            }
+            let load = |s: &[$t]| <$v>::new(s.try_into().unwrap());
+            let (prev, next) = (
+                load(&data[i - 1..i + $lanes - 1]),
+                load(&data[i..i + $lanes]),
+            );
+            if prev.cmp_lt(next).all() {
+                Some(std::cmp::Ordering::Less)
+            } else if prev.cmp_gt(next).all() {
+                Some(std::cmp::Ordering::Greater)
+            } else {
+                None
+            }
+        }
+    };
+}
+
+simd_chunk_trend!(chunk_trend_f32, f32, wide::f32x8, 8);
+simd_chunk_trend!(chunk_trend_f64, f64, wide::f64x4, 4);
+
+/// Setup for the peak filtering.
+///
+/// Change the settings by using the methods for specifing the lower and upper bounds.
+#[derive(Clone)]
+pub struct PeakFinder<'a, T, S>
+where
+    [T]: ToOwned,
+    [S]: ToOwned,
+{
+    y_data: Cow<'a, [T]>,
+    x_data: Cow<'a, [S]>,
+    height: Limits<T>,
+    prominence: Limits<T>,
+    difference: Limits<T>,
+    side_thresholds: (Option<T>, Option<T>),
+    left_difference: Limits<T>,
+    right_difference: Limits<T>,
+    min_runs: (Option<usize>, Option<usize>),
+    run_plateaus: bool,
+    plateau_size: Limits<usize>,
+    width_x: Limits<S>,
+    x_range: Limits<S>,
+    distance: Limits<S>,
+    zero: Option<T>,
+    edge_peaks: bool,
+    prominence_window: Option<usize>,
+    prominence_max_scan: Option<usize>,
+    local_baseline: Option<usize>,
+    prominence_reference: ProminenceRef<T>,
+    merge_distance: Option<S>,
+    plateau_tolerance: Option<T>,
+    split_plateaus: Option<f64>,
+    hysteresis: Option<(T, T)>,
+    min_sample_gap: Option<usize>,
+    local_window: Option<usize>,
+    local_contrast: Option<(usize, f64)>,
+    mask: Option<Cow<'a, [bool]>>,
+    saturation_level: Option<T>,
+    drop_clipped: bool,
+    compute_all: bool,
+    compute_rank: bool,
+    max_peaks: Option<usize>,
+    target_peak_count: Option<usize>,
+    expected_peaks: Option<usize>,
+    sort_order: SortOrder,
+    distance_priority: DistancePriority,
+    distance_first: bool,
+    tie_break: TieBreak,
+    direction: Direction,
+    abs: bool,
+    min_peaks: usize,
+    classification_thresholds: ClassificationThresholds,
+    circular: Option<(S, usize)>,
+    wrap: Option<(S, usize)>,
+    persistence: bool,
+    labeler: Option<Labeler<T>>,
+    filter: Option<(PeakFilter<T>, bool)>,
+    distance_metric: Option<DistanceMetric<S>>,
+    base_fn: Option<BaseFn<T>>,
+    min_relative_prominence: Option<f64>,
+    min_relative_difference: Option<f64>,
+    min_prominence_ratio: Option<f64>,
+    dog: Option<(f64, f64)>,
+    log_amplitude: Option<f64>,
+    resample: Option<usize>,
+    measured: Option<&'a [T]>,
+    score_weights: Option<ScoreWeights>,
+    min_prominence_f64: Option<f64>,
+    height_offset: Option<T>,
+    apex_height: Limits<f64>,
+    compute_symmetry: bool,
+    compute_widths: bool,
+    compute_sharpness: bool,
+    min_distance_fraction: Option<f64>,
+    x_as_f64: Option<fn(&S) -> f64>,
+    x_from_f64: Option<fn(f64) -> S>,
+    as_f64: Option<fn(&T) -> f64>,
+    from_f64: Option<fn(f64) -> T>,
+    chunk_trend: Option<(usize, ChunkTrend<T>)>,
+}
+
+impl<'a, T> PeakFinder<'a, T, usize>
+where
+    T: Clone + std::ops::Sub<Output = T> + PartialOrd,
+{
+    /// Initialize with a data slice.
+    pub fn new(y_data: &'a [T]) -> Self {
+        let x: Vec<usize> = (0..y_data.len()).collect();
+        Self::from_parts(Cow::from(y_data), Cow::from(x))
+    }
+
+    /// Initialize with a signal and a reference signal, e.g. the prediction of a model, to find
+    /// the peaks of the residual `y_data[i] - reference[i]`. All the bounds and properties are
+    /// computed on the residual, but the returned `height` is the sample of `y_data` at the
+    /// start of the peak (not relative to `with_height_reference`), and so is `valley_value`.
+    /// For unsigned types the reference must not exceed the signal.
+    ///
+    /// # Panics
+    ///
+    /// If there are not as many reference values as y-values, see `try_new_with_reference`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use find_peaks::PeakFinder;
+    /// let y = [1., 2., 3., 5., 5., 6., 7.];
+    /// let model = [1., 2., 3., 4., 5., 6., 7.];
+    ///
+    /// let ps = PeakFinder::new_with_reference(&y, &model).find_peaks();
+    ///
+    /// // one above the model, but the sample is 5.
+    /// assert_eq!(ps.len(), 1);
+    /// assert_eq!(ps[0].position, 3..4);
+    /// assert_eq!(ps[0].height, Some(5.));
+    /// ```
+    pub fn new_with_reference(y_data: &'a [T], reference: &[T]) -> Self {
+        match Self::try_new_with_reference(y_data, reference) {
+            Ok(fp) => fp,
+            Err(err) => panic!("{}", err),
+        }
+    }
+
+    /// Like `new_with_reference`, but fails with `PeakError::ReferenceLengthMismatch` instead of
+    /// panicking if there are not as many reference values as y-values.
+    pub fn try_new_with_reference(y_data: &'a [T], reference: &[T]) -> Result<Self, PeakError> {
+        if y_data.len() != reference.len() {
+            return Err(PeakError::ReferenceLengthMismatch {
+                y: y_data.len(),
+                reference: reference.len(),
+            });
+        }
+
+        let residual: Vec<T> = y_data
+            .iter()
+            .zip(reference)
+            .map(|(y, r)| y.clone() - r.clone())
+            .collect();
+        let x: Vec<usize> = (0..y_data.len()).collect();
+        let mut fp = Self::from_parts(Cow::from(residual), Cow::from(x));
+        fp.measured = Some(y_data);
+        Ok(fp)
+    }
+
+    /// Initialize with the samples of a ring buffer, oldest first.
+    ///
+    /// The buffer is rotated in place to make its samples contiguous (`VecDeque::make_contiguous`),
+    /// so they are borrowed without a copy. For other indexed containers, the samples can be
+    /// collected into a finder directly, see the `FromIterator` implementation.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use find_peaks::PeakFinder;
+    /// use std::collections::VecDeque;
+    ///
+    /// let mut ring: VecDeque<f64> = VecDeque::with_capacity(4);
+    /// for y in [9., 1., 3., 0., 2.] {
+    ///     if ring.len() == 4 {
+    ///         ring.pop_front();
+    ///     }
+    ///     ring.push_back(y);
+    /// }
+    ///
+    /// let ps = PeakFinder::from_deque(&mut ring).find_peaks();
+    ///
+    /// assert_eq!(ps[0].middle_position(), 1);
+    /// ```
+    pub fn from_deque(y_data: &'a mut VecDeque<T>) -> Self {
+        Self::new(y_data.make_contiguous())
+    }
+
+    /// Initialize with an `ndarray` view.
+    ///
+    /// The underlying memory is borrowed when the view is contiguous and in standard order,
+    /// otherwise the data are copied.
+    #[cfg(feature = "ndarray")]
+    pub fn from_array(y_data: ndarray::ArrayView1<'a, T>) -> Self {
+        let y: Cow<'a, [T]> = match y_data.to_slice() {
+            Some(slice) => Cow::from(slice),
+            None => Cow::from(y_data.to_vec()),
+        };
+        let x: Vec<usize> = (0..y.len()).collect();
+        Self::from_parts(y, Cow::from(x))
+    }
+
+    /// Initialize with complex samples, e.g. an FFT spectrum, to find the peaks of their
+    /// magnitudes (requires the `num-complex` feature). The heights, prominences and all the
+    /// bounds are in magnitudes; the complex value of a peak, with its phase, is found by
+    /// `Peak::complex_value` in `data`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use find_peaks::PeakFinder;
+    /// use num_complex::Complex;
+    /// let data = [Complex::new(0., 1.), Complex::new(3., 4.), Complex::new(0., -2.)];
+    ///
+    /// let ps = PeakFinder::from_complex(&data).with_min_height(1.).find_peaks();
+    ///
+    /// assert_eq!(ps[0].height, Some(5.));
+    /// assert_eq!(ps[0].complex_value(&data), &Complex::new(3., 4.));
+    /// ```
+    #[cfg(feature = "num-complex")]
+    pub fn from_complex(data: &[num_complex::Complex<T>]) -> Self
+    where
+        num_complex::Complex<T>: num_complex::ComplexFloat<Real = T>,
+    {
+        use num_complex::ComplexFloat;
+
+        let magnitudes: Vec<T> = data.iter().map(|z| z.abs()).collect();
+        let x: Vec<usize> = (0..data.len()).collect();
+        Self::from_parts(Cow::from(magnitudes), Cow::from(x))
+    }
+
+    /// Initialize with a data slice and the settings of `config`.
+    pub fn with_config(y_data: &'a [T], config: PeakConfig<T, usize>) -> Self {
+        let mut fp = Self::new(y_data);
+        fp.set_config(config);
+        fp
+    }
+}
+
+macro_rules! impl_with_simd {
+    ($t:ty, $trend:ident, $lanes:expr) => {
+        #[cfg(feature = "simd")]
+        impl<'a, S> PeakFinder<'a, $t, S>
+        where
+            S: Clone + std::ops::Sub<Output = S> + PartialOrd,
+            [S]: ToOwned,
+        {
+            /// Speed up the scan for local extrema with SIMD comparisons (requires the `simd`
+            /// feature): chunks of strictly rising or falling samples, which can not contain an
+            /// extremum, are recognised and skipped at once. The peaks are exactly the same as
+            /// without it; the gain depends on how long the monotonic runs of the data are.
+            pub fn with_simd(&mut self) -> &mut Self {
+                self.chunk_trend = Some(($lanes, $trend));
+                self
+            }
+        }
+    };
+}
+
+impl_with_simd!(f32, chunk_trend_f32, 8);
+impl_with_simd!(f64, chunk_trend_f64, 4);
+
+/// Summary of the configured bounds and the length of the data, without the data themselves.
+///
+/// # Examples
+///
+/// ```
+/// use find_peaks::PeakFinder;
+/// let y = [1., 2., 3., 0., 5., 0.];
+///
+/// let mut fp = PeakFinder::new(&y);
+/// fp.with_min_height(1.);
+///
+/// let summary = format!("{:?}", fp);
+/// assert!(summary.starts_with("PeakFinder { len: 6, height: Limits { lower: Some(1.0)"));
+/// ```
+impl<T, S> std::fmt::Debug for PeakFinder<'_, T, S>
+where
+    T: std::fmt::Debug,
+    S: std::fmt::Debug,
+    [T]: ToOwned,
+    [S]: ToOwned,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PeakFinder")
+            .field("len", &self.y_data.len())
+            .field("height", &self.height)
+            .field("prominence", &self.prominence)
+            .field("difference", &self.difference)
+            .field("plateau_size", &self.plateau_size)
+            .field("width_x", &self.width_x)
+            .field("x_range", &self.x_range)
+            .field("distance", &self.distance)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Initialize with samples produced by an iterator (e.g. a lazy decoder), without a `Vec` on the
+/// caller's side. The finder needs random access to the data, so they are buffered internally.
+///
+/// # Examples
+///
+/// ```
+/// use find_peaks::PeakFinder;
+///
+/// let fp: PeakFinder<f64, usize> = (0..10).map(|i| (i as f64).sin()).collect();
+/// let ps = fp.find_peaks();
+///
+/// assert_eq!(ps.iter().map(|p| p.middle_position()).collect::<Vec<_>>(), vec![8, 2]);
+/// ```
+impl<T> std::iter::FromIterator<T> for PeakFinder<'_, T, usize>
+where
+    T: Clone + std::ops::Sub<Output = T> + PartialOrd,
+{
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let y: Vec<T> = iter.into_iter().collect();
+        let x: Vec<usize> = (0..y.len()).collect();
+        Self::from_parts(Cow::from(y), Cow::from(x))
+    }
+}
+
+/// Prominences computed on demand with the settings of a finder, see
+/// `PeakFinder::prominence_calculator`. It only borrows the finder, so it is cheap to make and
+/// to hold.
+pub struct ProminenceCalculator<'f, 'a, T, S>
+where
+    [T]: ToOwned,
+    [S]: ToOwned,
+{
+    finder: &'f PeakFinder<'a, T, S>,
+}
+
+impl<T, S> ProminenceCalculator<'_, '_, T, S>
+where
+    T: Clone + std::ops::Sub<Output = T> + PartialOrd,
+    S: Clone + std::ops::Sub<Output = S> + PartialOrd,
+    [S]: ToOwned,
+{
+    /// Prominence of a peak spanning `position`, as `PeakFinder::prominence_of`.
+    ///
+    /// The valleys are searched from the peak outwards only up to the nearest higher samples,
+    /// so the time taken grows with the size of the peak and not with the length of the data.
+    pub fn prominence_for(&self, position: Range<usize>) -> T {
+        let fp = self.finder;
+        let data: &[T] = &fp.y_data;
+        assert!(
+            position.start < position.end && position.end <= data.len(),
+            "Position must be a non-empty range of the data!"
+        );
+
+        let local = fp.base_fn.is_none()
+            && fp.local_baseline.is_none()
+            && fp.prominence_reference == ProminenceRef::LocalValleys
+            && fp.scan_reach().is_none();
+        if !local {
+            // these searches are bounded already
+            return fp
+                .calc_prominences(std::slice::from_ref(&position))
+                .pop()
+                .unwrap();
+        }
+
+        let h = &data[position.start];
+        let valleys = fp.scan_valleys(&position, h, data.len());
+        fp.valley_search(h, valleys).prominence
+    }
+}
+
+impl<'a, T, S> PeakFinder<'a, T, S>
+where
+    T: Clone + std::ops::Sub<Output = T> + PartialOrd,
+    S: Clone + std::ops::Sub<Output = S> + PartialOrd,
+    [S]: ToOwned,
+{
+    /// Initialize with y-values and the x-values they were sampled at. The x-values only need to
+    /// be ordered and to subtract (without going below zero, as the larger one is always the
+    /// minuend), so timestamps such as `std::time::Duration` work too.
+    ///
+    /// For the bounds measured in x-units (distance, merge distance and width) the x-values have
+    /// to be monotonic, which `validate` checks.
+    ///
+    /// # Panics
+    ///
+    /// If there are not as many x-values as y-values, see `try_new_with_x`.
+    pub fn new_with_x(y_data: &'a [T], x_data: &'a [S]) -> Self {
+        match Self::try_new_with_x(y_data, x_data) {
+            Ok(fp) => fp,
+            Err(err) => panic!("{}", err),
+        }
+    }
+
+    /// Like `new_with_x`, but fails with `PeakError::LengthMismatch` instead of panicking if there
+    /// are not as many x-values as y-values.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use find_peaks::{PeakError, PeakFinder};
+    /// let y = [0., 2., 0.];
+    ///
+    /// assert!(PeakFinder::try_new_with_x(&y, &[1., 2., 3.]).is_ok());
+    /// assert_eq!(
+    ///     PeakFinder::try_new_with_x(&y, &[1., 2.]).unwrap_err(),
+    ///     PeakError::LengthMismatch { y: 3, x: 2 }
+    /// );
+    /// ```
+    pub fn try_new_with_x(y_data: &'a [T], x_data: &'a [S]) -> Result<Self, PeakError> {
+        if y_data.len() != x_data.len() {
+            return Err(PeakError::LengthMismatch {
+                y: y_data.len(),
+                x: x_data.len(),
+            });
+        }
+        Ok(Self::from_parts(Cow::from(y_data), Cow::from(x_data)))
+    }
+
+    /// Initialize with the function `f` sampled at the x-values, e.g. an analytically defined
+    /// signal or a fitted model. The samples are stored by the finder.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use find_peaks::PeakFinder;
+    /// let x: Vec<f64> = (0..100).map(|i| i as f64 / 10.).collect();
+    ///
+    /// let ps = PeakFinder::from_fn(&x, |x| (-(x - 4.2f64).powi(2)).exp()).find_peaks();
+    ///
+    /// assert_eq!(ps[0].middle_position(), 42);
+    /// ```
+    pub fn from_fn(x_data: &'a [S], f: impl Fn(&S) -> T) -> Self {
+        let y_data: Vec<T> = x_data.iter().map(f).collect();
+        Self::from_parts(Cow::from(y_data), Cow::from(x_data))
+    }
+
+    fn from_parts(y_data: Cow<'a, [T]>, x_data: Cow<'a, [S]>) -> Self {
+        let zero = y_data.first().map(|y| y.clone() - y.clone());
+        Self {
+            y_data,
+            x_data,
+            height: Limits::empty(),
+            prominence: Limits::empty(),
+            difference: Limits {
+                lower: zero.clone(),
+                upper: None,
+            },
+            side_thresholds: (None, None),
+            left_difference: Limits::empty(),
+            right_difference: Limits::empty(),
+            min_runs: (None, None),
+            run_plateaus: false,
+            plateau_size: Limits::empty(),
+            width_x: Limits::empty(),
+            x_range: Limits::empty(),
+            distance: Limits::empty(),
+            zero,
+            edge_peaks: false,
+            prominence_window: None,
+            prominence_max_scan: None,
+            local_baseline: None,
+            prominence_reference: ProminenceRef::LocalValleys,
+            merge_distance: None,
+            plateau_tolerance: None,
+            split_plateaus: None,
+            hysteresis: None,
+            min_sample_gap: None,
+            local_window: None,
+            local_contrast: None,
+            mask: None,
+            saturation_level: None,
+            drop_clipped: false,
+            compute_all: false,
+            compute_rank: false,
+            max_peaks: None,
+            target_peak_count: None,
+            expected_peaks: None,
+            sort_order: SortOrder::Height,
+            distance_priority: DistancePriority::Height,
+            distance_first: false,
+            tie_break: TieBreak::LeftmostFirst,
+            direction: Direction::Forward,
+            abs: false,
+            min_peaks: 0,
+            classification_thresholds: ClassificationThresholds::default(),
+            circular: None,
+            wrap: None,
+            persistence: false,
+            labeler: None,
+            filter: None,
+            distance_metric: None,
+            base_fn: None,
+            chunk_trend: None,
+            min_relative_prominence: None,
+            min_relative_difference: None,
+            min_prominence_ratio: None,
+            dog: None,
+            log_amplitude: None,
+            resample: None,
+            measured: None,
+            score_weights: None,
+            min_prominence_f64: None,
+            height_offset: None,
+            apex_height: Limits::empty(),
+            compute_symmetry: false,
+            compute_widths: false,
+            compute_sharpness: false,
+            min_distance_fraction: None,
+            x_as_f64: None,
+            x_from_f64: None,
+            as_f64: None,
+            from_f64: None,
+        }
+    }
+
+    /// Peak candidates: the local maxima whose neighbour differences are within `limit`, and the
+    /// edge peaks if enabled.
+    fn candidates<'b>(
+        &'b self,
+        limit: &'b Limits<T>,
+        visit: impl FnMut(usize) + 'b,
+    ) -> impl Iterator<Item = Peak<T>> + 'b {
+        let (left_edge, right_edge) = if self.edge_peaks {
+            self.get_edge_maxima(limit)
+        } else {
+            (None, None)
+        };
+
+        left_edge
+            .into_iter()
+            .chain(self.get_local_extrema(false, limit, visit))
+            .chain(right_edge)
+    }
+
+    /// Scan for local maxima, or for local minima if `minima` is set -- the differences are then
+    /// taken the other way around so that they are positive towards the minimum. An extremum
+    /// spans a run of equal samples (see `Peak::plateau_value`).
+    ///
+    /// A difference towards a higher (for minima lower) neighbour is `None` instead of negative,
+    /// so that nothing is subtracted the wrong way for unsigned types; it is never inside the
+    /// difference bounds.
+    ///
+    /// Every sample that could be a global extremum is passed to `visit` (all of them, except
+    /// those inside the skipped monotonic chunks), see `ExtremaScan`.
+    fn get_local_extrema<'b>(
+        &'b self,
+        minima: bool,
+        limit: &'b Limits<T>,
+        mut visit: impl FnMut(usize) + 'b,
+    ) -> impl Iterator<Item = Peak<T>> + 'b {
+        let diff = move |a: &T, b: &T| {
+            let (hi, lo) = if minima { (b, a) } else { (a, b) };
+            if hi >= lo {
+                Some(hi.clone() - lo.clone())
+            } else {
+                None
+            }
+        };
+        let inside = move |d: &Option<T>| d.as_ref().is_some_and(|d| limit.is_inside(d));
+
+        // samples are read by index, so each one is cloned only for the subtractions
+        let data: &[T] = &self.y_data;
+
+        // with less than two samples there is nothing to scan; a plateau at the first index is
+        // left to the edge candidates
+        let init = match (&self.zero, data.first(), data.get(1)) {
+            (Some(zero), Some(zeroth), Some(first)) if first == zeroth => {
+                Some((zero.clone(), None))
+            }
+            (Some(zero), Some(zeroth), Some(first)) => Some((zero.clone(), diff(first, zeroth))),
+            _ => None,
+        };
+
+        // steps towards (away from) the extremum
+        let (upward, downward) = if minima {
+            (std::cmp::Ordering::Greater, std::cmp::Ordering::Less)
+        } else {
+            (std::cmp::Ordering::Less, std::cmp::Ordering::Greater)
+        };
+
+        init.map(move |(zero, mut back_diff)| {
+            visit(0);
+            visit(1);
+            let mut start: Option<usize> = None;
+            let mut next = 2;
+
+            std::iter::from_fn(move || {
+                while next < data.len() {
+                    // skip a chunk of strictly monotonic steps, in which no extremum ends; the
+                    // state after it is that of the last step
+                    match self.chunk_trend {
+                        Some((lanes, trend)) if next + lanes <= data.len() => {
+                            let t = trend(data, next, &zero);
+                            if t == Some(upward) {
+                                next += lanes;
+                                visit(next - 1);
+                                start = None;
+                                back_diff = diff(&data[next - 1], &data[next - 2]);
+                                continue;
+                            }
+                            // the first step down only ends an extremum after a step up
+                            if t == Some(downward) && back_diff.is_none() {
+                                next += lanes;
+                                visit(next - 1);
+                                start = None;
+                                continue;
+                            }
+                        }
+                        _ => {}
+                    }
+
+                    let i = next;
+                    next += 1;
+                    visit(i);
+                    let ahead_diff = diff(&data[i - 1], &data[i]); // positive for downward slope
+                    let back_inside = inside(&back_diff);
+
+                    if ahead_diff.as_ref() == Some(&zero) {
+                        // a plateau keeps the difference leading onto it, so that a flat step of
+                        // a staircase is not taken for a peak
+                        if back_inside && start.is_none() {
+                            start = Some(i - 1);
+                        }
+                    } else {
+                        let r = match (&back_diff, &ahead_diff) {
+                            (Some(back), Some(ahead)) if back_inside && inside(&ahead_diff) => {
+                                Some(Peak::new(
+                                    start.unwrap_or(i - 1)..i,
+                                    back.clone(),
+                                    ahead.clone(),
+                                ))
+                            }
+                            _ => None,
+                        };
+
+                        start = None;
+                        back_diff = diff(&data[i], &data[i - 1]);
+
+                        if r.is_some() {
+                            return r;
+                        }
+                    }
+                }
+                None
+            })
+        })
+        .into_iter()
+        .flatten()
+    }
+
+    /// Scan for the local maxima and the local minima at once, in the order of their positions:
+    /// an extremum ends where the slope changes direction, taking the runs of equal samples
+    /// in between as plateaus. The extrema are the same as those of `get_local_extrema` for
+    /// both kinds, with the differences positive towards the extremum.
+    fn get_turning_points(&self, limit: &Limits<T>) -> Vec<Extremum<T>> {
+        let data: &[T] = &self.y_data;
+        self.scan_with_zero(|zero| {
+            // the kind of extremum ended by a step from `a` to `b` (a maximum for a fall), its
+            // difference, or `None` for a flat or incomparable step
+            let step = |a: &T, b: &T| {
+                if a >= b {
+                    let d = a.clone() - b.clone();
+                    if d == zero {
+                        None
+                    } else {
+                        Some((ExtremumKind::Maximum, d))
+                    }
+                } else if b > a {
+                    Some((ExtremumKind::Minimum, b.clone() - a.clone()))
+                } else {
+                    None
+                }
+            };
+
+            let mut extrema = Vec::new();
+            // the last non-flat step, if it leads towards an extremum within the bounds
+            let mut back: Option<(ExtremumKind, T)> = None;
+            let mut start: Option<usize> = None;
+            for i in 1..data.len() {
+                let ahead = step(&data[i - 1], &data[i]);
+                let flat = ahead.is_none() && data[i - 1] == data[i];
+                if flat {
+                    // a plateau at the first sample is left to the edge candidates
+                    if back.is_some() && start.is_none() {
+                        start = Some(i - 1);
+                    }
+                    continue;
+                }
+
+                if let (Some((kind, back_diff)), Some((ahead_kind, ahead_diff))) = (&back, &ahead) {
+                    // a step in the other direction ends the extremum
+                    if kind == ahead_kind && limit.is_inside(ahead_diff) {
+                        extrema.push(Extremum {
+                            peak: Peak::new(
+                                start.unwrap_or(i - 1)..i,
+                                back_diff.clone(),
+                                ahead_diff.clone(),
+                            ),
+                            kind: *kind,
+                        });
+                    }
+                }
+
+                // the step leads towards an extremum of the other kind
+                back = ahead
+                    .map(|(kind, d)| {
+                        let towards = match kind {
+                            ExtremumKind::Maximum => ExtremumKind::Minimum,
+                            ExtremumKind::Minimum => ExtremumKind::Maximum,
+                        };
+                        (towards, d)
+                    })
+                    .filter(|(_, d)| limit.is_inside(d));
+                start = None;
+            }
+            extrema
+        })
+    }
+
+    /// Candidate peaks of the hysteresis detection: the highest samples between each rise above
+    /// `high` and the following fall below `low`. Every sample is passed to `visit`.
+    fn get_hysteresis_maxima(
+        &self,
+        low: &T,
+        high: &T,
+        mut visit: impl FnMut(usize),
+    ) -> Vec<Peak<T>> {
+        let data: &[T] = &self.y_data;
+        self.scan_with_zero(|zero| {
+            let mut peaks = Vec::new();
+            // the run of the highest samples since the last rise above `high`
+            let mut armed: Option<Range<usize>> = None;
+
+            for (i, y) in data.iter().enumerate() {
+                visit(i);
+                match &mut armed {
+                    None => {
+                        if y > high {
+                            armed = Some(i..i + 1);
+                        }
+                    }
+                    Some(run) => {
+                        let top = &data[run.start];
+                        if y < low {
+                            // the sample before the rise is below `high`, so the diffs are positive
+                            let left_diff = if run.start == 0 {
+                                zero.clone()
+                            } else {
+                                top.clone() - data[run.start - 1].clone()
+                            };
+                            let right_diff = top.clone() - data[run.end].clone();
+                            peaks.push(Peak::new(run.clone(), left_diff, right_diff));
+                            armed = None;
+                        } else if y > top {
+                            *run = i..i + 1;
+                        } else if y == top && run.end == i {
+                            run.end = i + 1;
+                        }
+                    }
+                }
+            }
+            peaks
+        })
+    }
+
+    /// Shoulders (see `find_shoulders`): runs of equal steps on a monotonic flank that are
+    /// flatter than the steps before and after them, located at the higher end of the run (the
+    /// whole run if it is flat).
+    fn get_shoulders(&self) -> Vec<Peak<T>> {
+        let data: &[T] = &self.y_data;
+        let abs_diff = |a: &T, b: &T| {
+            if a > b {
+                a.clone() - b.clone()
+            } else {
+                b.clone() - a.clone()
+            }
+        };
+        // direction and size of the step from sample `i` to `i + 1`
+        let step = |i: usize| {
+            let direction = data[i + 1].partial_cmp(&data[i]);
+            (direction, abs_diff(&data[i + 1], &data[i]))
+        };
+        let n_steps = data.len().saturating_sub(1);
+
+        let mut shoulders = Vec::new();
+        let mut k = 1;
+        while k + 1 < n_steps {
+            let (before, (direction, size)) = (step(k - 1), step(k));
+            if size >= before.1 {
+                k += 1;
+                continue;
+            }
+
+            let mut e = k;
+            while e + 1 < n_steps && step(e + 1) == (direction, size.clone()) {
+                e += 1;
+            }
+            if e + 1 < n_steps {
+                let after = step(e + 1);
+                let flank = before.0 == after.0 && before.0 != Some(std::cmp::Ordering::Equal);
+                let flat = direction == Some(std::cmp::Ordering::Equal);
+                if after.1 > size && flank && (flat || direction == before.0) {
+                    let position = if flat {
+                        k..e + 2
+                    } else if direction == Some(std::cmp::Ordering::Greater) {
+                        e + 1..e + 2
+                    } else {
+                        k..k + 1
+                    };
+                    let left_diff = abs_diff(&data[position.start], &data[position.start - 1]);
+                    let right_diff = abs_diff(&data[position.end - 1], &data[position.end]);
+                    let mut p = Peak::new(position, left_diff, right_diff);
+                    p.is_shoulder = true;
+                    shoulders.push(p);
+                }
+            }
+            k = e + 1;
+        }
+        shoulders
+    }
+
+    /// Candidate peaks where the first difference crosses from positive to negative (see
+    /// `find_peaks_derivative`). The differences are only compared, so this is safe for unsigned
+    /// types.
+    fn get_derivative_maxima(&self) -> Vec<Peak<T>> {
+        let limit = &self.difference;
+
+        let mut peaks = Vec::new();
+        // the index the last positive difference led to, and that difference
+        let mut rise: Option<(usize, T)> = None;
+
+        for (i, (slope, diff)) in self.differences().into_iter().enumerate() {
+            match slope {
+                Slope::Rising => rise = Some((i + 1, diff)),
+                Slope::Falling => {
+                    if let Some((start, left_diff)) = rise.take() {
+                        if limit.is_inside(&left_diff) && limit.is_inside(&diff) {
+                            peaks.push(Peak::new(start..i + 1, left_diff, diff));
+                        }
+                    }
+                }
+                // a zero difference keeps the pending rise, making a plateau
+                Slope::Flat => {}
+            }
+        }
+
+        peaks
+    }
+
+    /// `scan(zero)` with the zero of the data, or no candidates (`R::default()`) without one,
+    /// which is only the case for empty data (see `with_zero`).
+    fn scan_with_zero<R: Default>(&self, scan: impl FnOnce(T) -> R) -> R {
+        self.zero.clone().map_or_else(R::default, scan)
+    }
+
+    /// Candidate peaks at the first and the last sample (see `with_edge_peaks`).
+    fn get_edge_maxima(&self, limit: &Limits<T>) -> (Option<Peak<T>>, Option<Peak<T>>) {
+        let data: &[T] = &self.y_data;
+        self.scan_with_zero(|zero| {
+            // runs of equal values touching the edges
+            let left_run = data.iter().take_while(|&y| y == &data[0]).count();
+            if left_run == data.len() {
+                return (None, None);
+            }
+            let last = data.len() - 1;
+            let right_run = data.iter().rev().take_while(|&y| y == &data[last]).count();
+
+            let left = if data[left_run] < data[0] {
+                let diff = data[0].clone() - data[left_run].clone();
+                if limit.is_inside(&diff) {
+                    Some(Peak::new(0..left_run, zero.clone(), diff))
+                } else {
+                    None
+                }
+            } else {
+                None
+            };
+
+            let right_start = data.len() - right_run;
+            let right = if data[right_start - 1] < data[last] {
+                let diff = data[last].clone() - data[right_start - 1].clone();
+                if limit.is_inside(&diff) {
+                    Some(Peak::new(right_start..data.len(), diff, zero))
+                } else {
+                    None
+                }
+            } else {
+                None
+            };
+
+            (left, right)
+        })
+    }
+
+    fn filter_plateau(&self, mut peaks: Vec<Peak<T>>) -> Vec<Peak<T>> {
+        let limit = &self.plateau_size;
+
+        if !limit.is_empty() {
+            peaks.retain(|p| limit.is_inside(&p.position.len()));
+        }
+        peaks
+    }
+
+    fn filter_width_x(&self, mut peaks: Vec<Peak<T>>) -> Vec<Peak<T>> {
+        let limit = &self.width_x;
+        let x_data = &self.x_data;
+
+        if !limit.is_empty() {
+            peaks.retain(|p| {
+                let width = self.x_distance(&x_data[p.first_index()], &x_data[p.last_index()]);
+                limit.is_inside(&width)
+            });
+        }
+        peaks
+    }
+
+    fn filter_x_range(&self, mut peaks: Vec<Peak<T>>) -> Vec<Peak<T>> {
+        let limit = &self.x_range;
+
+        if !limit.is_empty() {
+            peaks.retain(|p| limit.is_inside(&self.x_data[p.middle_position()]));
+        }
+        peaks
+    }
+
+    /// Flag the peaks at or above `saturation_level` as clipped, dropping them if `drop_clipped`
+    /// is set.
+    fn filter_clipped(&self, mut peaks: Vec<Peak<T>>) -> Vec<Peak<T>> {
+        if let Some(level) = &self.saturation_level {
+            for p in &mut peaks {
+                p.clipped = &self.y_data[p.position.start] >= level;
+            }
+            if self.drop_clipped {
+                peaks.retain(|p| !p.clipped);
+            }
+        }
+        peaks
+    }
+
+    fn filter_local_window(&self, mut peaks: Vec<Peak<T>>) -> Vec<Peak<T>> {
+        let data: &[T] = &self.y_data;
+
+        if let Some(w) = self.local_window {
+            peaks.retain(|p| {
+                let window =
+                    p.position.start.saturating_sub(w)..(p.position.end + w).min(data.len());
+                data[window].iter().all(|y| y <= &data[p.position.start])
+            });
+        }
+        peaks
+    }
+
+    /// Keep the peaks that are at least the contrast amount above the mean of the `k` unmasked
+    /// samples on each side of them.
+    fn filter_local_contrast(&self, mut peaks: Vec<Peak<T>>) -> Vec<Peak<T>> {
+        let data: &[T] = &self.y_data;
+
+        if let Some(((k, amount), as_f64)) = self.local_contrast.zip(self.as_f64) {
+            peaks.retain(|p| {
+                let left = p.position.start.saturating_sub(k)..p.position.start;
+                let right = p.position.end..(p.position.end + k).min(data.len());
+                let (sum, count) = left
+                    .chain(right)
+                    .filter(|&i| !self.is_masked(i))
+                    .fold((0., 0), |(sum, count), i| {
+                        (sum + as_f64(&data[i]), count + 1)
+                    });
+                // nothing to compare with
+                count == 0 || as_f64(&data[p.position.start]) - sum / count as f64 >= amount
+            });
+        }
+        peaks
+    }
+
+    /// Keep the peaks whose height above `height_offset` is inside the height bounds, storing
+    /// the sample value as the height; `reference_heights` subtracts the offset at the end.
+    fn filter_height(&self, peaks: Vec<Peak<T>>) -> Vec<Peak<T>> {
+        let limit = &self.height;
+
+        if limit.is_empty() && !self.compute_all && self.height_offset.is_none() {
+            // do nothing
+            peaks
+        } else {
+            peaks
+                .into_iter()
+                .filter_map(|mut p| {
+                    let y = self.y_data[p.position.start].clone();
+                    let inside = match &self.height_offset {
+                        None => limit.is_inside(&y),
+                        Some(offset) => {
+                            offset <= &y && limit.is_inside(&(y.clone() - offset.clone()))
+                        }
+                    };
+
+                    if inside {
+                        p.add_height(y);
+                        Some(p)
+                    } else {
+                        None
+                    }
+                })
+                .collect()
+        }
+    }
+
+    /// Keep the peaks whose apex estimated by `apex_height` (above `height_offset`) is inside
+    /// the apex height bounds.
+    fn filter_apex_height(&self, mut peaks: Vec<Peak<T>>) -> Vec<Peak<T>> {
+        if let Some(as_f64) = self.as_f64.filter(|_| !self.apex_height.is_empty()) {
+            let offset = self.height_offset.as_ref().map_or(0., as_f64);
+            peaks.retain(|p| {
+                self.apex_height
+                    .is_inside(&(self.apex_height_of(p, as_f64) - offset))
+            });
+        }
+        peaks
+    }
+
+    /// Height of the vertex of the parabola through a single-sample peak and its two
+    /// neighbours, `b - (a - c)^2 / (8 (a - 2b + c))` for the samples `a`, `b`, `c`. The
+    /// sample value for a plateau or an edge peak, whose top is flat or has no parabola.
+    fn apex_height_of(&self, p: &Peak<T>, as_f64: fn(&T) -> f64) -> f64 {
+        let data: &[T] = &self.y_data;
+        let i = p.position.start;
+        let b = as_f64(&data[i]);
+        if p.position.len() != 1 || i == 0 || i + 1 >= data.len() {
+            return b;
+        }
+
+        let (a, c) = (as_f64(&data[i - 1]), as_f64(&data[i + 1]));
+        let curvature = a - 2. * b + c;
+        if curvature < 0. {
+            b - (a - c) * (a - c) / (8. * curvature)
+        } else {
+            b
+        }
+    }
+
+    fn filter_prominence(&self, peaks: Vec<Peak<T>>) -> Vec<Peak<T>> {
+        if self.persistence {
+            // the persistence of the candidates is their prominence
+            return peaks
+                .into_iter()
+                .filter_map(|mut p| {
+                    let prom = p.prominence.take()?;
+                    self.accept_prominence(p, prom)
+                })
+                .collect();
+        }
+
+        let empty = self.prominence.is_empty()
+            && self.min_relative_prominence.is_none()
+            && self.min_prominence_ratio.is_none()
+            && self.min_prominence_f64.is_none()
+            && !self.compute_all;
+
+        if empty {
+            // do nothing
+            peaks
+        } else {
+            let peaks = self.below_global_bound(peaks);
+            let positions: Vec<Range<usize>> = peaks.iter().map(|p| p.position.clone()).collect();
+            let proms = self.calc_prominence_valleys(&positions);
+
+            peaks
+                .into_iter()
+                .zip(proms)
+                .filter_map(|(mut p, search)| {
+                    let prom = self.add_valley(&mut p, search);
+                    self.accept_prominence(p, prom)
+                })
+                .collect()
+        }
+    }
+
+    /// Drop the peaks less than the lower prominence bound above the global minimum without
+    /// searching their valleys, as no valley is lower than the global minimum.
+    fn below_global_bound(&self, peaks: Vec<Peak<T>>) -> Vec<Peak<T>> {
+        let lower = match &self.prominence.lower {
+            Some(lower) if self.prominence_reference == ProminenceRef::LocalValleys => lower,
+            _ => return peaks,
+        };
+        let data: &[T] = &self.y_data;
+        let min =
+            data.iter()
+                .filter(|y| y.partial_cmp(y).is_some())
+                .fold(
+                    None,
+                    |m: Option<&T>, y| if m.is_none_or(|m| y < m) { Some(y) } else { m },
+                );
+        let min = match min {
+            Some(min) => min,
+            None => return peaks,
+        };
+
+        peaks
+            .into_iter()
+            .filter(|p| {
+                let y = &data[p.position.start];
+                // incomparable heights are left to the valley search
+                !(y >= min && &(y.clone() - min.clone()) < lower)
+            })
+            .collect()
+    }
+
+    /// Check `prom` against the prominence bounds and store it in `p` if it is inside.
+    fn accept_prominence(&self, mut p: Peak<T>, prom: T) -> Option<Peak<T>> {
+        let relative_inside = match self.min_relative_prominence.zip(self.as_f64) {
+            None => true,
+            Some((frac, as_f64)) => {
+                let y = self.y_data[p.position.start].clone();
+                let offset = self.height_offset.as_ref().map_or(0., as_f64);
+                let inside = as_f64(&prom) >= frac * (as_f64(&y) - offset);
+                p.add_height(y);
+                inside
+            }
+        };
+
+        let noise_inside = match self.min_prominence_f64.zip(self.as_f64) {
+            None => true,
+            Some((min, as_f64)) => as_f64(&prom) >= min,
+        };
+
+        if relative_inside && noise_inside && self.prominence.is_inside(&prom) {
+            p.add_prominence(prom);
+            Some(p)
+        } else {
+            None
+        }
+    }
+
+    /// Drop the peaks whose prominence is below `min_prominence_ratio` times the prominence of
+    /// the nearest taller peak. The nearest taller peaks are found with a stack of the peaks in
+    /// order of position; of two equally near ones the more prominent counts.
+    fn filter_prominence_ratio(&self, peaks: Vec<Peak<T>>) -> Vec<Peak<T>> {
+        let (ratio, as_f64) = match self.min_prominence_ratio.zip(self.as_f64) {
+            Some(setting) => setting,
+            None => return peaks,
+        };
+
+        let mut order: Vec<usize> = (0..peaks.len()).collect();
+        order.sort_by_key(|&i| peaks[i].position.start);
+        let height = |i: usize| as_f64(&self.y_data[peaks[i].position.start]);
+
+        let nearest_taller = |order: &mut dyn Iterator<Item = &usize>| {
+            let mut taller = vec![None; peaks.len()];
+            let mut stack: Vec<usize> = Vec::new();
+            for &i in order {
+                while stack.last().is_some_and(|&j| height(j) <= height(i)) {
+                    stack.pop();
+                }
+                taller[i] = stack.last().copied();
+                stack.push(i);
+            }
+            taller
+        };
+        let left = nearest_taller(&mut order.iter());
+        let right = nearest_taller(&mut order.iter().rev());
+
+        let prominence = |i: usize| peaks[i].prominence.as_ref().map_or(0., as_f64);
+        let keep: Vec<bool> = (0..peaks.len())
+            .map(|i| {
+                let middle = peaks[i].middle_position();
+                let nearest = match (left[i], right[i]) {
+                    (Some(l), Some(r)) => {
+                        let dl = middle - peaks[l].middle_position();
+                        let dr = peaks[r].middle_position() - middle;
+                        if dl < dr || (dl == dr && prominence(l) >= prominence(r)) {
+                            Some(l)
+                        } else {
+                            Some(r)
+                        }
+                    }
+                    (l, r) => l.or(r),
+                };
+                nearest.is_none_or(|j| prominence(i) >= ratio * prominence(j))
+            })
+            .collect();
+
+        peaks
+            .into_iter()
+            .zip(keep)
+            .filter_map(|(p, keep)| if keep { Some(p) } else { None })
+            .collect()
+    }
+
+    /// Candidate peaks with prominences computed by a persistence sweep: samples are visited from
+    /// the highest to the lowest, merging the visited neighbours into connected components. When
+    /// two components meet, the one with the lower maximum dies and its peak's persistence is
+    /// its height above the merging level.
+    ///
+    /// Like `calc_prominence` (and scipy), the base on a side where the component reaches the
+    /// edge of the data is the minimum between the peak and the edge. Every sample is passed to
+    /// `visit`.
+    fn get_persistent_maxima(&self, mut visit: impl FnMut(usize)) -> Vec<Peak<T>> {
+        let data: &[T] = &self.y_data;
+        self.scan_with_zero(|zero| {
+            let last = data.len() - 1;
+            let limit = &self.difference;
+
+            let mut order: Vec<usize> = (0..data.len()).collect();
+            order.sort_by(|&a, &b| cmp_descending(&data[a], &data[b]).then(a.cmp(&b)));
+
+            let mut visited = vec![false; data.len()];
+            let mut parent: Vec<usize> = (0..data.len()).collect();
+            let mut components: Vec<Option<Component<T>>> = vec![None; data.len()];
+            let mut persistence: Vec<(usize, T)> = Vec::new();
+
+            for i in order {
+                visit(i);
+                let v = data[i].clone();
+                let left = if i > 0 && visited[i - 1] {
+                    Some(find_root(&mut parent, i - 1))
+                } else {
+                    None
+                };
+                let right = if i < last && visited[i + 1] {
+                    Some(find_root(&mut parent, i + 1))
+                } else {
+                    None
+                };
+                visited[i] = true;
+
+                match (left, right) {
+                    (None, None) => {
+                        components[i] = Some(Component {
+                            birth: i,
+                            lo: i,
+                            hi: i,
+                            left_min: v.clone(),
+                            right_min: v,
+                        });
+                    }
+                    (Some(r), None) => {
+                        parent[i] = r;
+                        let c = components[r].as_mut().unwrap();
+                        c.hi = i;
+                        c.right_min = v;
+                    }
+                    (None, Some(r)) => {
+                        parent[i] = r;
+                        let c = components[r].as_mut().unwrap();
+                        c.lo = i;
+                        c.left_min = v;
+                    }
+                    (Some(a), Some(b)) => {
+                        let ca = components[a].take().unwrap();
+                        let cb = components[b].take().unwrap();
+
+                        // the left component survives a tie
+                        let (root, dying, base, merged) = if data[ca.birth] >= data[cb.birth] {
+                            let base = if cb.hi == last {
+                                cb.right_min
+                            } else {
+                                v.clone()
+                            };
+                            let merged = Component {
+                                birth: ca.birth,
+                                lo: ca.lo,
+                                hi: cb.hi,
+                                left_min: ca.left_min,
+                                right_min: v,
+                            };
+                            (a, cb.birth, base, merged)
+                        } else {
+                            let base = if ca.lo == 0 { ca.left_min } else { v.clone() };
+                            let merged = Component {
+                                birth: cb.birth,
+                                lo: ca.lo,
+                                hi: cb.hi,
+                                left_min: v,
+                                right_min: cb.right_min,
+                            };
+                            (b, ca.birth, base, merged)
+                        };
+
+                        persistence.push((dying, data[dying].clone() - base));
+                        parent[a] = root;
+                        parent[b] = root;
+                        parent[i] = root;
+                        components[root] = Some(merged);
+                    }
+                }
+            }
+
+            // the component of the highest peak never dies
+            let root = find_root(&mut parent, 0);
+            let c = components[root].take().unwrap();
+            let base = if c.left_min >= c.right_min {
+                c.left_min
+            } else {
+                c.right_min
+            };
+            persistence.push((c.birth, data[c.birth].clone() - base));
+
+            persistence.sort_unstable_by_key(|(birth, _)| *birth);
+            persistence
+                .into_iter()
+                .filter(|(_, pers)| pers > &zero)
+                .filter_map(|(start, pers)| {
+                    // a component is born at the leftmost sample of its plateau
+                    let end = start
+                        + data[start..]
+                            .iter()
+                            .take_while(|&y| y == &data[start])
+                            .count();
+
+                    let left_diff = if start == 0 {
+                        zero.clone()
+                    } else {
+                        data[start].clone() - data[start - 1].clone()
+                    };
+                    let right_diff = if end == data.len() {
+                        zero.clone()
+                    } else {
+                        data[start].clone() - data[end].clone()
+                    };
+
+                    let at_edge = start == 0 || end == data.len();
+                    let inside = (start == 0 || limit.is_inside(&left_diff))
+                        && (end == data.len() || limit.is_inside(&right_diff));
+
+                    if inside && (self.edge_peaks || !at_edge) {
+                        let mut p = Peak::new(start..end, left_diff, right_diff);
+                        p.add_prominence(pers);
+                        Some(p)
+                    } else {
+                        None
+                    }
+                })
+                .collect()
+        })
+    }
+
+    /// Merge peaks whose middle positions are within the merge distance of the preceding peak
+    /// into one peak spanning all of them, see `with_merge_distance`.
+    fn merge_close(&self, peaks: Vec<Peak<T>>) -> Vec<Peak<T>> {
+        match &self.merge_distance {
+            Some(distance) => self
+                .group_close(peaks, distance)
+                .into_iter()
+                .map(|group| self.merge_group(group))
+                .collect(),
+            None => peaks,
+        }
+    }
+
+    /// Join the neighbouring (position sorted) `peaks` of the same value separated by dips less
+    /// than `tolerance` deep into single plateaus, see `with_merge_plateau_tolerance`.
+    fn merge_plateaus(&self, peaks: Vec<Peak<T>>, tolerance: &T) -> Vec<Peak<T>> {
+        let data: &[T] = &self.y_data;
+        let mut merged: Vec<Peak<T>> = Vec::with_capacity(peaks.len());
+
+        for p in peaks {
+            if let Some(last) = merged.last_mut() {
+                let h = &data[last.position.start];
+                let shallow = (last.position.end..p.position.start)
+                    .all(|i| &data[i] <= h && &(h.clone() - data[i].clone()) < tolerance);
+                if &data[p.position.start] == h && shallow {
+                    last.position.end = p.position.end;
+                    last.right_diff = p.right_diff;
+                    continue;
+                }
+            }
+            merged.push(p);
+        }
+        merged
+    }
+
+    /// Split the asymmetric plateaus of `peaks` in two, see `with_split_asymmetric_plateaus`.
+    fn split_plateaus(&self, peaks: Vec<Peak<T>>) -> Vec<Peak<T>> {
+        let (threshold, as_f64, zero) = match (self.split_plateaus, self.as_f64, &self.zero) {
+            (Some(threshold), Some(as_f64), Some(zero)) => (threshold, as_f64, zero),
+            _ => return peaks,
+        };
+        let n = self.y_data.len();
+        let asymmetric = |p: &Peak<T>| {
+            let (left, right) = (as_f64(&p.left_diff), as_f64(&p.right_diff));
+            left.max(right) > threshold * left.min(right)
+        };
+
+        let mut split = Vec::with_capacity(peaks.len());
+        for p in peaks {
+            let edge = p.position.start == 0 || p.position.end == n;
+            if p.position.len() < 2 || edge || !asymmetric(&p) {
+                split.push(p);
+                continue;
+            }
+            let middle = p.position.start + p.position.len() / 2;
+            let mut left = p.clone();
+            left.position = p.position.start..middle;
+            left.right_diff = zero.clone();
+            let mut right = p;
+            right.position = middle..right.position.end;
+            right.left_diff = zero.clone();
+            split.push(left);
+            split.push(right);
+        }
+        split
+    }
+
+    /// Whether the differences of the peak to its neighbours are inside the difference bounds;
+    /// the outer side of an edge peak has no neighbour and is not checked.
+    fn differences_inside(&self, p: &Peak<T>) -> bool {
+        (p.position.start == 0 || self.difference.is_inside(&p.left_diff))
+            && (p.position.end == self.y_data.len() || self.difference.is_inside(&p.right_diff))
+    }
+
+    /// Sort `peaks` by position and split them into groups of peaks whose middle positions are
+    /// at most `max_gap` apart from the preceding peak.
+    fn group_close(&self, mut peaks: Vec<Peak<T>>, max_gap: &S) -> Vec<Vec<Peak<T>>> {
+        let x_data = &self.x_data;
+
+        peaks.sort_unstable_by_key(|p| p.position.start);
+
+        let mut groups: Vec<Vec<Peak<T>>> = Vec::new();
+        let mut last_x: Option<S> = None;
+        for p in peaks {
+            let x = x_data[p.middle_position()].clone();
+
+            let close = last_x.is_some_and(|x_l| self.peak_distance(&x_l, &x).le(max_gap));
+
+            match groups.last_mut() {
+                Some(group) if close => group.push(p),
+                _ => groups.push(vec![p]),
+            }
+            last_x = Some(x);
+        }
+        groups
+    }
+
+    /// One peak spanning the (position sorted, non-empty) `group` with the height of its highest
+    /// peak; the prominence, if computed, is recalculated for the whole span.
+    fn merge_group(&self, mut group: Vec<Peak<T>>) -> Peak<T> {
+        if group.len() == 1 {
+            return group.pop().unwrap();
+        }
+
+        let data: &[T] = &self.y_data;
+        let (first, last) = (&group[0], &group[group.len() - 1]);
+        let span = first.position.start..last.position.end;
+
+        let height = group
+            .iter()
+            .map(|p| &data[p.position.start])
+            .fold(&data[span.start], |m, y| if y > m { y } else { m });
+
+        let mut merged = Peak::new(
+            span.clone(),
+            first.left_diff.clone(),
+            last.right_diff.clone(),
+        );
+        merged.add_height(height.clone());
+        merged.clipped = group.iter().any(|p| p.clipped);
+
+        if group.iter().any(|p| p.prominence.is_some()) {
+            let middle = (span.start + span.end) / 2;
+            let floor = match &self.base_fn {
+                Some(base) => Some(self.prominence_above_base(middle, height, base)),
+                None => self.prominence_from_floor(height),
+            };
+            let search = match floor {
+                Some(prom) => ValleySearch::without_valleys(prom),
+                None => {
+                    let reach = self.scan_reach().unwrap_or(data.len());
+                    let valleys = self.scan_valleys(&span, height, reach);
+                    ValleySearch {
+                        bounded: self.scan_reaches_edge(&span, height, reach),
+                        capped: self.scan_capped(&span, height, reach),
+                        ..self.valley_search(height, valleys)
+                    }
+                }
+            };
+            let prom = self.add_valley(&mut merged, search);
+            merged.add_prominence(prom);
+        }
+        merged
+    }
+
+    /// Distance between the x-values of two peaks for the distance bounds and the merge
+    /// distance: the metric of `with_distance_metric`, or else `x_distance`.
+    fn peak_distance(&self, a: &S, b: &S) -> S {
+        match &self.distance_metric {
+            Some(metric) => metric(a, b),
+            None => self.x_distance(a, b),
+        }
+    }
+
+    /// Absolute difference of x-values, the shorter way around for circular data.
+    fn x_distance(&self, a: &S, b: &S) -> S {
+        // done without abs because of trait bounds
+        let dist = if a > b {
+            a.clone() - b.clone()
+        } else {
+            b.clone() - a.clone()
+        };
+
+        match &self.wrap {
+            Some((period, _)) if dist <= *period => {
+                let other = period.clone() - dist.clone();
+                if other < dist {
+                    other
+                } else {
+                    dist
+                }
+            }
+            _ => dist,
+        }
+    }
+
+    /// Number of samples between indices `i` and `j`, the shorter way around for circular data.
+    fn sample_distance(&self, i: usize, j: usize) -> usize {
+        let dist = i.abs_diff(j);
+        match &self.wrap {
+            Some((_, n)) => dist.min(n.saturating_sub(dist)),
+            None => dist,
+        }
+    }
+
+    /// Apply only the distance bounds of this finder to `peaks` that are already sorted by
+    /// height, highest first and equal heights as set by `with_tie_break` -- e.g. the result of
+    /// `find_peaks` sorted by height -- without sorting them again. This is for trying
+    /// different distances on one set of peaks; the kept peaks are returned sorted by height.
+    ///
+    /// Debug builds check that the peaks are sorted.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use find_peaks::PeakFinder;
+    /// let y = [0., 5., 0., 4., 0., 3., 0., 2., 0.];
+    ///
+    /// let mut fp = PeakFinder::new(&y);
+    /// let candidates = fp.find_peaks();
+    /// for (distance, kept) in [(1, 4), (3, 2), (5, 2), (7, 1)].iter() {
+    ///     fp.with_min_distance(*distance);
+    ///     assert_eq!(fp.filter_distance_presorted(candidates.clone()).len(), *kept);
+    /// }
+    /// ```
+    pub fn filter_distance_presorted(&self, mut peaks: Vec<Peak<T>>) -> Vec<Peak<T>> {
+        self.fill_heights(&mut peaks);
+        debug_assert!(
+            peaks
+                .windows(2)
+                .all(|w| self.cmp_heights(&w[0], &w[1]) != std::cmp::Ordering::Greater),
+            "The peaks must be sorted by height!"
+        );
+
+        if peaks.is_empty() {
+            return peaks;
+        }
+        self.split_sorted_by_distance(peaks).0
+    }
+
+    /// Store the height of the peaks without one, for sorting them.
+    fn fill_heights(&self, peaks: &mut [Peak<T>]) {
+        for p in peaks.iter_mut().filter(|p| p.height.is_none()) {
+            let y = self.y_data[p.position.start].clone();
+            p.add_height(y);
+        }
+    }
+
+    /// The peaks kept by the distance bounds and the ones they suppress, both sorted by height.
+    fn split_by_distance(&self, mut peaks: Vec<Peak<T>>) -> (Vec<Peak<T>>, Vec<Peak<T>>) {
+        self.fill_heights(&mut peaks);
+        if self.max_peaks.is_some() && !self.has_distance_bounds() {
+            // `select_highest` sorts the peaks it keeps
+            return (peaks, Vec::new());
+        }
+        peaks.sort_unstable_by(|a, b| self.cmp_heights(a, b));
+        self.split_sorted_by_distance(peaks)
+    }
+
+    /// Whether any distance bound is set.
+    fn has_distance_bounds(&self) -> bool {
+        !self.distance.is_empty()
+            || self.min_distance_fraction.is_some()
+            || self.min_sample_gap.is_some()
+    }
+
+    /// Like `split_by_distance`, but for `peaks` sorted by height and with their heights set.
+    fn split_sorted_by_distance(&self, mut peaks: Vec<Peak<T>>) -> (Vec<Peak<T>>, Vec<Peak<T>>) {
+        let limit = &self.distance;
+        let x_data = &self.x_data;
+
+        // the distance relative to the span of the x-values, in `f64`
+        let min_fraction = self
+            .min_distance_fraction
+            .zip(self.x_as_f64)
+            .and_then(|(f, as_f64)| {
+                let span = as_f64(x_data.last()?) - as_f64(x_data.first()?);
+                Some((f * span.abs(), as_f64))
+            });
+
+        if limit.is_empty() && min_fraction.is_none() && self.min_sample_gap.is_none() {
+            return (peaks, Vec::new());
+        }
+
+        // the indices of `peaks` from the highest priority to the lowest
+        let order: Vec<usize> = match self.distance_priority {
+            DistancePriority::Height => (0..peaks.len()).collect(),
+            DistancePriority::Prominence => {
+                let missing: Vec<Range<usize>> = peaks
+                    .iter()
+                    .filter(|p| p.prominence.is_none())
+                    .map(|p| p.position.clone())
+                    .collect();
+                let mut proms = self.calc_prominence_valleys(&missing).into_iter();
+                for p in peaks.iter_mut().filter(|p| p.prominence.is_none()) {
+                    if let Some(search) = proms.next() {
+                        p.prominence = Some(self.add_valley(p, search));
+                    }
+                }
+
+                // equally prominent peaks stay in the order of their heights
+                let mut order: Vec<usize> = (0..peaks.len()).collect();
+                order.sort_by(|&a, &b| {
+                    cmp_descending(&peaks[a].prominence, &peaks[b].prominence).then(a.cmp(&b))
+                });
+                order
+            }
+        };
+        let middles: Vec<usize> = order.iter().map(|&k| peaks[k].middle_position()).collect();
+        let metric = self.wrap.is_some() || self.distance_metric.is_some();
+        let monotonic = !metric && self.is_monotonic(&middles);
+
+        // the peaks kept with the minimum distance `min_distance` and the other bounds
+        let select = |min_distance: Option<&S>| {
+            // the lower bounds between the peaks at the middle positions `i` and `j`
+            let apart = |i: usize, j: usize| {
+                let dist = self.peak_distance(&x_data[i], &x_data[j]);
+                min_distance.is_none_or(|lower| lower <= &dist)
+                    && min_fraction.is_none_or(|(min, as_f64)| as_f64(&dist) >= min)
+                    && self
+                        .min_sample_gap
+                        .is_none_or(|gap| self.sample_distance(i, j) >= gap)
+            };
+            // and the upper bound
+            let near = |i: usize, j: usize| {
+                let dist = self.peak_distance(&x_data[i], &x_data[j]);
+                limit.upper.as_ref().is_none_or(|upper| &dist <= upper)
+            };
+
+            if monotonic {
+                select_by_distance(&middles, apart, near)
+            } else {
+                // a peak is kept if it is within the bounds to every higher peak kept
+                let mut kept: Vec<usize> = Vec::new();
+                middles
+                    .iter()
+                    .map(|&i| {
+                        let inside = kept.iter().all(|&k| apart(k, i) && near(k, i));
+                        if inside {
+                            kept.push(i);
+                        }
+                        inside
+                    })
+                    .collect::<Vec<bool>>()
+            }
+        };
+        let count = |keep: &[bool]| keep.iter().filter(|&&k| k).count();
+
+        let mut keep_ordered = select(limit.lower.as_ref());
+        if let (Some(n), Some(lower)) = (self.target_peak_count, &limit.lower) {
+            if count(&keep_ordered) < n {
+                // bisect the distances between the peaks below the minimum distance for the
+                // largest one keeping `n` peaks, or else the smallest one
+                let distances = self.distances_below(&middles, lower, monotonic);
+                let (mut lo, mut hi) = (0, distances.len());
+                let mut relaxed = None;
+                while lo < hi {
+                    let mid = (lo + hi) / 2;
+                    let keep = select(Some(&distances[mid]));
+                    if count(&keep) >= n {
+                        relaxed = Some(keep);
+                        hi = mid;
+                    } else {
+                        lo = mid + 1;
+                    }
+                }
+                if let Some(keep) = relaxed {
+                    keep_ordered = keep;
+                } else if let Some(smallest) = distances.last() {
+                    keep_ordered = select(Some(smallest));
+                }
+            }
+        }
+
+        let mut keep = vec![false; peaks.len()];
+        for (&k, kept) in order.iter().zip(keep_ordered) {
+            keep[k] = kept;
+        }
+
+        let (kept, suppressed): (Vec<_>, Vec<_>) =
+            peaks.into_iter().zip(keep).partition(|(_, keep)| *keep);
+        (
+            kept.into_iter().map(|(p, _)| p).collect(),
+            suppressed.into_iter().map(|(p, _)| p).collect(),
+        )
+    }
+
+    /// The distinct distances below `lower` between the peaks at the `middles`, from the largest.
+    /// With `monotonic` x-values, only the peaks closer than `lower` in the order of the
+    /// positions are compared.
+    fn distances_below(&self, middles: &[usize], lower: &S, monotonic: bool) -> Vec<S> {
+        let mut sorted = middles.to_vec();
+        sorted.sort_unstable();
+        let x_data = &self.x_data;
+
+        let mut distances: Vec<S> = Vec::new();
+        for (a, &i) in sorted.iter().enumerate() {
+            for &j in &sorted[a + 1..] {
+                let dist = self.peak_distance(&x_data[i], &x_data[j]);
+                if &dist < lower {
+                    distances.push(dist);
+                } else if monotonic {
+                    break;
+                }
+            }
+        }
+        distances.sort_by(|a, b| cmp_descending(a, b));
+        distances.dedup_by(|a, b| a == b);
+        distances
+    }
+
+    /// Whether the x-values at the `middles` are monotonic in the order of the positions, so
+    /// that the distances grow with the number of peaks in between.
+    fn is_monotonic(&self, middles: &[usize]) -> bool {
+        let mut sorted = middles.to_vec();
+        sorted.sort_unstable();
+        let x: Vec<&S> = sorted.iter().map(|&i| &self.x_data[i]).collect();
+
+        x.windows(2).all(|w| w[0] <= w[1]) || x.windows(2).all(|w| w[0] >= w[1])
+    }
+
+    /// Prominences of peaks spanning `positions`, computed for all of them at once by one pass
+    /// over the data in each direction.
+    ///
+    /// On each side of a peak the valley is the minimum between the peak and the nearest
+    /// higher sample (or the edge of the data); the prominence is the peak height above the
+    /// higher of the two valleys.
+    ///
+    /// If a prominence window or a maximum scan is set, each side is instead scanned separately
+    /// for at most half of the window or the maximum scan.
+    fn calc_prominences(&self, positions: &[Range<usize>]) -> Vec<T> {
+        self.calc_prominence_valleys(positions)
+            .into_iter()
+            .map(|search| search.prominence)
+            .collect()
+    }
+
+    /// Like `calc_prominences`, but also the heights above the two valleys of each peak and the
+    /// index of the lower one, `None` without valleys or for a global prominence reference,
+    /// whether a valley
+    /// search reached an edge of the data (see `Peak::bounded_by_edge`) and whether one was cut
+    /// short by the maximum scan (see `Peak::prominence_capped`).
+    fn calc_prominence_valleys(&self, positions: &[Range<usize>]) -> Vec<ValleySearch<T>> {
+        let data: &[T] = &self.y_data;
+
+        if let Some(base) = &self.base_fn {
+            return positions
+                .iter()
+                .map(|position| {
+                    let middle = (position.start + position.end) / 2;
+                    let prom = self.prominence_above_base(middle, &data[position.start], base);
+                    ValleySearch::without_valleys(prom)
+                })
+                .collect();
+        }
+
+        if let Some(window) = self.local_baseline {
+            return positions
+                .iter()
+                .map(|position| {
+                    ValleySearch::without_valleys(self.prominence_above_baseline(position, window))
+                })
+                .collect();
+        }
+
+        if self.prominence_reference != ProminenceRef::LocalValleys {
+            return positions
+                .iter()
+                .map(|position| {
+                    let prom = self.prominence_from_floor(&data[position.start]).unwrap();
+                    ValleySearch::without_valleys(prom)
+                })
+                .collect();
+        }
+
+        if let Some(reach) = self.scan_reach() {
+            return positions
+                .iter()
+                .map(|position| {
+                    let h = &data[position.start];
+                    let valleys = self.scan_valleys(position, h, reach);
+                    ValleySearch {
+                        bounded: self.scan_reaches_edge(position, h, reach),
+                        capped: self.scan_capped(position, h, reach),
+                        ..self.valley_search(h, valleys)
+                    }
+                })
+                .collect();
+        }
+
+        let mut order: Vec<usize> = (0..positions.len()).collect();
+
+        order.sort_by_key(|&k| positions[k].start);
+        let queries: Vec<usize> = order.iter().map(|&k| positions[k].start).collect();
+        let mut left_valleys = vec![(None, false); positions.len()];
+        let masked = |i: usize| self.is_masked(i);
+        let forward = data.iter().enumerate().map(|(i, y)| (i, y, masked(i)));
+        for (&k, v) in order.iter().zip(valley_minima(forward, &queries)) {
+            left_valleys[k] = v;
+        }
+
+        // the right valleys are searched on the reversed data
+        order.sort_by_key(|&k| data.len() - positions[k].end);
+        let queries: Vec<usize> = order
+            .iter()
+            .map(|&k| data.len() - positions[k].end)
+            .collect();
+        let mut right_valleys = vec![(None, false); positions.len()];
+        let backward = data
+            .iter()
+            .enumerate()
+            .rev()
+            .map(|(i, y)| (i, y, masked(i)));
+        for (&k, v) in order.iter().zip(valley_minima(backward, &queries)) {
+            right_valleys[k] = v;
+        }
+
+        positions
+            .iter()
+            .zip(left_valleys.into_iter().zip(right_valleys))
+            .map(
+                |(position, ((left, left_edge), (right, right_edge)))| ValleySearch {
+                    bounded: left_edge || right_edge,
+                    ..self.valley_search(&data[position.start], (left, right))
+                },
+            )
+            .collect()
+    }
+
+    /// The prominence of a peak of `height` with the `valleys` on its left and right, and the
+    /// index of the lower valley. The searches are taken as not bounded or capped.
+    fn valley_search(&self, height: &T, valleys: (Valley<T>, Valley<T>)) -> ValleySearch<T> {
+        let above = |v: Valley<T>| v.map(|(_, v)| height.clone() - v.clone());
+        ValleySearch {
+            prominence: self.prominence_above(height, valleys),
+            sides: (above(valleys.0), above(valleys.1)),
+            valley: lower_valley(valleys.0, valleys.1).map(|(i, _)| i),
+            bounded: false,
+            capped: false,
+        }
+    }
+
+    /// Store the valleys of `search` in `p`: the index and the value of the lower valley, the
+    /// heights above both, and whether a search was bounded by an edge or capped. Returns the
+    /// prominence.
+    fn add_valley(&self, p: &mut Peak<T>, search: ValleySearch<T>) -> T {
+        p.valley_index = search.valley;
+        p.valley_value = search.valley.map(|i| self.y_data[i].clone());
+        p.left_prominence = search.sides.0;
+        p.right_prominence = search.sides.1;
+        p.bounded_by_edge = search.bounded;
+        p.prominence_capped = search.capped;
+        search.prominence
+    }
+
+    /// Prominence of a peak spanning `position`, which does not have to be one found by this
+    /// finder (e.g. a peak moved by hand). The peak height is the sample at `position.start`,
+    /// and the valleys are searched as for `find_peaks`, within the prominence window and
+    /// relative to the prominence reference if they are set. The data are taken as linear,
+    /// also if `with_circular` is set.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use find_peaks::PeakFinder;
+    /// let y = [0., 2., 1., 4., 0.];
+    ///
+    /// let fp = PeakFinder::new(&y);
+    ///
+    /// assert_eq!(fp.prominence_of(1..2), 1.);
+    /// assert_eq!(fp.prominence_of(2..3), 0.);
+    /// ```
+    pub fn prominence_of(&self, position: Range<usize>) -> T {
+        self.prominence_calculator().prominence_for(position)
+    }
+
+    /// Borrow the finder as a `ProminenceCalculator`, to compute the prominences of single
+    /// peaks on demand, e.g. of peaks found without a prominence bound when they are
+    /// inspected, instead of for all of them.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use find_peaks::PeakFinder;
+    /// let y = [0., 2., 1., 4., 0., 3., 0.];
+    ///
+    /// let mut fp = PeakFinder::new(&y);
+    /// fp.with_prominence_window(4);
+    /// let ps = fp.find_peaks();
+    /// assert_eq!(ps[0].prominence, None);
+    ///
+    /// // the window of 4 samples reaches the valley at 1. on the left
+    /// let calculator = fp.prominence_calculator();
+    /// assert_eq!(calculator.prominence_for(ps[0].position.clone()), 3.);
+    /// assert_eq!(calculator.prominence_for(ps[2].position.clone()), 1.);
+    /// ```
+    pub fn prominence_calculator(&self) -> ProminenceCalculator<'_, 'a, T, S> {
+        ProminenceCalculator::<T, S> { finder: self }
+    }
+
+    /// The line the prominence of `peak` is measured against, as drawn in scipy's figures: its
+    /// left and right x-values and its level, the height of the peak minus its prominence (the
+    /// higher of the two valleys). The line ends on each side at the first sample at or below the
+    /// level, so at the higher valley on one side (there is no interpolation, as the x-values
+    /// only subtract); it is clamped to the edge of the data if there is no such sample, e.g. for
+    /// a `ProminenceRef::Fixed` floor below the data.
+    ///
+    /// The prominence of `peak` is used if it was computed, otherwise it is computed as by
+    /// `prominence_of`. As there, the data are taken as linear.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use find_peaks::PeakFinder;
+    /// let x = [0., 1., 2., 3., 4., 5., 6.];
+    /// let y = [1., 2., 1.5, 4., 3., 3.5, 0.];
+    ///
+    /// let fp = PeakFinder::new_with_x(&y, &x);
+    /// let ps = fp.find_peaks();
+    ///
+    /// // the left valley at 1. is higher than the right one at 0.
+    /// assert_eq!(fp.prominence_contour(&ps[0]), (0., 6., 1.));
+    /// ```
+    pub fn prominence_contour(&self, peak: &Peak<T>) -> (S, S, T) {
+        let data: &[T] = &self.y_data;
+        let position = &peak.position;
+        let prominence = match &peak.prominence {
+            Some(prominence) => prominence.clone(),
+            None => self.prominence_of(position.clone()),
+        };
+        let level = data[position.start].clone() - prominence;
+
+        let left = (0..position.start)
+            .rev()
+            .find(|&i| data[i] <= level)
+            .unwrap_or(0);
+        let right = (position.end..data.len())
+            .find(|&i| data[i] <= level)
+            .unwrap_or(data.len() - 1);
+        (self.x_data[left].clone(), self.x_data[right].clone(), level)
+    }
+
+    /// Height of the peak at `position` above the median of the (unmasked) samples at most
+    /// `window` samples away on either side of it, zero if it is below it.
+    fn prominence_above_baseline(&self, position: &Range<usize>, window: usize) -> T {
+        let data: &[T] = &self.y_data;
+        let zero = self.zero_like(&data[position.start]);
+        let mut around: Vec<&T> = (position.start.saturating_sub(window)..position.start)
+            .chain(position.end..(position.end + window).min(data.len()))
+            .filter(|&i| !self.is_masked(i))
+            .map(|i| &data[i])
+            .filter(|y| y.partial_cmp(y).is_some())
+            .collect();
+        if around.is_empty() {
+            return zero;
+        }
+
+        around.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        // the lower median, no need to average two samples
+        let baseline = around[(around.len() - 1) / 2];
+        let h = &data[position.start];
+        if h > baseline {
+            h.clone() - baseline.clone()
+        } else {
+            zero
+        }
+    }
+
+    /// Height `h` of the peak at the index `i` above the level of `with_base_fn` there, zero if
+    /// it is below it.
+    fn prominence_above_base(&self, i: usize, h: &T, base: &BaseFn<T>) -> T {
+        let level = base(i, &self.y_data);
+        if *h > level {
+            h.clone() - level
+        } else {
+            self.zero_like(h)
+        }
+    }
+
+    /// The zero of the data, `y_data[0] - y_data[0]`, or `value - value` for empty data, which
+    /// have no zero. Every value to measure gives one, so only the functions without a value
+    /// have to handle the missing zero, by returning early on empty data.
+    fn zero_like(&self, value: &T) -> T {
+        self.zero
+            .clone()
+            .unwrap_or_else(|| value.clone() - value.clone())
+    }
+
+    /// Height `h` above the floor of a global prominence reference (zero below it), `None` for
+    /// `ProminenceRef::LocalValleys`.
+    fn prominence_from_floor(&self, h: &T) -> Option<T> {
+        let zero = self.zero_like(h);
+        let floor = match &self.prominence_reference {
+            ProminenceRef::LocalValleys => return None,
+            ProminenceRef::GlobalMin => {
+                self.y_data.iter().fold(h, |m, y| if y < m { y } else { m })
+            }
+            ProminenceRef::Fixed(floor) => floor,
+        };
+
+        Some(if h > floor {
+            h.clone() - floor.clone()
+        } else {
+            zero
+        })
+    }
+
+    /// Minima between `span` and the nearest sample higher than `h` on each side, searching at
+    /// most `reach` samples per side.
+    ///
+    /// As in scipy's `peak_prominences`, the search goes on past samples as high as the peak and
+    /// stops only at a strictly higher one (the first sample over `h` bounds the peak's base, a
+    /// deeper valley beyond it belongs to the higher peak).
+    ///
+    /// A masked sample (see `with_exclusion_mask`) stops the search like an edge of the data.
+    fn scan_valleys(
+        &self,
+        span: &Range<usize>,
+        h: &T,
+        reach: usize,
+    ) -> (Valley<'_, T>, Valley<'_, T>) {
+        let data: &[T] = &self.y_data;
+        let valley = |indices: &mut dyn Iterator<Item = usize>| {
+            indices
+                .take_while(|&i| !self.is_masked(i) && &data[i] <= h)
+                .fold(None, |m, i| lower_valley(m, Some((i, &data[i]))))
+        };
+        let left = valley(&mut (span.start.saturating_sub(reach)..span.start).rev());
+        let right = valley(&mut (span.end..span.end.saturating_add(reach).min(data.len())));
+        (left, right)
+    }
+
+    /// Whether the search of `scan_valleys` reaches an edge of the data (or a masked sample) on
+    /// either side, passing no sample higher than `h`.
+    fn scan_reaches_edge(&self, span: &Range<usize>, h: &T, reach: usize) -> bool {
+        let data: &[T] = &self.y_data;
+        let stop = |&i: &usize| self.is_masked(i) || &data[i] > h;
+
+        // a stop further away than `reach + 1` samples is not reached
+        let left = match (span.start.saturating_sub(reach.saturating_add(1))..span.start)
+            .rev()
+            .find(stop)
+        {
+            None => span.start <= reach,
+            Some(i) => self.is_masked(i),
+        };
+        let right_end = span
+            .end
+            .saturating_add(reach)
+            .saturating_add(1)
+            .min(data.len());
+        let right = match (span.end..right_end).find(stop) {
+            None => data.len() - span.end <= reach,
+            Some(i) => self.is_masked(i),
+        };
+        left || right
+    }
+
+    /// How far the valley searches scan on each side of a peak, if they are limited by the
+    /// prominence window or the maximum scan.
+    fn scan_reach(&self) -> Option<usize> {
+        let half = self.prominence_window.map(|wlen| wlen / 2);
+        match (half, self.prominence_max_scan) {
+            (Some(half), Some(cap)) => Some(half.min(cap)),
+            (half, cap) => half.or(cap),
+        }
+    }
+
+    /// Whether the search of `scan_valleys` is stopped on either side by the maximum scan,
+    /// rather than by a higher or masked sample, an edge of the data or the prominence window.
+    fn scan_capped(&self, span: &Range<usize>, h: &T, reach: usize) -> bool {
+        if self.prominence_max_scan != Some(reach)
+            || self.prominence_window.is_some_and(|wlen| wlen / 2 <= reach)
+        {
+            return false;
+        }
+
+        let data: &[T] = &self.y_data;
+        let open = |i: usize| !self.is_masked(i) && &data[i] <= h;
+        // the search would have gone on to the sample after the last scanned one
+        let left = span.start > reach && (span.start - reach - 1..span.start).all(open);
+        let right = data.len() - span.end > reach && (span.end..span.end + reach + 1).all(open);
+        left || right
+    }
+
+    /// Whether sample `i` is excluded by `with_exclusion_mask`.
+    fn is_masked(&self, i: usize) -> bool {
+        self.mask.as_ref().is_some_and(|mask| mask[i])
+    }
+
+    /// Height `peak_height` above the higher of the left and right valleys.
+    ///
+    /// The valley searches of the global maximum run to the edges of the data, so, as in scipy,
+    /// its prominence is its height above the higher of the minima on both sides. A side has no
+    /// valley only if it has no samples, i.e. the outer side of an edge peak, or if the search
+    /// stops right away at a masked sample; the prominence is zero only without any valley.
+    fn prominence_above(&self, peak_height: &T, valleys: (Valley<T>, Valley<T>)) -> T {
+        let peak_height = peak_height.clone();
+        match valleys {
+            (None, None) => self.zero_like(&peak_height),
+            (Some((_, v)), None) => peak_height - v.clone(),
+            (None, Some((_, v))) => peak_height - v.clone(),
+            (Some((_, v1)), Some((_, v2))) => {
+                peak_height - (if v1.ge(v2) { v1 } else { v2 }).clone()
+            }
+        }
+    }
+
+    /// Outputs a vector of `Peak<_>` structures containing peaks that matched the criteria
+    /// specified in `PeakFinder<_>`.
+    ///
+    /// Output will **not** contain some properties (for example, height, prominence) unless you
+    /// specified at least on of the corresponding bounds in `PeakFinder<_>` -- the calculation of
+    /// the property is skipped.
+    ///
+    /// Peaks are sorted by their height, unlike the peaks of scipy's `find_peaks`, which are
+    /// sorted by position -- see `with_sort_order`. Equally high peaks are sorted by position
+    /// (see `with_tie_break`) and NaN heights come last, so the order, like the peaks, only
+    /// depends on the data and the settings. The same holds for the order in which the distance
+    /// bounds visit the peaks, also by prominence (see `with_distance_priority`).
+    ///
+    /// The first and the last sample are not peak candidates unless `with_edge_peaks` is set, so
+    /// strictly monotonic data (and any data with less than 3 samples) has no peaks.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use find_peaks::PeakFinder;
+    /// let y = [1., 2., 3., 0., 5., 0.];
+    ///
+    /// let ps = PeakFinder::new(&y)
+    ///            .with_min_height(0.)
+    ///            .with_min_prominence(1.)
+    ///            .find_peaks();
+    ///
+    /// assert_eq!(
+    ///    ps.iter().map(|x| x.middle_position()).collect::<Vec<_>>(),
+    ///    vec![4, 2]
+    /// );
+    ///
+    /// // the consuming variants of the bounds allow to keep the configured finder
+    /// let fp = PeakFinder::new(&y).min_height(0.).min_prominence(1.);
+    /// assert_eq!(fp.find_peaks(), ps);
+    /// ```
+    pub fn find_peaks(&self) -> Vec<Peak<T>> {
+        self.run(None, None, None)
+    }
+
+    /// Like `find_peaks`, but also returns how many peaks were left after each filtering stage.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use find_peaks::PeakFinder;
+    /// let y = [1., 2., 3., 0., 5., 0.];
+    ///
+    /// let (ps, stats) = PeakFinder::new(&y).with_min_height(4.).find_peaks_with_stats();
+    ///
+    /// assert_eq!(ps.len(), 1);
+    /// assert_eq!(stats.local_maxima, 2);
+    /// assert_eq!(stats.after_height, 1);
+    /// ```
+    pub fn find_peaks_with_stats(&self) -> (Vec<Peak<T>>, DetectionStats) {
+        let mut stats = DetectionStats::default();
+        let peaks = self.run(Some(&mut stats), None, None);
+        (peaks, stats)
+    }
+
+    /// Like `find_peaks`, but also returns the peaks suppressed by the distance bounds (see
+    /// `with_min_distance`): the ones that passed every other filter but were too close to (or
+    /// too far from) a higher peak kept. Their properties are computed as for the kept peaks, up
+    /// to the distance filtering (there is no symmetry or label).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use find_peaks::PeakFinder;
+    /// let y = [0., 3., 0., 2., 0., 0., 0., 1., 0.];
+    ///
+    /// let (ps, suppressed) = PeakFinder::new(&y)
+    ///     .with_min_distance(3)
+    ///     .find_peaks_with_suppressed();
+    ///
+    /// assert_eq!(ps.iter().map(|p| p.middle_position()).collect::<Vec<_>>(), vec![1, 7]);
+    /// assert_eq!(suppressed[0].middle_position(), 3);
+    /// ```
+    pub fn find_peaks_with_suppressed(&self) -> (Vec<Peak<T>>, Vec<Peak<T>>) {
+        let mut suppressed = Vec::new();
+        let peaks = self.run(None, Some(&mut suppressed), None);
+        (peaks, suppressed)
+    }
+
+    /// Like `find_peaks`, but also returns the highest and the lowest sample of the data, e.g.
+    /// to normalize the peak heights. They are tracked by the scan for the peak candidates,
+    /// without another pass over the data. Unlike the peaks they include the edges and the
+    /// masked samples; incomparable ones (NaN) are skipped, and without any comparable sample
+    /// there are no extrema.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use find_peaks::PeakFinder;
+    /// let y = [6., 2., 5., 0., 3., 1.];
+    ///
+    /// let (ps, extrema) = PeakFinder::new(&y).find_peaks_with_extrema();
+    /// let extrema = extrema.unwrap();
+    ///
+    /// assert_eq!(ps.len(), 2);
+    /// // the edge sample is the global maximum, but no peak
+    /// assert_eq!((extrema.max_index, extrema.max), (0, 6.));
+    /// assert_eq!((extrema.min_index, extrema.min), (3, 0.));
+    /// ```
+    pub fn find_peaks_with_extrema(&self) -> (Vec<Peak<T>>, Option<GlobalExtrema<T>>) {
+        let mut scan = ExtremaScan::new(&self.y_data);
+        let peaks = self.run(None, None, Some(&mut scan));
+        (peaks, scan.extrema)
+    }
+
+    /// Like `find_peaks`, but each peak comes with the x-value of its middle position,
+    /// `x_data[middle_position()]` (taken modulo the data length for a peak across the seam of
+    /// circular data). With the default x-values this is the middle index.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use find_peaks::PeakFinder;
+    /// let x = [0.5, 1., 1.5, 2., 2.5];
+    /// let y = [0., 2., 0., 1., 0.];
+    ///
+    /// let ps = PeakFinder::new_with_x(&y, &x).find_peaks_x();
+    ///
+    /// assert_eq!(ps.iter().map(|(x, _)| *x).collect::<Vec<_>>(), vec![1., 2.]);
+    /// ```
+    pub fn find_peaks_x(&self) -> Vec<(S, Peak<T>)> {
+        let n = self.x_data.len();
+        self.find_peaks()
+            .into_iter()
+            .map(|p| (self.x_data[p.middle_position() % n].clone(), p))
+            .collect()
+    }
+
+    /// Run `find_peaks` and return the peaks as columns, see `PeakColumns`.
+    pub fn find_peaks_columns(&self) -> PeakColumns<T> {
+        self.find_peaks().into()
+    }
+
+    /// Run `find_peaks` on `y_data[range]` only, with the positions (and valley indices) of
+    /// the peaks in indices of the whole data, e.g. to analyze one region of a long signal
+    /// without slicing the y- and x-values and shifting the results by hand. The data are not
+    /// copied.
+    ///
+    /// The range is searched as data of its own: its first and last samples are its edges and
+    /// the prominences are measured within it, so no sample outside of it is considered. The
+    /// data are not treated as circular. The `with_filter` filters applied before the distance
+    /// bounds and `with_base_fn` are given the range as the data, the other closures see the
+    /// translated peaks.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use find_peaks::PeakFinder;
+    /// let y = [9., 0., 2., 1., 3., 0., 9.];
+    ///
+    /// let mut fp = PeakFinder::new(&y);
+    /// fp.with_min_prominence(0.);
+    /// let ps = fp.find_peaks_in(1..6);
+    ///
+    /// // positions in indices of the whole data
+    /// assert_eq!(ps.iter().map(|p| p.middle_position()).collect::<Vec<_>>(), vec![4, 2]);
+    /// assert_eq!(ps[0].prominence, Some(3.));
+    /// assert_eq!(ps[1].valley_index, Some(1));
+    /// ```
+    pub fn find_peaks_in(&self, range: Range<usize>) -> Vec<Peak<T>> {
+        assert!(
+            range.start <= range.end && range.end <= self.y_data.len(),
+            "Range must be within the data!"
+        );
+
+        let y = Cow::Borrowed(&self.y_data[range.clone()]);
+        let mut finder = self.inner_finder(y, Cow::Borrowed(&self.x_data[range.clone()]));
+        finder.mask = self
+            .mask
+            .as_deref()
+            .map(|mask| Cow::Borrowed(&mask[range.clone()]));
+        finder.measured = self.measured.map(|measured| &measured[range.clone()]);
+        finder.circular = None;
+
+        let mut peaks = finder.find_peaks();
+        let start = range.start;
+        for p in &mut peaks {
+            p.position = p.position.start + start..p.position.end + start;
+            p.valley_index = p.valley_index.map(|i| i + start);
+        }
+        self.finish(peaks, None)
+    }
+
+    /// Run `find_peaks_in` on sliding windows of the data, `y_data[start..start + window]` for
+    /// the starts `0, step, 2 * step, ..`, and return the range of each window with its peaks.
+    /// The windows go on until one reaches the end of the data; that last one is shorter if the
+    /// data do not end at a full window.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use find_peaks::PeakFinder;
+    /// let y = [0., 2., 0., 1., 0., 3., 0., 1., 0.];
+    ///
+    /// let windows = PeakFinder::new(&y).find_peaks_windowed(5, 4);
+    ///
+    /// assert_eq!(windows.len(), 2);
+    /// assert_eq!(windows[1].0, 4..9);
+    /// let middles: Vec<usize> = windows[1].1.iter().map(|p| p.middle_position()).collect();
+    /// assert_eq!(middles, vec![5, 7]);
+    /// ```
+    pub fn find_peaks_windowed(
+        &self,
+        window: usize,
+        step: usize,
+    ) -> Vec<(Range<usize>, Vec<Peak<T>>)> {
+        assert!(window > 0, "Window must be positive!");
+        assert!(step > 0, "Step must be positive!");
+
+        let n = self.y_data.len();
+        let mut windows = Vec::new();
+        let mut start = 0;
+        while start < n {
+            let range = start..(start + window).min(n);
+            let peaks = self.find_peaks_in(range.clone());
+            let at_end = range.end == n;
+            windows.push((range, peaks));
+            if at_end {
+                break;
+            }
+            start += step;
+        }
+        windows
+    }
+
+    /// Run `find_peaks` with each of `configs` in place of the settings of this finder (the
+    /// settings that are not part of a `PeakConfig` are kept) and merge the results, e.g. to find
+    /// both narrow spikes and broad humps with parameters tuned for each.
+    ///
+    /// Peaks whose middle positions are at most `tolerance` apart (in x-units) are duplicates;
+    /// the most prominent of them is kept, or the one of the earlier config if they are equally
+    /// prominent. The heights and prominences are computed for all the peaks, and the merged
+    /// peaks are ordered by the sort order of this finder.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use find_peaks::PeakFinder;
+    /// let y = [0., 5., 0., 1., 2., 3., 2., 1., 0.];
+    ///
+    /// let spikes = PeakFinder::new(&y).with_min_height(4.).config();
+    /// let humps = PeakFinder::new(&y).with_min_prominence(2.).config();
+    /// let ps = PeakFinder::new(&y).find_peaks_multiscale(&[spikes, humps], 1);
+    ///
+    /// // the spike found by both configs is reported once
+    /// assert_eq!(ps.iter().map(|p| p.middle_position()).collect::<Vec<_>>(), vec![1, 5]);
+    /// ```
+    pub fn find_peaks_multiscale(&self, configs: &[PeakConfig<T, S>], tolerance: S) -> Vec<Peak<T>>
+    where
+        S: std::ops::Add<Output = S>,
+    {
+        let mut peaks: Vec<Peak<T>> = configs
+            .iter()
+            .flat_map(|config| {
+                let mut finder = self.clone();
+                finder.set_config(config.clone());
+                finder.compute_all = true;
+                finder.find_peaks()
+            })
+            .collect();
+
+        // stable, so equally prominent peaks stay in the order of the configs
+        peaks.sort_by(|a, b| cmp_descending(&a.prominence, &b.prominence));
+
+        let n = self.y_data.len();
+        let x = |p: &Peak<T>| &self.x_data[p.middle_position() % n];
+        let mut merged: Vec<Peak<T>> = Vec::new();
+        for p in peaks {
+            if merged
+                .iter()
+                .all(|m| self.x_distance(x(m), x(&p)) > tolerance)
+            {
+                merged.push(p);
+            }
+        }
+
+        merged.sort_by(|a, b| self.cmp_heights(a, b));
+        self.sort_peaks(&mut merged);
+        merged
+    }
+
+    /// The peak candidates before any filtering: all the local maxima (and the edge peaks if
+    /// `with_edge_peaks` is set) with their neighbour differences, sorted by position. These
+    /// are the peaks counted by `DetectionStats::local_maxima`; no properties are computed.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use find_peaks::PeakFinder;
+    /// let y = [1., 2., 3., 0., 5., 0.];
+    ///
+    /// let ps = PeakFinder::new(&y).with_min_height(4.).local_maxima();
+    ///
+    /// assert_eq!(ps.len(), 2);
+    /// ```
+    pub fn local_maxima(&self) -> Vec<Peak<T>> {
+        let default_limit = self.default_difference();
+        self.candidates(&default_limit, |_| ()).collect()
+    }
+
+    /// The first differences of the data, `y[i + 1] - y[i]` for each pair of neighbours, as the
+    /// direction and the absolute value of the difference so that they work for unsigned types
+    /// too. A difference between incomparable values (NaN) is `Slope::Flat`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use find_peaks::{PeakFinder, Slope};
+    /// let y: [u8; 4] = [1, 4, 4, 2];
+    ///
+    /// assert_eq!(
+    ///     PeakFinder::new(&y).differences(),
+    ///     vec![(Slope::Rising, 3), (Slope::Flat, 0), (Slope::Falling, 2)]
+    /// );
+    /// ```
+    pub fn differences(&self) -> Vec<(Slope, T)> {
+        self.y_data
+            .windows(2)
+            .map(|pair| difference(&pair[0], &pair[1]))
+            .collect()
+    }
+
+    /// The number of `local_maxima`, counted without collecting them. Together with an empty
+    /// result of `find_peaks` it tells data without any peak candidates from bounds filtering
+    /// out all of them.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use find_peaks::PeakFinder;
+    /// let y = [1., 2., 3., 0., 5., 0.];
+    ///
+    /// let mut fp = PeakFinder::new(&y);
+    /// fp.with_min_height(6.);
+    ///
+    /// assert!(fp.find_peaks().is_empty());
+    /// assert_eq!(fp.local_maxima_count(), 2);
+    /// ```
+    pub fn local_maxima_count(&self) -> usize {
+        let default_limit = self.default_difference();
+        self.candidates(&default_limit, |_| ()).count()
+    }
+
+    /// Estimate the work of `find_peaks` without running it, from one scan for the candidates
+    /// and the settings, e.g. to decide on the settings before running `find_peaks` on huge
+    /// data.
+    ///
+    /// The valley search of the prominences takes linear time in any case (one pass over the
+    /// data in each direction, or at most the prominence window or the maximum scan around each
+    /// candidate), and so do the other bounds. Only the distance bounds may be quadratic: they
+    /// walk along the sorted positions if the x-values are monotonic, but otherwise, and for a
+    /// distance metric or circular data, compare each candidate with every higher peak kept
+    /// so far. Such a configuration is flagged by `quadratic_risk`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use find_peaks::PeakFinder;
+    /// let y = [1., 2., 3., 0., 5., 0., 4., 0.];
+    ///
+    /// let mut fp = PeakFinder::new(&y);
+    /// fp.with_min_prominence(1.).with_min_distance(2);
+    /// let cost = fp.estimate_cost();
+    ///
+    /// assert_eq!(cost.candidates, 3);
+    /// assert!(!cost.quadratic_risk);
+    ///
+    /// fp.with_distance_metric(|a: &usize, b: &usize| a.abs_diff(*b));
+    /// assert!(fp.estimate_cost().quadratic_risk);
+    /// ```
+    pub fn estimate_cost(&self) -> CostEstimate {
+        let x = &self.x_data;
+        let monotonic = x.windows(2).all(|w| w[0] <= w[1]) || x.windows(2).all(|w| w[0] >= w[1]);
+        let pairwise = self.circular.is_some()
+            || self.wrap.is_some()
+            || self.distance_metric.is_some()
+            || !monotonic;
+
+        CostEstimate {
+            candidates: self.local_maxima_count(),
+            quadratic_risk: self.has_distance_bounds() && pairwise,
+        }
+    }
+
+    /// Whether there are any `local_maxima`, stopping the scan at the first one.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use find_peaks::PeakFinder;
+    ///
+    /// assert!(PeakFinder::new(&[0., 1., 0.]).has_any_local_maxima());
+    /// assert!(!PeakFinder::new(&[1., 1., 1.]).has_any_local_maxima());
+    /// ```
+    pub fn has_any_local_maxima(&self) -> bool {
+        let default_limit = self.default_difference();
+        let mut candidates = self.candidates(&default_limit, |_| ());
+        candidates.next().is_some()
+    }
+
+    /// The difference bounds making every local maximum a candidate.
+    fn default_difference(&self) -> Limits<T> {
+        Limits {
+            lower: self.zero.clone(),
+            upper: None,
+        }
+    }
+
+    /// Check the settings for contradictions that would silently filter out every peak: a lower
+    /// bound greater than the upper bound of the height, prominence, difference, plateau size,
+    /// width or distance.
+    ///
+    /// Also check that the x-values are monotonic (non-decreasing or non-increasing) if they are
+    /// used for distances, by the distance, merge distance or width bounds. The distances are the
+    /// absolute differences of the x-values, which are meaningless for e.g. a phase that
+    /// wraps around -- order such data by the phase and use `with_circular` instead. Before
+    /// that, and also with a custom distance metric or an x range, the first x-value not
+    /// comparable to itself (NaN, e.g. a dropped timestamp) is an error: no distance to it is
+    /// meaningful, so the peaks around it would be kept or suppressed arbitrarily.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use find_peaks::{PeakError, PeakFinder};
+    /// let y = [1., 2., 3., 0., 5., 0.];
+    ///
+    /// let mut fp = PeakFinder::new(&y);
+    /// fp.with_min_plateau_size(5).with_max_plateau_size(3);
+    ///
+    /// assert_eq!(fp.validate(), Err(PeakError::InvertedLimits("plateau size")));
+    /// ```
+    pub fn validate(&self) -> Result<(), PeakError> {
+        let inverted = [
+            ("height", self.height.is_inverted()),
+            ("prominence", self.prominence.is_inverted()),
+            ("difference", self.difference.is_inverted()),
+            ("plateau size", self.plateau_size.is_inverted()),
+            ("width", self.width_x.is_inverted()),
+            ("x range", self.x_range.is_inverted()),
+            ("distance", self.distance.is_inverted()),
+        ];
+
+        if let Some((property, _)) = inverted.iter().find(|(_, inverted)| *inverted) {
+            return Err(PeakError::InvertedLimits(property));
+        }
+
+        // a custom metric need not be monotonic in the x-values
+        let uses_distance = !self.distance.is_empty()
+            || self.merge_distance.is_some()
+            || self.min_distance_fraction.is_some();
+        let uses_x_distance =
+            !self.width_x.is_empty() || (uses_distance && self.distance_metric.is_none());
+        let x: &[S] = &self.x_data;
+        if uses_x_distance || uses_distance || !self.x_range.is_empty() {
+            if let Some(index) = x.iter().position(|v| v.partial_cmp(v).is_none()) {
+                return Err(PeakError::UnorderedX { index });
+            }
+        }
+        if uses_x_distance {
+            let rising = x.windows(2).position(|w| w[0] > w[1]);
+            let falling = x.windows(2).position(|w| w[0] < w[1]);
+            if let (Some(r), Some(f)) = (rising, falling) {
+                return Err(PeakError::NonMonotonicX {
+                    index: r.max(f) + 1,
+                });
+            }
+        }
+        Ok(())
+    }
+
+    /// Like `find_peaks`, but checks the settings with `validate` first, and fails if fewer
+    /// peaks than set by `with_min_peaks` are found.
+    pub fn try_find_peaks(&self) -> Result<Vec<Peak<T>>, PeakError> {
+        self.validate()?;
+        let peaks = self.find_peaks();
+        if peaks.len() < self.min_peaks {
+            return Err(PeakError::TooFewPeaks {
+                found: peaks.len(),
+                expected: self.min_peaks,
+            });
+        }
+        Ok(peaks)
+    }
+
+    /// Make `try_find_peaks` fail with `PeakError::TooFewPeaks` if fewer than `k` peaks are left
+    /// after all the bounds, including the distance bounds (default: `0`). `find_peaks` is not
+    /// affected.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use find_peaks::{PeakError, PeakFinder};
+    /// let y = [1., 2., 3., 0., 5., 0.];
+    ///
+    /// let result = PeakFinder::new(&y).with_min_peaks(3).try_find_peaks();
+    ///
+    /// assert_eq!(result, Err(PeakError::TooFewPeaks { found: 2, expected: 3 }));
+    /// ```
+    pub fn with_min_peaks(&mut self, k: usize) -> &mut Self {
+        self.min_peaks = k;
+        self
+    }
+
+    /// Like `find_peaks`, but returns only the middle positions of the peaks, in the same order.
+    ///
+    /// All bounds are applied as usual and only the properties they need are computed. For
+    /// circular data (`with_circular`) the positions are taken modulo the data length.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use find_peaks::PeakFinder;
+    /// let y = [1., 2., 3., 0., 5., 0.];
+    ///
+    /// assert_eq!(PeakFinder::new(&y).find_peak_indices(), vec![4, 2]);
+    /// ```
+    pub fn find_peak_indices(&self) -> Vec<usize> {
+        let n = self.y_data.len();
+        self.run(None, None, None)
+            .iter()
+            .map(|p| p.middle_position() % n)
+            .collect()
+    }
+
+    /// The stages of `find_peaks`, recording the number of peaks after each of them in `stats`,
+    /// collecting the peaks removed by the distance bounds in `suppressed` and visiting the
+    /// samples for the global extrema in `extrema`.
+    fn run(
+        &self,
+        mut stats: Option<&mut DetectionStats>,
+        suppressed: Option<&mut Vec<Peak<T>>>,
+        mut extrema: Option<&mut ExtremaScan<'_, T>>,
+    ) -> Vec<Peak<T>> {
+        if let Some(measured) = self.measured {
+            let data = Cow::Borrowed(&*self.y_data);
+            let mut residual = self.inner_finder(data, Cow::Borrowed(&self.x_data));
+            residual.measured = None;
+            let measured_values = |peaks: &mut [Peak<T>]| {
+                for p in peaks.iter_mut() {
+                    if p.height.is_some() {
+                        p.height = Some(measured[p.position.start].clone());
+                    }
+                    p.valley_value = p.valley_index.map(|i| measured[i].clone());
+                }
+            };
+
+            let mut residual_suppressed = Vec::new();
+            let mut peaks = residual.run(
+                stats.as_deref_mut(),
+                Some(&mut residual_suppressed),
+                extrema,
+            );
+            measured_values(&mut peaks);
+            let peaks = self.finish(peaks, stats);
+            if let Some(suppressed) = suppressed {
+                measured_values(&mut residual_suppressed);
+                suppressed.extend(residual_suppressed);
+            }
+            return peaks;
+        }
+
+        if let Some((resampled, nearest)) = self.resampled(extrema.as_deref_mut()) {
+            let original = |peaks: &mut [Peak<T>]| {
+                for p in peaks.iter_mut() {
+                    let (first, last) = (nearest[p.position.start], nearest[p.position.end - 1]);
+                    p.position = first..last + 1;
+                    p.valley_index = p.valley_index.map(|i| nearest[i]);
+                }
+            };
+
+            let mut resampled_suppressed = Vec::new();
+            let mut peaks =
+                resampled.run(stats.as_deref_mut(), Some(&mut resampled_suppressed), None);
+            original(&mut peaks);
+            let peaks = self.finish(peaks, stats);
+            if let Some(suppressed) = suppressed {
+                original(&mut resampled_suppressed);
+                self.sort_peaks(&mut resampled_suppressed);
+                suppressed.extend(resampled_suppressed);
+            }
+            return peaks;
+        }
+
+        if let Some(reversed) = self.reversed(extrema.as_deref_mut()) {
+            let n = self.y_data.len();
+            let unreverse = |peaks: &mut [Peak<T>]| {
+                for p in peaks.iter_mut() {
+                    p.position = n - p.position.end..n - p.position.start;
+                    std::mem::swap(&mut p.left_diff, &mut p.right_diff);
+                    std::mem::swap(&mut p.left_width, &mut p.right_width);
+                    std::mem::swap(&mut p.left_prominence, &mut p.right_prominence);
+                    p.symmetry = p.symmetry.map(|s| 1. / s);
+                    p.valley_index = p.valley_index.map(|i| n - 1 - i);
+                }
+            };
+
+            let mut reversed_suppressed = Vec::new();
+            let mut peaks =
+                reversed.run(stats.as_deref_mut(), Some(&mut reversed_suppressed), None);
+            unreverse(&mut peaks);
+            let peaks = self.finish(peaks, stats);
+            if let Some(suppressed) = suppressed {
+                unreverse(&mut reversed_suppressed);
+                self.sort_peaks(&mut reversed_suppressed);
+                suppressed.extend(reversed_suppressed);
+            }
+            return peaks;
+        }
+
+        if let Some(transformed) = self.transformed() {
+            let data: &[T] = &self.y_data;
+            // the peaks come sorted by their transformed heights
+            let original = |peaks: &mut [Peak<T>]| {
+                for p in peaks.iter_mut() {
+                    if p.height.is_some() {
+                        p.height = Some(data[p.position.start].clone());
+                    }
+                    p.valley_value = p.valley_index.map(|i| data[i].clone());
+                }
+                peaks.sort_by(|a, b| self.cmp_heights(a, b));
+            };
+
+            let mut transformed_suppressed = Vec::new();
+            let mut peaks = transformed.run(
+                stats.as_deref_mut(),
+                Some(&mut transformed_suppressed),
+                extrema,
+            );
+            original(&mut peaks);
+            let peaks = self.finish(peaks, stats);
+            if let Some(suppressed) = suppressed {
+                original(&mut transformed_suppressed);
+                self.sort_peaks(&mut transformed_suppressed);
+                suppressed.extend(transformed_suppressed);
+            }
+            return peaks;
+        }
+
+        if let Some((rotated, shift)) = self.rotated(extrema.as_deref_mut()) {
+            let n = self.y_data.len();
+            let unrotate = |peaks: &mut [Peak<T>]| {
+                for p in peaks.iter_mut() {
+                    let start = (p.position.start + shift) % n;
+                    p.position = start..start + p.position.len();
+                    // the repeated minimum at the end of the rotated data is at `shift`
+                    p.valley_index = p.valley_index.map(|i| (i + shift) % n);
+                }
+            };
+
+            let mut rotated_suppressed = Vec::new();
+            let mut peaks = rotated.run(stats.as_deref_mut(), Some(&mut rotated_suppressed), None);
+            unrotate(&mut peaks);
+            let peaks = self.finish(peaks, stats);
+            if let Some(suppressed) = suppressed {
+                unrotate(&mut rotated_suppressed);
+                self.sort_peaks(&mut rotated_suppressed);
+                suppressed.extend(rotated_suppressed);
+            }
+            return peaks;
+        }
+
+        // there can be no peaks with less than 3 data points (2 if the edges count)
+        let min_len = if self.edge_peaks || (self.hysteresis.is_some() && !self.persistence) {
+            2
+        } else {
+            3
+        };
+        let visit = |i| {
+            if let Some(extrema) = extrema.as_deref_mut() {
+                extrema.visit(i);
+            }
+        };
+        if self.y_data.len() < min_len {
+            (0..self.y_data.len()).for_each(visit);
+            return Vec::new();
+        }
+
+        if self.persistence {
+            let peaks = self.split_plateaus(self.get_persistent_maxima(visit));
+            if let Some(stats) = stats.as_deref_mut() {
+                stats.local_maxima = peaks.len();
+            }
+            return self.filter_candidates(peaks, stats, suppressed);
+        }
+
+        if let Some((low, high)) = &self.hysteresis {
+            let peaks = self.get_hysteresis_maxima(low, high, visit);
+            if let Some(stats) = stats.as_deref_mut() {
+                stats.local_maxima = peaks.len();
+            }
+            return self.filter_candidates(peaks, stats, suppressed);
+        }
+
+        // counting the local maxima requires a second scan with the default difference bound
+        if let Some(stats) = stats.as_deref_mut() {
+            stats.local_maxima = self.local_maxima_count();
+        }
+
+        let mut peaks = Vec::with_capacity(self.expected_peaks.unwrap_or(0));
+        match &self.plateau_tolerance {
+            Some(tolerance) => {
+                // the inner sides of merged plateaus do not count for the difference bounds
+                let candidates = self.candidates(&self.default_difference(), visit).collect();
+                peaks.extend(
+                    self.merge_plateaus(candidates, tolerance)
+                        .into_iter()
+                        .filter(|p| self.differences_inside(p)),
+                );
+            }
+            None => peaks.extend(self.candidates(&self.difference, visit)),
+        }
+        let peaks = self.split_plateaus(peaks);
+        self.filter_candidates(peaks, stats, suppressed)
+    }
+
+    /// For `with_resample`, a finder over the data linearly interpolated at evenly spaced
+    /// x-values, and for each of its samples the index of the nearest original sample. The
+    /// original samples are visited for `extrema` as they are converted.
+    fn resampled(
+        &self,
+        mut extrema: Option<&mut ExtremaScan<'_, T>>,
+    ) -> Option<(PeakFinder<'_, T, S>, Vec<usize>)> {
+        let n = self.resample?;
+        let (as_f64, from_f64) = self.as_f64.zip(self.from_f64)?;
+        let (x_as_f64, x_from_f64) = self.x_as_f64.zip(self.x_from_f64)?;
+        let x: Vec<f64> = self.x_data.iter().map(x_as_f64).collect();
+        let mut visit = |i| {
+            if let Some(extrema) = extrema.as_deref_mut() {
+                extrema.visit(i);
+            }
+        };
+        let y: Vec<f64> = (self.y_data.iter().enumerate())
+            .map(|(i, y)| {
+                visit(i);
+                as_f64(y)
+            })
+            .collect();
+        let (first, last) = (*x.first()?, *x.last()?);
+
+        let step = (last - first) / (n - 1) as f64;
+        let grid: Vec<f64> = (0..n).map(|k| first + k as f64 * step).collect();
+        let mut data = Vec::with_capacity(n);
+        let mut nearest = Vec::with_capacity(n);
+        for &g in &grid {
+            // the original samples `i` and `j` around `g`
+            let j = x.partition_point(|&xj| xj < g).min(x.len() - 1);
+            let i = j.saturating_sub(1);
+            let value = if x[j] == x[i] {
+                y[j]
+            } else {
+                y[i] + (g - x[i]) / (x[j] - x[i]) * (y[j] - y[i])
+            };
+            data.push(from_f64(value));
+            nearest.push(if g - x[i] <= x[j] - g { i } else { j });
+        }
+        let x_grid: Vec<S> = grid.into_iter().map(x_from_f64).collect();
+
+        let mut resampled = self.inner_finder(Cow::Owned(data), Self::owned_x(x_grid));
+        resampled.mask = self
+            .mask
+            .as_ref()
+            .map(|mask| Cow::Owned(nearest.iter().map(|&i| mask[i]).collect()));
+        resampled.resample = None;
+        Some((resampled, nearest))
+    }
+
+    /// For `with_dog`, `with_abs` and `with_log_amplitude`, a finder over the filtered data,
+    /// their absolute values and their logarithms, in this order, see `inner_finder`.
+    fn transformed(&self) -> Option<PeakFinder<'_, T, S>> {
+        if !self.abs && self.dog.is_none() && self.log_amplitude.is_none() {
+            return None;
+        }
+        let zero = self.zero.clone()?;
+
+        let data = Cow::Borrowed(&*self.y_data);
+        let mut transformed = self.inner_finder(data, Cow::Borrowed(&self.x_data));
+        let conversions = self.as_f64.zip(self.from_f64);
+        if let Some(((sigma1, sigma2), (as_f64, from_f64))) = self.dog.zip(conversions) {
+            let data: Vec<f64> = self.y_data.iter().map(as_f64).collect();
+            let circular = self.circular.is_some();
+            transformed.y_data = Cow::Owned(
+                dog::difference_of_gaussians(&data, sigma1, sigma2, circular)
+                    .into_iter()
+                    .map(from_f64)
+                    .collect(),
+            );
+            transformed.dog = None;
+        }
+        if self.abs {
+            for y in transformed.y_data.to_mut() {
+                if *y < zero {
+                    *y = zero.clone() - y.clone();
+                }
+            }
+            transformed.abs = false;
+        }
+        if let Some((base, (as_f64, from_f64))) = self.log_amplitude.zip(conversions) {
+            let data: Vec<f64> = transformed.y_data.iter().map(as_f64).collect();
+            // the non-positive samples are clamped to the smallest positive one
+            let floor = data
+                .iter()
+                .copied()
+                .filter(|&v| v > 0.)
+                .fold(f64::INFINITY, f64::min);
+            let floor = if floor.is_finite() { floor } else { 1. };
+            transformed.y_data = Cow::Owned(
+                data.into_iter()
+                    .map(|v| from_f64(if v > 0. { v } else { floor }.log(base)))
+                    .collect(),
+            );
+            transformed.log_amplitude = None;
+        }
+        Some(transformed)
+    }
+
+    /// For circular data, a linear finder over the data rotated to start at the (first) global
+    /// minimum, with that minimum repeated at the end, and the rotation.
+    ///
+    /// A minimum can not be part of a peak, so every peak of the circular data is a peak of
+    /// the rotated data, and the prominence valleys search around the seam as well. The search
+    /// for the minimum visits the samples for `extrema`. See `inner_finder`.
+    fn rotated(
+        &self,
+        mut extrema: Option<&mut ExtremaScan<'_, T>>,
+    ) -> Option<(PeakFinder<'_, T, S>, usize)> {
+        let (data, x_data) = (&self.y_data, &self.x_data);
+        let wrap = self.circular.clone()?;
+
+        let mut visit = |i| {
+            if let Some(extrema) = extrema.as_deref_mut() {
+                extrema.visit(i);
+            }
+        };
+        visit(0);
+        let shift = (1..data.len()).fold(0, |m, i| {
+            visit(i);
+            if data[i] < data[m] {
+                i
+            } else {
+                m
+            }
+        });
+        let order = || (shift..data.len()).chain(0..=shift);
+        let x: Vec<S> = order().map(|i| x_data[i].clone()).collect();
+        let y = order().map(|i| data[i].clone()).collect();
+
+        let mut rotated = self.inner_finder(Cow::Owned(y), Self::owned_x(x));
+        rotated.mask = self
+            .mask
+            .as_ref()
+            .map(|mask| Cow::Owned(order().map(|i| mask[i]).collect()));
+        rotated.circular = None;
+        rotated.edge_peaks = false;
+        rotated.wrap = Some(wrap);
+        Some((rotated, shift))
+    }
+
+    /// A copy of the finder on the reversed data for `Direction::Reverse`, see
+    /// `inner_finder`. The samples are visited for `extrema` as they are copied.
+    fn reversed(
+        &self,
+        mut extrema: Option<&mut ExtremaScan<'_, T>>,
+    ) -> Option<PeakFinder<'_, T, S>> {
+        if self.direction == Direction::Forward {
+            return None;
+        }
+        let mut visit = |i| {
+            if let Some(extrema) = extrema.as_deref_mut() {
+                extrema.visit(i);
+            }
+        };
+        let y = (self.y_data.iter().enumerate().rev())
+            .map(|(i, y)| {
+                visit(i);
+                y.clone()
+            })
+            .collect();
+        let x: Vec<S> = self.x_data.iter().rev().cloned().collect();
+
+        let mut reversed = self.inner_finder(Cow::Owned(y), Self::owned_x(x));
+        reversed.mask = self
+            .mask
+            .as_ref()
+            .map(|mask| Cow::Owned(mask.iter().rev().copied().collect()));
+        reversed.direction = Direction::Forward;
+        reversed.tie_break = match self.tie_break {
+            TieBreak::LeftmostFirst => TieBreak::RightmostFirst,
+            TieBreak::RightmostFirst => TieBreak::LeftmostFirst,
+        };
+        Some(reversed)
+    }
+
+    /// A copy of the finder on other y- and x-values (a part of the data, the residual, the
+    /// resampled, rotated or reversed data), whose peaks are brought back to this finder's data and
+    /// passed to `finish`. The labeler, the maximum number of peaks and a filter run after the
+    /// distance bounds are left out, as `finish` applies them to the peaks in this data.
+    fn inner_finder<'b>(&'b self, y: Cow<'b, [T]>, x: Cow<'b, [S]>) -> PeakFinder<'b, T, S> {
+        let mut inner: PeakFinder<'b, T, S> = self.clone();
+        inner.y_data = y;
+        inner.x_data = x;
+        inner.labeler = None;
+        inner.max_peaks = None;
+        if self.filter.as_ref().is_some_and(|(_, before)| !before) {
+            inner.filter = None;
+        }
+        inner
+    }
+
+    /// `x` as the x-values of a finder. The owned form of `[S]` is only known through
+    /// `ToOwned`, so `x` is converted through it.
+    fn owned_x<'b>(x: Vec<S>) -> Cow<'b, [S]> {
+        Cow::Owned(x.as_slice().to_owned())
+    }
+
+    /// The filtering stages of `find_peaks` applied to candidates that are already within the
+    /// difference bounds.
+    fn filter_candidates(
+        &self,
+        peaks: Vec<Peak<T>>,
+        mut stats: Option<&mut DetectionStats>,
+        suppressed: Option<&mut Vec<Peak<T>>>,
+    ) -> Vec<Peak<T>> {
+        let mut record = |stage: fn(&mut DetectionStats) -> &mut usize, n: usize| {
+            if let Some(stats) = stats.as_deref_mut() {
+                *stage(stats) = n;
+            }
+        };
+
+        record(|s| &mut s.after_difference, peaks.len());
+        let peaks = self.filter_masked(peaks);
+        record(|s| &mut s.after_mask, peaks.len());
+        let peaks = self.filter_sides(peaks);
+        record(|s| &mut s.after_sides, peaks.len());
+        let peaks = self.filter_runs(peaks);
+        record(|s| &mut s.after_runs, peaks.len());
+        let peaks = self.filter_plateau(peaks);
+        record(|s| &mut s.after_plateau_size, peaks.len());
+        let peaks = self.filter_x_range(peaks);
+        record(|s| &mut s.after_x_range, peaks.len());
+        let peaks = self.filter_width_x(peaks);
+        record(|s| &mut s.after_width_x, peaks.len());
+        let peaks = self.filter_local_window(peaks);
+        record(|s| &mut s.after_local_window, peaks.len());
+        let peaks = self.filter_local_contrast(peaks);
+        record(|s| &mut s.after_local_contrast, peaks.len());
+        let peaks = self.filter_clipped(peaks);
+        record(|s| &mut s.after_clipped, peaks.len());
+        let peaks = self.filter_apex_height(peaks);
+        record(|s| &mut s.after_apex_height, peaks.len());
+        let peaks = self.filter_height(peaks);
+        record(|s| &mut s.after_height, peaks.len());
+        let peaks = if self.distance_first {
+            let (peaks, removed) = self.filter_close(peaks, suppressed);
+            record(|s| &mut s.after_distance, peaks.len());
+            record(|s| &mut s.suppressed_by_distance, removed);
+            let peaks = self.filter_prominence(peaks);
+            record(|s| &mut s.after_prominence, peaks.len());
+            let peaks = self.filter_prominence_ratio(peaks);
+            record(|s| &mut s.after_prominence_ratio, peaks.len());
+            peaks
+        } else {
+            let peaks = self.filter_prominence(peaks);
+            record(|s| &mut s.after_prominence, peaks.len());
+            let peaks = self.filter_prominence_ratio(peaks);
+            record(|s| &mut s.after_prominence_ratio, peaks.len());
+            let (peaks, removed) = self.filter_close(peaks, suppressed);
+            record(|s| &mut s.after_distance, peaks.len());
+            record(|s| &mut s.suppressed_by_distance, removed);
+            peaks
+        };
+
+        let mut peaks = self.add_shape(peaks);
+        self.reference_heights(&mut peaks);
+        self.finish(peaks, stats)
+    }
+
+    /// Rank `peaks` by prominence, or by height if not all of them have one, for `with_rank`.
+    fn add_ranks(&self, peaks: &mut [Peak<T>]) {
+        if !self.compute_rank {
+            return;
+        }
+
+        let by_prominence = peaks.iter().all(|p| p.prominence.is_some());
+        let mut order: Vec<usize> = (0..peaks.len()).collect();
+        order.sort_by(|&a, &b| {
+            let (a, b) = (&peaks[a], &peaks[b]);
+            if by_prominence {
+                cmp_descending(&a.prominence, &b.prominence).then(self.cmp_positions(a, b))
+            } else {
+                self.cmp_heights(a, b)
+            }
+        });
+        for (rank, k) in order.into_iter().enumerate() {
+            peaks[k].rank = Some(rank);
+        }
+    }
+
+    /// The filters after the distance bounds, the `with_max_peaks` selection, the ranks, the
+    /// labels and the sort order, the last stages of `find_peaks`.
+    fn finish(&self, peaks: Vec<Peak<T>>, stats: Option<&mut DetectionStats>) -> Vec<Peak<T>> {
+        let peaks = self.filter_user(peaks, false);
+        let filtered = peaks.len();
+        let mut peaks = self.select_highest(peaks);
+        if let Some(stats) = stats {
+            stats.after_filter = filtered;
+            stats.after_max_peaks = peaks.len();
+        }
+        self.add_ranks(&mut peaks);
+        if let Some(labeler) = &self.labeler {
+            for p in &mut peaks {
+                p.label = Some(labeler(p));
+            }
+        }
+        self.sort_peaks(&mut peaks);
+        peaks
+    }
+
+    /// Apply the filters before the distance bounds and the distance bounds, adding the peaks
+    /// removed by the distance to `suppressed`. Also returns the number of those.
+    fn filter_close(
+        &self,
+        peaks: Vec<Peak<T>>,
+        suppressed: Option<&mut Vec<Peak<T>>>,
+    ) -> (Vec<Peak<T>>, usize) {
+        let peaks = self.filter_user(peaks, true);
+        if peaks.is_empty() {
+            return (peaks, 0);
+        }
+
+        let (peaks, mut removed) = self.split_by_distance(self.merge_close(peaks));
+        let n_removed = removed.len();
+        if let Some(suppressed) = suppressed {
+            self.reference_heights(&mut removed);
+            self.sort_peaks(&mut removed);
+            suppressed.extend(removed);
+        }
+        (peaks, n_removed)
+    }
+
+    /// Drop the peaks containing a sample excluded by `with_exclusion_mask`.
+    fn filter_masked(&self, peaks: Vec<Peak<T>>) -> Vec<Peak<T>> {
+        match &self.mask {
+            Some(mask) => peaks
+                .into_iter()
+                .filter(|p| !mask[p.position.clone()].contains(&true))
+                .collect(),
+            None => peaks,
+        }
+    }
+
+    /// Check the differences of `peaks` against the side thresholds. The outer side of an edge
+    /// peak has no neighbour and is not checked.
+    fn filter_sides(&self, peaks: Vec<Peak<T>>) -> Vec<Peak<T>> {
+        let (left, right) = &self.side_thresholds;
+        let (left_range, right_range) = (&self.left_difference, &self.right_difference);
+        let relative = self.min_relative_difference.zip(self.as_f64);
+        if left.is_none()
+            && right.is_none()
+            && left_range.is_empty()
+            && right_range.is_empty()
+            && relative.is_none()
+        {
+            return peaks;
+        }
+        let n = self.y_data.len();
+        let accepted = |threshold: &Option<T>, range: &Limits<T>, diff: &T, edge: bool| {
+            edge || (threshold.as_ref().is_none_or(|t| t <= diff) && range.is_inside(diff))
+        };
+        // the minimum of `with_min_difference_relative` for a peak starting at `i`
+        let relative_min = |i: usize| {
+            relative.map(|(frac, as_f64)| {
+                let offset = self.height_offset.as_ref().map_or(0., as_f64);
+                frac * (as_f64(&self.y_data[i]) - offset)
+            })
+        };
+        let steep = |min: Option<f64>, diff: &T, edge: bool| {
+            edge || min
+                .zip(self.as_f64)
+                .is_none_or(|(min, as_f64)| as_f64(diff) >= min)
+        };
+
+        peaks
+            .into_iter()
+            .filter(|p| {
+                let (left_edge, right_edge) = (p.position.start == 0, p.position.end == n);
+                let min = relative_min(p.position.start);
+                accepted(left, left_range, &p.left_diff, left_edge)
+                    && accepted(right, right_range, &p.right_diff, right_edge)
+                    && steep(min, &p.left_diff, left_edge)
+                    && steep(min, &p.right_diff, right_edge)
+            })
+            .collect()
+    }
+
+    /// Drop the peaks without the monotonic runs of `with_min_rise_run` and
+    /// `with_min_fall_run`. The outer side of an edge peak is not checked.
+    fn filter_runs(&self, peaks: Vec<Peak<T>>) -> Vec<Peak<T>> {
+        let (rise, fall) = self.min_runs;
+        if rise.is_none() && fall.is_none() {
+            return peaks;
+        }
+        let y = &self.y_data;
+        let n = y.len();
+        // whether the step from `a` to the higher-or-equal `b` continues the run
+        let steps = |a: &T, b: &T| a < b || (self.run_plateaus && a == b);
+        // the number of steps, up to `max`, of the run towards `i` from the samples `next`
+        let run = |i: usize, max: usize, next: &dyn Fn(usize) -> Option<usize>| {
+            let mut len = 0;
+            let mut i = i;
+            while len < max {
+                match next(i) {
+                    Some(j) if steps(&y[j], &y[i]) => {
+                        len += 1;
+                        i = j;
+                    }
+                    _ => break,
+                }
+            }
+            len
+        };
+
+        peaks
+            .into_iter()
+            .filter(|p| {
+                let (start, last) = (p.position.start, p.position.end - 1);
+                rise.is_none_or(|r| start == 0 || run(start, r, &|i| i.checked_sub(1)) == r)
+                    && fall.is_none_or(|f| {
+                        p.position.end == n
+                            || run(last, f, &|i| Some(i + 1).filter(|&j| j < n)) == f
+                    })
+            })
+            .collect()
+    }
+
+    /// Apply the predicate of `with_filter` if it is set to run at this stage, before or after
+    /// the distance bounds. The heights are always shown to it relative to `height_offset`.
+    fn filter_user(&self, peaks: Vec<Peak<T>>, before_distance: bool) -> Vec<Peak<T>> {
+        let filter = match &self.filter {
+            Some((filter, before)) if *before == before_distance => filter,
+            _ => return peaks,
+        };
+        let data: &[T] = &self.y_data;
+
+        peaks
+            .into_iter()
+            .filter(|p| {
+                if before_distance && self.height_offset.is_some() {
+                    let mut referenced = p.clone();
+                    self.reference_heights(std::slice::from_mut(&mut referenced));
+                    filter(&referenced, data)
+                } else {
+                    filter(p, data)
+                }
+            })
+            .collect()
+    }
+
+    /// Measure the heights of `peaks` from `height_offset`. All of them passed `filter_height`,
+    /// so none is below the offset.
+    fn reference_heights(&self, peaks: &mut [Peak<T>]) {
+        if let Some(offset) = &self.height_offset {
+            for p in peaks {
+                p.height = p.height.take().map(|h| h - offset.clone());
+            }
+        }
+    }
+
+    /// The `max_peaks` highest of `peaks`, sorted by height, or `peaks` if it is not set. A
+    /// max-heap of the kept peaks has the lowest of them on top, to be replaced by any higher
+    /// peak.
+    fn select_highest(&self, peaks: Vec<Peak<T>>) -> Vec<Peak<T>> {
+        let n = match self.max_peaks {
+            Some(n) => n,
+            None => return peaks,
+        };
+        let cmp = |a: &Peak<T>, b: &Peak<T>| self.cmp_heights(a, b);
+
+        let mut heap = BinaryHeap::with_capacity(n.min(peaks.len()));
+        for peak in peaks {
+            let entry = HeapEntry { peak, cmp: &cmp };
+            if heap.len() < n {
+                heap.push(entry);
+            } else if let Some(mut lowest) = heap.peek_mut() {
+                if cmp(&entry.peak, &lowest.peak) == std::cmp::Ordering::Less {
+                    *lowest = entry;
+                }
+            }
+        }
+        heap.into_sorted_vec()
+            .into_iter()
+            .map(|entry| entry.peak)
+            .collect()
+    }
+
+    /// Order of peaks by height, the highest first and the NaN heights last, and equally high
+    /// ones by `tie_break`. It is total, as no two peaks start at the same sample.
+    fn cmp_heights(&self, a: &Peak<T>, b: &Peak<T>) -> std::cmp::Ordering {
+        cmp_descending(&a.height, &b.height).then(self.cmp_positions(a, b))
+    }
+
+    /// Order of peaks by position as set by `tie_break`.
+    fn cmp_positions(&self, a: &Peak<T>, b: &Peak<T>) -> std::cmp::Ordering {
+        match self.tie_break {
+            TieBreak::LeftmostFirst => a.position.start.cmp(&b.position.start),
+            TieBreak::RightmostFirst => b.position.start.cmp(&a.position.start),
+        }
+    }
+
+    /// Reorder `peaks`, which are sorted by height, by `sort_order`.
+    fn sort_peaks(&self, peaks: &mut [Peak<T>]) {
+        if self.sort_order == SortOrder::Position {
+            peaks.sort_by_key(|p| p.position.start);
+        }
+    }
+
+    /// Store the properties derived from the half-prominence crossings in `peaks`: the symmetry
+    /// if `with_symmetry` is set, the left and right widths and the sharpness if `with_widths`
+    /// is set, and the score if `with_score` is set.
+    ///
+    /// The properties are computed together, so the crossings of each peak are searched only
+    /// once, and the prominence only for the peaks that do not have one yet.
+    fn add_shape(&self, mut peaks: Vec<Peak<T>>) -> Vec<Peak<T>> {
+        let as_f64 = match self.as_f64 {
+            Some(as_f64)
+                if self.compute_symmetry || self.compute_widths || self.score_weights.is_some() =>
+            {
+                as_f64
+            }
+            _ => return peaks,
+        };
+
+        let missing: Vec<Range<usize>> = peaks
+            .iter()
+            .filter(|p| p.prominence.is_none())
+            .map(|p| p.position.clone())
+            .collect();
+        let mut missing_proms = self.calc_prominences(&missing).into_iter();
+
+        // the score normalizes by the span of the data
+        let score = self.score_weights.map(|weights| {
+            let (min, max) = self
+                .y_data
+                .iter()
+                .map(as_f64)
+                .filter(|y| !y.is_nan())
+                .fold((f64::INFINITY, f64::NEG_INFINITY), |(lo, hi), y| {
+                    (lo.min(y), hi.max(y))
+                });
+            (weights, min, max - min)
+        });
+
+        for p in peaks.iter_mut() {
+            let prom = match &p.prominence {
+                Some(prom) => as_f64(prom),
+                // one per peak without a prominence, in order
+                None => as_f64(&missing_proms.next().unwrap()),
+            };
+            let crossings = self.half_crossings(&p.position, prom, as_f64);
+            let widths = crossings.and_then(|crossings| half_widths(&p.position, crossings));
+
+            if self.compute_symmetry {
+                p.symmetry = widths.map(|(left, right)| left / right);
+            }
+            if let Some(x_as_f64) = self.x_as_f64.filter(|_| self.compute_widths) {
+                let widths_x =
+                    crossings.map(|crossings| self.half_widths_x(&p.position, crossings, x_as_f64));
+                p.left_width = widths_x.map(|(left, _)| left);
+                p.right_width = widths_x.map(|(_, right)| right);
+                if self.compute_sharpness || p.prominence.is_some() {
+                    p.sharpness = widths_x
+                        .map(|(left, right)| left + right)
+                        .filter(|&width| width > 0.)
+                        .map(|width| prom / width);
+                }
+            }
+            if let Some((weights, min, range)) = score {
+                let height = (as_f64(&self.y_data[p.position.start]) - min) / range;
+                let sharpness = widths.map(|(left, right)| 1. / (left + right).max(1.));
+
+                let mut terms = vec![(weights.height, height), (weights.prominence, prom / range)];
+                terms.extend(sharpness.map(|sharpness| (weights.sharpness, sharpness)));
+                let total: f64 = terms.iter().map(|(w, _)| w).sum();
+                p.score =
+                    (total > 0.).then(|| terms.iter().map(|(w, v)| w * v).sum::<f64>() / total);
+            }
+        }
+        peaks
+    }
+
+    /// Distances (in x-units) from the middle of the peak at `position` to its half-prominence
+    /// `crossings` (see `half_crossings`), a crossing that is not found before an edge of the
+    /// data clamped to the edge sample.
+    fn half_widths_x(
+        &self,
+        position: &Range<usize>,
+        (left, right): (Option<f64>, Option<f64>),
+        x_as_f64: fn(&S) -> f64,
+    ) -> (f64, f64) {
+        let last = self.y_data.len() - 1;
+
+        // x at the fractional index `i`, linearly interpolated between the samples
+        let x_data: &[S] = &self.x_data;
+        let x = |i: f64| {
+            let (lo, hi) = (i.floor() as usize, i.ceil() as usize);
+            let x_lo = x_as_f64(&x_data[lo]);
+            x_lo + (i - lo as f64) * (x_as_f64(&x_data[hi]) - x_lo)
+        };
+
+        let middle = x((position.start + position.end - 1) as f64 / 2.);
+        let left = x(left.unwrap_or(0.));
+        let right = x(right.unwrap_or(last as f64));
+        ((middle - left).abs(), (right - middle).abs())
+    }
+
+    /// Fractional indices of the linearly interpolated crossings of the half-prominence level
+    /// to the left and to the right of the peak at `position`, each `None` if the level is not
+    /// crossed before an edge of the data. `None` for a non-positive prominence.
+    fn half_crossings(
+        &self,
+        position: &Range<usize>,
+        prominence: f64,
+        as_f64: fn(&T) -> f64,
+    ) -> Option<(Option<f64>, Option<f64>)> {
+        if prominence <= 0. {
+            return None;
+        }
+
+        let data: &[T] = &self.y_data;
+        let y = |i: usize| as_f64(&data[i]);
+        let level = y(position.start) - prominence / 2.;
+
+        let left = (0..position.start)
+            .rev()
+            .find(|&i| y(i) <= level)
+            .map(|l| l as f64 + (level - y(l)) / (y(l + 1) - y(l)));
+        let right = (position.end..data.len())
+            .find(|&i| y(i) <= level)
+            .map(|r| r as f64 - (level - y(r)) / (y(r - 1) - y(r)));
+        Some((left, right))
+    }
+
+    /// Like `find_peaks`, but the candidates are the points where the first difference of the
+    /// data crosses from positive to negative, instead of the neighbour comparison of
+    /// `find_peaks`. A run of zero differences at the crossing makes the peak a plateau spanning
+    /// the run, while a run between two rises (or two falls) is no peak.
+    ///
+    /// All the other bounds are applied to the original data as usual.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use find_peaks::PeakFinder;
+    /// let y = [0., 1., 1., 2., 2., 2., 0., 3., 3., 4.];
+    ///
+    /// let ps = PeakFinder::new(&y).find_peaks_derivative();
+    ///
+    /// assert_eq!(ps.len(), 1);
+    /// assert_eq!(ps[0].position, 3..6);
+    /// ```
+    pub fn find_peaks_derivative(&self) -> Vec<Peak<T>> {
+        let min_len = if self.edge_peaks { 2 } else { 3 };
+        if self.y_data.len() < min_len {
+            return Vec::new();
+        }
+
+        let (left_edge, right_edge) = if self.edge_peaks {
+            self.get_edge_maxima(&self.difference)
+        } else {
+            (None, None)
+        };
+        let peaks = left_edge
+            .into_iter()
+            .chain(self.get_derivative_maxima())
+            .chain(right_edge)
+            .collect();
+
+        self.filter_candidates(peaks, None, None)
+    }
+
+    /// Like `find_peaks`, but finds the troughs (local minima) of the data.
+    ///
+    /// The peaks are searched in the data reflected at its maximum, `max - y` (which does not go
+    /// below zero for unsigned types), so the prominence of a trough is its depth below the
+    /// lower of the ridges around it and the distance bounds prefer deeper troughs. The troughs
+    /// are sorted by depth, deepest first (unless sorted by position), their height is the value
+    /// of the trough and the height bounds apply to that value. Their valley (`valley_index`) is
+    /// the higher of the two ridges.
+    ///
+    /// The hysteresis and a fixed prominence reference are not applied to troughs.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use find_peaks::PeakFinder;
+    /// let y = [5., 2., 4., 0., 3.];
+    ///
+    /// let ts = PeakFinder::new(&y).with_min_prominence(1.).find_troughs();
+    ///
+    /// assert_eq!(ts.iter().map(|t| t.middle_position()).collect::<Vec<_>>(), vec![3, 1]);
+    /// assert_eq!(ts[0].height, Some(0.));
+    /// assert_eq!(ts[0].prominence, Some(3.));
+    /// ```
+    pub fn find_troughs(&self) -> Vec<Peak<T>> {
+        let data: &[T] = &self.y_data;
+        let max = match data.iter().filter(|y| y.partial_cmp(y).is_some()).fold(
+            None,
+            |m: Option<&T>, y| if m.is_none_or(|m| y > m) { Some(y) } else { m },
+        ) {
+            Some(max) => max.clone(),
+            None => return Vec::new(),
+        };
+        let reflect = |v: &T| max.clone() - v.clone();
+
+        let mut reflected = self.clone();
+        reflected.y_data = Cow::Owned(data.iter().map(reflect).collect());
+        // `lower <= y <= upper` is `max - upper <= max - y <= max - lower`
+        if self.height.lower.as_ref().is_some_and(|lower| lower > &max) {
+            return Vec::new();
+        }
+        reflected.height = Limits {
+            lower: self
+                .height
+                .upper
+                .as_ref()
+                .filter(|&u| u < &max)
+                .map(reflect),
+            upper: self.height.lower.as_ref().map(reflect),
+        };
+        reflected.hysteresis = None;
+        reflected.height_offset = None;
+        reflected.saturation_level = None;
+        if let ProminenceRef::Fixed(_) = reflected.prominence_reference {
+            reflected.prominence_reference = ProminenceRef::LocalValleys;
+        }
+        reflected.labeler = None;
+
+        let mut troughs = reflected.run(None, None, None);
+        for t in &mut troughs {
+            t.height = Some(data[t.position.start % data.len()].clone());
+            t.valley_value = t.valley_index.map(|i| data[i % data.len()].clone());
+            if let Some(labeler) = &self.labeler {
+                t.label = Some(labeler(t));
+            }
+        }
+        troughs
+    }
+
+    /// Shoulders of the data sorted by position: the sub-peaks of overlapping peaks that merge
+    /// into the flank of a larger one, so that the data never turn downward (or upward) there.
+    ///
+    /// A shoulder is where the second difference changes sign on a monotonic flank, i.e. a run
+    /// of equal steps that are flatter than the steps before and after it, which go in the same
+    /// direction. Its position is the higher end of the flattest step, or the whole step if it is
+    /// flat (a stair of a staircase). The differences are to the neighbouring samples and
+    /// `is_shoulder` is set.
+    ///
+    /// Only the height bounds and the x range are applied and the heights are always computed.
+    /// Shoulders have no prominence, as no valley separates them from the peak they lean on.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use find_peaks::PeakFinder;
+    /// let y = [0., 6., 10., 8., 7., 6., 3., 0.];
+    ///
+    /// let ss = PeakFinder::new(&y).find_shoulders();
+    ///
+    /// assert_eq!(ss.len(), 1);
+    /// assert_eq!(ss[0].position, 3..4);
+    /// assert!(ss[0].is_shoulder);
+    /// ```
+    pub fn find_shoulders(&self) -> Vec<Peak<T>> {
+        let shoulders = self.filter_x_range(self.get_shoulders());
+        let limit = &self.height;
+        shoulders
+            .into_iter()
+            .filter_map(|mut p| {
+                let h = match &self.height_offset {
+                    None => self.y_data[p.position.start].clone(),
+                    Some(offset) if offset <= &self.y_data[p.position.start] => {
+                        self.y_data[p.position.start].clone() - offset.clone()
+                    }
+                    Some(_) => return None,
+                };
+                if limit.is_inside(&h) {
+                    p.add_height(h);
+                    Some(p)
+                } else {
+                    None
+                }
+            })
+            .collect()
+    }
+
+    /// Outputs the local maxima and minima of the data sorted by position, so that consecutive
+    /// elements trace the zigzag of the series.
+    ///
+    /// Only the difference and plateau bounds are applied; no other properties are calculated.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use find_peaks::{ExtremumKind, PeakFinder};
+    /// let y = [1., 2., 3., 0., 5., 0.];
+    ///
+    /// let es = PeakFinder::new(&y).find_extrema();
+    ///
+    /// assert_eq!(
+    ///    es.iter().map(|e| (e.peak.middle_position(), e.kind)).collect::<Vec<_>>(),
+    ///    vec![(2, ExtremumKind::Maximum), (3, ExtremumKind::Minimum), (4, ExtremumKind::Maximum)]
+    /// );
+    /// ```
+    pub fn find_extrema(&self) -> Vec<Extremum<T>> {
+        if self.y_data.len() < 3 {
+            return Vec::new();
+        }
+
+        let limit = &self.plateau_size;
+        let mut extrema = self.get_turning_points(&self.difference);
+        if !limit.is_empty() {
+            extrema.retain(|e| limit.is_inside(&e.peak.position.len()));
+        }
+        extrema
+    }
+
+    /// The local maxima and the local minima of `find_extrema`, separately, each sorted by
+    /// position. Both come from a single scan of the data, which is cheaper than running
+    /// `find_peaks` (or `find_extrema`'s maxima) on the data and on its negation.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use find_peaks::PeakFinder;
+    /// let y = [1., 2., 3., 0., 5., 0., 0., 1.];
+    ///
+    /// let (peaks, valleys) = PeakFinder::new(&y).find_peaks_and_valleys();
+    ///
+    /// assert_eq!(peaks.iter().map(|p| p.middle_position()).collect::<Vec<_>>(), vec![2, 4]);
+    /// // the valley at 5..7 has a middle position of 6
+    /// assert_eq!(valleys.iter().map(|p| p.middle_position()).collect::<Vec<_>>(), vec![3, 6]);
+    /// ```
+    pub fn find_peaks_and_valleys(&self) -> (Vec<Peak<T>>, Vec<Peak<T>>) {
+        let (maxima, minima): (Vec<Extremum<T>>, Vec<Extremum<T>>) = self
+            .find_extrema()
+            .into_iter()
+            .partition(|e| e.kind == ExtremumKind::Maximum);
+
+        let peaks = |extrema: Vec<Extremum<T>>| extrema.into_iter().map(|e| e.peak).collect();
+        (peaks(maxima), peaks(minima))
+    }
+
+    /// Like `find_peaks`, but the prominence of every peak is computed globally by topological
+    /// persistence (see below) instead of the valley search around each peak, and it is always
+    /// part of the output.
+    ///
+    /// The samples are visited from the highest to the lowest while tracking the connected
+    /// components of visited samples. Each component is born at a peak and dies when it merges with
+    /// a component with a higher peak; the persistence is the peak's height above the merging
+    /// level. This is the same as prominence, except for peaks of equal height: the leftmost of
+    /// them is considered the higher one, while `find_peaks` (and scipy) let the valley search pass
+    /// through equally high samples. The valleys (`valley_index`) and `bounded_by_edge` are not
+    /// computed, the settings of the valley search (the prominence window, reference and maximum
+    /// scan, the local baseline and `with_base_fn`) do not apply, and the mask only drops the peaks
+    /// containing masked samples. Peaks merged by `with_merge_distance` get the prominence of the
+    /// valley search for their span. All the other settings apply as for `find_peaks`.
+    pub fn find_peaks_persistence(&self) -> Vec<Peak<T>> {
+        let mut persistent = self.clone();
+        persistent.persistence = true;
+        persistent.run(None, None, None)
+    }
+
+    /// Index of the lowest sample between each pair of consecutive `peaks`, which have to be
+    /// sorted by position (note that `find_peaks` sorts them by height). The leftmost index is
+    /// returned if two samples are equally low; for touching peaks the valley is the first index
+    /// of the right peak.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use find_peaks::PeakFinder;
+    /// let y = [0., 3., 1., 2., 5., 0.5, 4., 0.];
+    ///
+    /// let fp = PeakFinder::new(&y);
+    /// let mut ps = fp.find_peaks();
+    /// ps.sort_by_key(|p| p.position.start);
+    ///
+    /// assert_eq!(fp.valleys_between(&ps), vec![2, 5]);
+    /// ```
+    pub fn valleys_between(&self, peaks: &[Peak<T>]) -> Vec<usize> {
+        let data: &[T] = &self.y_data;
+
+        peaks
+            .windows(2)
+            .map(|pair| {
+                let (start, end) = (pair[0].position.end, pair[1].position.start);
+                (start..end).fold(
+                    end,
+                    |m, i| {
+                        if m == end || data[i] < data[m] {
+                            i
+                        } else {
+                            m
+                        }
+                    },
+                )
+            })
+            .collect()
+    }
+
+    /// Partition the data into one segment per peak found by `find_peaks`, for slicing the data
+    /// into per-peak regions: the segments are bounded by the `valleys_between` consecutive
+    /// peaks, each valley starting the segment to its right, and the outer segments reach the
+    /// edges of the data. The peaks are returned with their segments in order of position.
+    ///
+    /// Unlike the bases of the prominences, which overlap for a peak on the flank of a higher
+    /// one, the segments do not overlap and cover all the data. The data are taken as linear,
+    /// also if `with_circular` is set.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use find_peaks::PeakFinder;
+    /// let y = [0., 3., 1., 2., 5., 0.5, 4., 0.];
+    ///
+    /// let segments = PeakFinder::new(&y).segment_by_peaks();
+    ///
+    /// let ranges: Vec<_> = segments.iter().map(|(range, _)| range.clone()).collect();
+    /// assert_eq!(ranges, vec![0..2, 2..5, 5..8]);
+    /// assert_eq!(segments[1].1.position, 4..5);
+    /// ```
+    pub fn segment_by_peaks(&self) -> Vec<(Range<usize>, Peak<T>)> {
+        let mut peaks = self.find_peaks();
+        peaks.sort_by_key(|p| p.position.start);
+
+        let valleys = self.valleys_between(&peaks);
+        let starts = std::iter::once(0).chain(valleys.iter().copied());
+        let ends = valleys
+            .iter()
+            .copied()
+            .chain(std::iter::once(self.y_data.len()));
+
+        starts
+            .zip(ends)
+            .map(|(start, end)| start..end)
+            .zip(peaks)
+            .collect()
+    }
+
+    /// Upper envelope of the data: the heights of the peaks found by `find_peaks`, linearly
+    /// interpolated between their middle positions and held constant before the first and after
+    /// the last one. Empty if there are no peaks.
+    fn upper_envelope(&self) -> Vec<f64>
+    where
+        T: Into<f64>,
+    {
+        let mut knots: Vec<(usize, f64)> = self
+            .find_peaks()
+            .iter()
+            .map(|p| (p.middle_position(), to_f64(&self.y_data[p.position.start])))
+            .collect();
+        knots.sort_unstable_by_key(|&(i, _)| i);
+
+        let (first, last) = match (knots.first(), knots.last()) {
+            (Some(&first), Some(&last)) => (first, last),
+            _ => return Vec::new(),
+        };
+
+        let mut envelope = vec![first.1; first.0];
+        for pair in knots.windows(2) {
+            let ((i0, y0), (i1, y1)) = (pair[0], pair[1]);
+            let slope = (y1 - y0) / (i1 - i0) as f64;
+            envelope.extend((0..i1 - i0).map(|k| y0 + slope * k as f64));
+        }
+        envelope.resize(self.y_data.len(), last.1);
+        envelope
+    }
+
+    /// Find the peaks of the upper envelope of the data, e.g. the beats of an amplitude
+    /// modulated signal. The envelope connects the peaks found by `find_peaks` (with all the
+    /// bounds of this finder) by straight lines and is constant before the first and after the
+    /// last of them. Its peaks are found with the default settings, so they lie at the middle
+    /// positions of the original peaks; their heights and prominences are computed on the
+    /// envelope.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use find_peaks::PeakFinder;
+    /// let y = [0., 1., 0., 3., 0., 2., 0., 1., 0., 4., 0., 1., 0.];
+    ///
+    /// let ps = PeakFinder::new(&y).find_envelope_peaks();
+    ///
+    /// assert_eq!(
+    ///    ps.iter().map(|x| x.middle_position()).collect::<Vec<_>>(),
+    ///    vec![9, 3]
+    /// );
+    /// ```
+    pub fn find_envelope_peaks(&self) -> Vec<Peak<f64>>
+    where
+        T: Into<f64>,
+    {
+        let envelope = self.upper_envelope();
+        let mut fp = PeakFinder::<f64, usize>::new(&envelope);
+        fp.with_min_prominence(0.);
+        fp.find_peaks()
+    }
+
+    /// Like `nearest_peak`, but closest to the x-value `x`, with the distances measured through
+    /// `x_data` and the heights taken from the data.
+    pub fn nearest_peak_x<'p>(&self, peaks: &'p [Peak<T>], x: S) -> Option<&'p Peak<T>> {
+        let data: &[T] = &self.y_data;
+        nearest_by(
+            peaks,
+            |p| self.x_distance(&self.x_data[p.middle_position()], &x),
+            |p| &data[p.position.start],
+        )
+    }
+
+    /// Partition `peaks` into clusters of neighbouring peaks, sorted by position: a peak whose
+    /// middle position is at most `max_gap` (in x-units) from the middle position of the
+    /// preceding peak is in the same cluster. Unlike `with_merge_distance`, the individual peaks
+    /// are kept.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use find_peaks::PeakFinder;
+    /// let y = [0., 2., 0., 3., 0., 0., 0., 0., 1., 0.];
+    ///
+    /// let fp = PeakFinder::new(&y);
+    /// let clusters = fp.cluster_peaks(&fp.find_peaks(), 2);
+    ///
+    /// assert_eq!(
+    ///    clusters
+    ///        .iter()
+    ///        .map(|c| c.iter().map(|p| p.middle_position()).collect::<Vec<_>>())
+    ///        .collect::<Vec<_>>(),
+    ///    vec![vec![1, 3], vec![8]]
+    /// );
+    /// ```
+    pub fn cluster_peaks(&self, peaks: &[Peak<T>], max_gap: S) -> Vec<Vec<Peak<T>>> {
+        self.group_close(peaks.to_vec(), &max_gap)
+    }
+
+    /// The intervals between consecutive `peaks` in order of position, e.g. the beat-to-beat
+    /// intervals of the R-peaks for the heart rate (see `ecg_preset`): the distances in x-units
+    /// between their middle positions, as for the distance bounds (the shorter way around for
+    /// circular data, or by `with_distance_metric`). The peaks need not be sorted; with the
+    /// default x-values the intervals are in samples. There is one interval less than peaks.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use find_peaks::PeakFinder;
+    /// let y = [0., 2., 0., 0., 3., 0., 1., 0.];
+    /// let t = [0., 0.5, 1., 1.5, 2., 2.5, 3., 3.5];
+    ///
+    /// let fp = PeakFinder::new_with_x(&y, &t);
+    /// let intervals = fp.inter_peak_intervals(&fp.find_peaks());
+    ///
+    /// assert_eq!(intervals, vec![1.5, 1.]);
+    /// ```
+    pub fn inter_peak_intervals(&self, peaks: &[Peak<T>]) -> Vec<S> {
+        // the middle of a plateau across the seam of circular data is past the end
+        let n = self.x_data.len();
+        let mut middles: Vec<usize> = peaks.iter().map(|p| p.middle_position() % n).collect();
+        middles.sort_unstable();
+
+        middles
+            .windows(2)
+            .map(|w| self.peak_distance(&self.x_data[w[0]], &self.x_data[w[1]]))
+            .collect()
+    }
+
+    /// Direction of the data from the left neighbour into `peak` and from `peak` to its right
+    /// neighbour, `None` on a side at the edge of the data. Together with `left_diff` and
+    /// `right_diff` this gives the signed slopes.
+    ///
+    /// For the peaks of `find_peaks` the slopes are always rising and falling; the other
+    /// detection methods (for example `find_peaks_cwt` or `find_extrema`) also find peaks on a
+    /// slope.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use find_peaks::{PeakFinder, Slope};
+    /// let y = [0., 2., 4., 5., 3.];
+    ///
+    /// let fp = PeakFinder::new(&y);
+    /// let ps = fp.find_peaks_cwt(&[1.]);
+    ///
+    /// assert_eq!(fp.slopes(&ps[0]), (Some(Slope::Rising), Some(Slope::Falling)));
+    /// ```
+    pub fn slopes(&self, peak: &Peak<T>) -> (Option<Slope>, Option<Slope>) {
+        let data: &[T] = &self.y_data;
+        let slope = |from: &T, to: &T| difference(from, to).0;
+
+        let left = peak
+            .position
+            .start
+            .checked_sub(1)
+            .map(|i| slope(&data[i], &data[peak.position.start]));
+        let right = data
+            .get(peak.position.end)
+            .map(|y| slope(&data[peak.last_index()], y));
+        (left, right)
+    }
+
+    /// Find peaks at multiple scales like scipy's `find_peaks_cwt`: the data is convolved with
+    /// Ricker wavelets of the given `widths` (in samples), the relative maxima of the transform
+    /// are connected into ridge lines across the widths, and a peak is reported where a ridge
+    /// line spans at least a quarter of the widths and stands out from the noise at the
+    /// narrowest width. The defaults of scipy are used for the other parameters.
+    ///
+    /// The bounds of `PeakFinder` are not applied. The peaks are single samples sorted by
+    /// position, with their height set; since they need not be local maxima of the data,
+    /// `left_diff` and `right_diff` are the absolute differences to the neighbours.
+    pub fn find_peaks_cwt(&self, widths: &[f64]) -> Vec<Peak<T>>
+    where
+        T: Into<f64>,
+    {
+        assert!(widths.iter().all(|&w| w > 0.), "Widths must be positive!");
+
+        let data: Vec<f64> = self.y_data.iter().map(to_f64).collect();
+        let zero = match &self.zero {
+            Some(zero) => zero,
+            None => return Vec::new(),
+        };
+
+        let abs_diff = |a: &T, b: &T| difference(a, b).1;
+
+        cwt::find_peaks_cwt(&data, widths)
+            .into_iter()
+            .map(|i| {
+                let y = &self.y_data[i];
+                let left_diff = match i.checked_sub(1) {
+                    Some(j) => abs_diff(y, &self.y_data[j]),
+                    None => zero.clone(),
+                };
+                let right_diff = match self.y_data.get(i + 1) {
+                    Some(next) => abs_diff(y, next),
+                    None => zero.clone(),
+                };
+
+                let mut p = Peak::new(i..i + 1, left_diff, right_diff);
+                p.add_height(y.clone());
+                p
+            })
+            .collect()
+    }
+
+    pub fn with_min_height(&mut self, h: T) -> &mut Self {
+        self.height.lower = Some(h);
+        self
+    }
+
+    pub fn with_max_height(&mut self, h: T) -> &mut Self {
+        self.height.upper = Some(h);
+        self
+    }
+
+    /// Set both height bounds at once, `with_min_height(lo)` and `with_max_height(hi)`.
+    /// The same pairs exist for the prominence, the difference, the plateau size and the
+    /// distance.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use find_peaks::PeakFinder;
+    /// let y = [0., 1., 0., 3., 0., 5., 0.];
+    ///
+    /// let ps = PeakFinder::new(&y).with_height_range(2., 4.).find_peaks();
+    ///
+    /// assert_eq!(ps.len(), 1);
+    /// assert_eq!(ps[0].position, 3..4);
+    /// ```
+    pub fn with_height_range(&mut self, lo: T, hi: T) -> &mut Self {
+        assert!(
+            lo <= hi,
+            "The lower end of the height range must not exceed the upper end!"
+        );
+
+        self.with_min_height(lo).with_max_height(hi)
+    }
+
+    /// Keep only the peaks whose apex, estimated by a parabola through the sample and its two
+    /// neighbours, is between `lo` and `hi` (inclusive). A plateau or an edge peak has no such
+    /// parabola, its apex is its sample. Like the height bounds, the apex is measured above
+    /// `with_height_reference`; `Peak::height` stays the sample value.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use find_peaks::PeakFinder;
+    /// let y = [0., 4., 4., 0., 0., 3.5, 4., 0., 0.];
+    ///
+    /// // the vertex of the parabola through 3.5, 4. and 0. is at about 4.34
+    /// let ps = PeakFinder::new(&y).with_apex_height_range(4.2, 5.).find_peaks();
+    ///
+    /// assert_eq!(ps.len(), 1);
+    /// assert_eq!(ps[0].position, 6..7);
+    /// assert_eq!(ps[0].height, Some(4.));
+    /// ```
+    pub fn with_apex_height_range(&mut self, lo: T, hi: T) -> &mut Self
+    where
+        T: Into<f64>,
+    {
+        let (lo, hi) = (lo.into(), hi.into());
+        assert!(
+            lo <= hi,
+            "The lower end of the apex height range must not exceed the upper end!"
+        );
+
+        self.apex_height = Limits {
+            lower: Some(lo),
+            upper: Some(hi),
+        };
+        self.as_f64 = Some(to_f64);
+        self
+    }
+
+    /// Measure the peak heights, for the height bounds and `Peak::height`, from `reference` instead
+    /// of zero. Peaks below the reference are dropped. The reference is computed once, from the
+    /// data of this finder (skipping NaN); it applies to `with_min_relative_prominence` as well,
+    /// but not to `find_troughs`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use find_peaks::{HeightRef, PeakFinder};
+    /// let y = [10., 13., 10., 11., 10.];
+    ///
+    /// let ps = PeakFinder::new(&y)
+    ///     .with_height_reference(HeightRef::AboveGlobalMin)
+    ///     .with_min_height(2.)
+    ///     .find_peaks();
+    ///
+    /// assert_eq!(ps.len(), 1);
+    /// assert_eq!(ps[0].height, Some(3.));
+    /// ```
+    pub fn with_height_reference(&mut self, reference: HeightRef) -> &mut Self
+    where
+        T: AsF64 + FromF64,
+    {
+        let comparable = self.y_data.iter().filter(|y| y.partial_cmp(y).is_some());
+        self.height_offset = match reference {
+            HeightRef::Absolute => None,
+            HeightRef::AboveGlobalMin => comparable
+                .fold(
+                    None,
+                    |m: Option<&T>, y| {
+                        if m.is_none_or(|m| y < m) {
+                            Some(y)
+                        } else {
+                            m
+                        }
+                    },
+                )
+                .cloned(),
+            HeightRef::AboveMean => {
+                let (sum, n) = comparable.fold((0., 0), |(sum, n), y| (sum + y.as_f64(), n + 1));
+                (n > 0).then(|| T::from_f64(sum / n as f64))
+            }
+        };
+        self
+    }
+
+    /// Keep only the peaks that are the maximum of the samples within `w` samples of them (equally
+    /// high samples are allowed), a cheap local alternative to a prominence bound. The window
+    /// shrinks at the edges, and for circular data it does not reach across the global minimum.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use find_peaks::PeakFinder;
+    /// let y = [0., 5., 0., 3., 0., 0., 1., 0.];
+    ///
+    /// let ps = PeakFinder::new(&y).with_local_window(2).find_peaks();
+    ///
+    /// // the 3. is within two samples of the 5.
+    /// assert_eq!(
+    ///     ps.iter().map(|p| p.middle_position()).collect::<Vec<_>>(),
+    ///     vec![1, 6]
+    /// );
+    /// ```
+    pub fn with_local_window(&mut self, w: usize) -> &mut Self {
+        self.local_window = Some(w);
+        self
+    }
+
+    /// Keep only the peaks that are at least `amount` above the mean of the `k` samples on each
+    /// side of them, which drops small bumps on a flat baseline. The windows shrink at the edges of
+    /// the data, and masked samples are left out of the mean.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use find_peaks::PeakFinder;
+    /// let y = [0., 4., 0., 0., 1., 0., 2., 6., 2.];
+    ///
+    /// let ps = PeakFinder::new(&y).with_min_local_contrast(2, 2.).find_peaks();
+    ///
+    /// // the 1. is only 0.5 above the mean of its neighbours
+    /// assert_eq!(
+    ///     ps.iter().map(|p| p.middle_position()).collect::<Vec<_>>(),
+    ///     vec![7, 1]
+    /// );
+    /// ```
+    pub fn with_min_local_contrast(&mut self, k: usize, amount: T) -> &mut Self
+    where
+        T: Into<f64>,
+    {
+        assert!(k > 0, "Contrast window must be positive!");
+
+        self.local_contrast = Some((k, amount.into()));
+        self.as_f64 = Some(to_f64);
+        self
+    }
+
+    /// Flag the peaks at (or above) the saturation `level` of the data, e.g. the full scale of
+    /// an ADC, as `clipped`. The samples of a clipped peak are all cut off at the level, so its
+    /// position is the saturated plateau, and its height and prominence are those of the
+    /// plateau rather than of the real peak. Troughs (`find_troughs`) are never flagged.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use find_peaks::PeakFinder;
+    /// let y = [0, 255, 255, 255, 0, 100, 0];
+    ///
+    /// let ps = PeakFinder::new(&y).with_saturation_level(255).find_peaks();
+    ///
+    /// assert_eq!(ps[0].position, 1..4);
+    /// assert!(ps[0].clipped);
+    /// assert!(!ps[1].clipped);
+    /// ```
+    pub fn with_saturation_level(&mut self, level: T) -> &mut Self {
+        self.saturation_level = Some(level);
+        self
+    }
+
+    /// Drop the peaks flagged as clipped by `with_saturation_level` (default: `false`).
+    pub fn with_drop_clipped(&mut self, drop: bool) -> &mut Self {
+        self.drop_clipped = drop;
+        self
+    }
+
+    /// Set the minimum height to the `p`-th percentile of the data: the sample at rank
+    /// `round(p / 100 * (n - 1))` of the `n` sorted samples, so no interpolation is needed and the
+    /// bound is one of the samples.
+    ///
+    /// The percentile is computed once, from a sorted copy of the data in O(n log n).
+    pub fn with_min_height_percentile(&mut self, p: f64) -> &mut Self {
+        assert!(
+            (0. ..=100.).contains(&p),
+            "Percentile must be between 0 and 100!"
+        );
+
+        let mut sorted: Vec<&T> = self.y_data.iter().collect();
+        sorted.sort_unstable_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
+
+        if let Some(last) = sorted.len().checked_sub(1) {
+            let rank = (p / 100. * last as f64).round() as usize;
+            self.height.lower = Some(sorted[rank].clone());
+        }
+        self
+    }
+
+    /// Set the minimum prominence of a peak. Prominences are never negative, also the depths of
+    /// troughs (see `find_troughs`), so neither is the bound.
+    pub fn with_min_prominence(&mut self, prominence: T) -> &mut Self {
+        let zero = self.zero_like(&prominence);
+        assert!(zero.le(&prominence), "Prominence must be positive!");
+
+        self.prominence.lower = Some(prominence);
+        self
+    }
+
+    pub fn with_max_prominence(&mut self, prominence: T) -> &mut Self {
+        let zero = self.zero_like(&prominence);
+        assert!(zero.le(&prominence), "Prominence must be positive!");
+
+        self.prominence.upper = Some(prominence);
+        self
+    }
+
+    /// Set both prominence bounds at once, see `with_height_range`.
+    pub fn with_prominence_range(&mut self, lo: T, hi: T) -> &mut Self {
+        assert!(
+            lo <= hi,
+            "The lower end of the prominence range must not exceed the upper end!"
+        );
+
+        self.with_min_prominence(lo).with_max_prominence(hi)
+    }
+
+    /// Limit the search for the prominence valleys to `wlen / 2` samples on each side of a peak
+    /// (like scipy's `wlen`). If no higher sample is found within the window, the lowest sample
+    /// in the window is the valley. This keeps the prominence local, e.g. on a drifting
+    /// baseline.
+    pub fn with_prominence_window(&mut self, wlen: usize) -> &mut Self {
+        assert!(wlen >= 2, "Prominence window must span at least 2 samples!");
+
+        self.prominence_window = Some(wlen);
+        self
+    }
+
+    /// Scan at most `samples` samples on each side of a peak for its prominence valleys, to bound
+    /// the cost on long gentle slopes. If the cap is hit, the lowest sample scanned on that side is
+    /// the valley and the peak is flagged `prominence_capped`: its real prominence may be larger.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use find_peaks::PeakFinder;
+    /// let y = [0., 1., 2., 3., 4., 5., 0., 6., 0.];
+    ///
+    /// let ps = PeakFinder::new(&y)
+    ///     .with_prominence_max_scan(2)
+    ///     .with_min_prominence(0.)
+    ///     .find_peaks();
+    ///
+    /// // the slope on the left is only scanned down to the 3., so the prominence is 2. instead
+    /// // of 5.
+    /// assert_eq!(ps[1].position, 5..6);
+    /// assert_eq!(ps[1].prominence, Some(2.));
+    /// assert!(ps[1].prominence_capped);
+    /// ```
+    pub fn with_prominence_max_scan(&mut self, samples: usize) -> &mut Self {
+        assert!(samples > 0, "Maximum scan must be positive!");
+
+        self.prominence_max_scan = Some(samples);
+        self
+    }
+
+    /// Measure the prominence from the median of the samples at most `window` samples away on each
+    /// side of a peak (the peak itself excluded) instead of the valleys, which follows a drifting
+    /// baseline better. It takes precedence over the prominence reference and window; the peaks
+    /// have no `valley_index` then. `find_peaks_persistence` still uses the valleys.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use find_peaks::PeakFinder;
+    /// let y = [0., 1., 2., 6., 4., 5., 6., 7., 12., 9., 10.];
+    ///
+    /// let ps = PeakFinder::new(&y)
+    ///     .with_local_baseline(2)
+    ///     .with_min_prominence(0.)
+    ///     .find_peaks();
+    ///
+    /// // 12 stands 5 over the lower median of 6, 7, 9 and 10
+    /// assert_eq!(ps[0].position, 8..9);
+    /// assert_eq!(ps[0].prominence, Some(5.));
+    /// ```
+    pub fn with_local_baseline(&mut self, window: usize) -> &mut Self {
+        assert!(window > 0, "Baseline window must be positive!");
+
+        self.local_baseline = Some(window);
+        self
+    }
+
+    /// Measure the prominence of a peak from the level `base(i, y_data)` instead of the valleys,
+    /// e.g. a baseline model at the middle position `i`; it is zero below the base. `y_data` are
+    /// the data scanned (the residual, filtered, rotated or reversed data where these apply). It
+    /// takes precedence over all the other prominence settings, and the peaks have no
+    /// `valley_index`; `find_peaks_persistence` still uses the valleys.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use find_peaks::PeakFinder;
+    /// let y = [1., 3., 2., 6.5, 5., 9., 7.];
+    ///
+    /// // peaks on the linear baseline 1 + i, the one at 1 stands only 1 above it
+    /// let ps = PeakFinder::new(&y)
+    ///     .with_base_fn(|i, _| 1. + i as f64)
+    ///     .with_min_prominence(2.)
+    ///     .find_peaks();
+    ///
+    /// assert_eq!(ps.len(), 2);
+    /// assert_eq!(ps[0].position, 5..6);
+    /// assert_eq!(ps[0].prominence, Some(3.));
+    /// assert_eq!(ps[1].prominence, Some(2.5));
+    /// ```
+    pub fn with_base_fn<F>(&mut self, base: F) -> &mut Self
+    where
+        F: Fn(usize, &[T]) -> T + Send + Sync + 'static,
+    {
+        self.base_fn = Some(Arc::new(base));
+        self
+    }
+
+    /// Choose what the prominence is measured from (default: `ProminenceRef::LocalValleys`). With a
+    /// global reference the prominence window does not apply, and `find_peaks_persistence` always
+    /// uses the local valleys.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use find_peaks::{PeakFinder, ProminenceRef};
+    /// let y = [1., 2., 3., 0., 5., 0.];
+    ///
+    /// let ps = PeakFinder::new(&y)
+    ///     .with_prominence_reference(ProminenceRef::Fixed(1.))
+    ///     .with_min_prominence(1.)
+    ///     .find_peaks();
+    ///
+    /// assert_eq!(ps[1].prominence, Some(2.));
+    /// ```
+    pub fn with_prominence_reference(&mut self, reference: ProminenceRef<T>) -> &mut Self {
+        self.prominence_reference = reference;
+        self
+    }
+
+    /// Keep only peaks whose prominence is at least `frac` times their height, e.g. `0.1` keeps
+    /// peaks sticking out by at least 10% of their own height.
+    pub fn with_min_relative_prominence(&mut self, frac: f64) -> &mut Self
+    where
+        T: Into<f64>,
+    {
+        assert!(frac >= 0., "Relative prominence must be positive!");
+
+        self.min_relative_prominence = Some(frac);
+        self.as_f64 = Some(to_f64);
+        self
+    }
+
+    /// Keep only peaks whose prominence is at least `ratio` times the prominence of the nearest
+    /// taller peak, which drops the small satellites of a dominant peak; peaks with no taller peak
+    /// are kept. The neighbours are the peaks passing the other prominence bounds, before the
+    /// distance bounds.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use find_peaks::PeakFinder;
+    /// let y = [0., 10., 8., 8.5, 0., 2., 0.];
+    ///
+    /// let ps = PeakFinder::new(&y).with_min_prominence_ratio(0.1).find_peaks();
+    ///
+    /// // the satellite at 3 is much less prominent than the peak at 1
+    /// let middles: Vec<usize> = ps.iter().map(|p| p.middle_position()).collect();
+    /// assert_eq!(middles, vec![1, 5]);
+    /// ```
+    pub fn with_min_prominence_ratio(&mut self, ratio: f64) -> &mut Self
+    where
+        T: Into<f64>,
+    {
+        assert!(ratio >= 0., "Prominence ratio must be positive!");
+
+        self.min_prominence_ratio = Some(ratio);
+        self.as_f64 = Some(to_f64);
+        self
+    }
+
+    /// Compute the symmetry of the peaks: the ratio of the left to the right half width at half
+    /// prominence, in samples from the middle of the peak to the interpolated crossings. Below `1.`
+    /// the peak rises faster than it falls. `None` for a peak truncated by an edge or of zero
+    /// prominence.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use find_peaks::PeakFinder;
+    /// let y = [0., 4., 3., 2., 1., 0.];
+    ///
+    /// let ps = PeakFinder::new(&y).with_symmetry().find_peaks();
+    ///
+    /// assert_eq!(ps[0].symmetry, Some(0.5 / 2.));
+    /// ```
+    pub fn with_symmetry(&mut self) -> &mut Self
+    where
+        T: Into<f64>,
+    {
+        self.compute_symmetry = true;
+        self.as_f64 = Some(to_f64);
+        self
+    }
+
+    /// Compute the left and right widths of the peaks (`Peak::left_width`, `Peak::right_width`) at
+    /// half prominence, in x-units from the middle of the peak to the interpolated crossings of
+    /// `with_symmetry`. A width cut by an edge of the data is clamped to it, a lower bound; both
+    /// are `None` for peaks of zero prominence. With prominences the `sharpness` is computed too.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use find_peaks::PeakFinder;
+    /// let x = [0., 2., 4., 6., 8., 10.];
+    /// let y = [0., 4., 3., 2., 1., 0.];
+    ///
+    /// let ps = PeakFinder::new_with_x(&y, &x).with_widths().find_peaks();
+    ///
+    /// assert_eq!(ps[0].left_width, Some(1.));
+    /// assert_eq!(ps[0].right_width, Some(4.));
+    /// ```
+    pub fn with_widths(&mut self) -> &mut Self
+    where
+        T: Into<f64>,
+        S: AsF64,
+    {
+        self.compute_widths = true;
+        self.as_f64 = Some(to_f64);
+        self.x_as_f64 = Some(S::as_f64);
+        self
+    }
+
+    /// Compute the sharpness of the peaks (`Peak::sharpness`), the prominence divided by the full
+    /// width at half prominence in x-units, to tell sharp spikes from broad bumps with one
+    /// number. This sets `with_widths`, which computes the sharpness as well whenever the
+    /// prominence of a peak is computed. The sharpness is `None` for a zero width.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use find_peaks::PeakFinder;
+    /// let x = [0., 2., 4., 6., 8., 10.];
+    /// let y = [0., 4., 3., 2., 1., 0.];
+    ///
+    /// let ps = PeakFinder::new_with_x(&y, &x).with_sharpness().find_peaks();
+    ///
+    /// // a prominence of 4 over a width of 5
+    /// assert_eq!(ps[0].sharpness, Some(0.8));
+    /// ```
+    pub fn with_sharpness(&mut self) -> &mut Self
+    where
+        T: Into<f64>,
+        S: AsF64,
+    {
+        self.compute_sharpness = true;
+        self.with_widths()
+    }
+
+    /// Compute a score of each peak (`Peak::score`) between `0.` and `1.`, to rank peaks from
+    /// different data on one scale: the weighted mean of the height above the minimum and the
+    /// prominence, both divided by the range of the data, and the sharpness (see
+    /// `with_sharpness`, capped at `1.`). A term that is unknown is left out with its weight; the
+    /// score is `None` if no weighted term is left.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use find_peaks::{PeakFinder, ScoreWeights};
+    /// let y = [0., 4., 0., 1., 2., 3., 2., 1., 0.];
+    ///
+    /// let weights = ScoreWeights { height: 1., prominence: 1., sharpness: 0. };
+    /// let ps = PeakFinder::new(&y).with_score(weights).find_peaks();
+    ///
+    /// assert_eq!(ps[0].score, Some(1.));
+    /// assert_eq!(ps[1].score, Some(0.75));
+    /// ```
+    pub fn with_score(&mut self, weights: ScoreWeights) -> &mut Self
+    where
+        T: Into<f64>,
+    {
+        let all = [weights.height, weights.prominence, weights.sharpness];
+        assert!(all.iter().all(|&w| w >= 0.), "Weights must be positive!");
+        assert!(
+            all.iter().any(|&w| w > 0.),
+            "At least one weight must be positive!"
+        );
+
+        self.score_weights = Some(weights);
+        self.as_f64 = Some(to_f64);
+        self
+    }
+
+    /// Set the thresholds of `classify` (default: `ClassificationThresholds::default()`).
+    pub fn with_classification_thresholds(
+        &mut self,
+        thresholds: ClassificationThresholds,
+    ) -> &mut Self {
+        self.classification_thresholds = thresholds;
+        self
+    }
+
+    /// Tag `peak`, found in the data of this finder, with its rough shape:
+    ///
+    /// * `Shoulder` if it is a shoulder (`is_shoulder`),
+    /// * `Noise` if its prominence is below `noise_prominence_ratio` times its absolute height,
+    /// * `Sharp` if its width at half prominence (the sum of the half widths of `with_symmetry`)
+    ///   is at most `max_sharp_width` samples,
+    /// * `Broad` otherwise, also if the width is unknown because the peak is truncated by an
+    ///   edge of the data.
+    ///
+    /// The prominence is computed if `peak` has none.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use find_peaks::{PeakFinder, PeakShape};
+    /// let y = [0., 0., 10., 0., 1., 2., 4., 6., 7., 7.2, 7., 6., 4., 2., 1., 0.];
+    ///
+    /// let fp = PeakFinder::new(&y);
+    /// let mut ps = fp.find_peaks();
+    /// ps.sort_by_key(|p| p.position.start);
+    ///
+    /// assert_eq!(
+    ///     ps.iter().map(|p| fp.classify(p)).collect::<Vec<_>>(),
+    ///     vec![PeakShape::Sharp, PeakShape::Broad]
+    /// );
+    /// ```
+    pub fn classify(&self, peak: &Peak<T>) -> PeakShape
+    where
+        T: Into<f64>,
+    {
+        if peak.is_shoulder {
+            return PeakShape::Shoulder;
+        }
+
+        let thresholds = &self.classification_thresholds;
+        let prominence = match &peak.prominence {
+            Some(prom) => to_f64(prom),
+            None => to_f64(&self.calc_prominences(std::slice::from_ref(&peak.position))[0]),
+        };
+        let height = to_f64(&self.y_data[peak.position.start]);
+
+        if prominence < thresholds.noise_prominence_ratio * height.abs() {
+            return PeakShape::Noise;
+        }
+        let crossings = self.half_crossings(&peak.position, prominence, to_f64);
+        match crossings.and_then(|crossings| half_widths(&peak.position, crossings)) {
+            Some((left, right)) if left + right <= thresholds.max_sharp_width => PeakShape::Sharp,
+            _ => PeakShape::Broad,
+        }
+    }
+
+    /// Set the minimum prominence to `k` times the median absolute deviation of the first
+    /// differences of the data, a noise estimate (without a scale factor; `k = 3.` is a reasonable
+    /// default). It is computed once and applies on top of the other prominence bounds.
+    pub fn with_auto_prominence(&mut self, k: f64) -> &mut Self
+    where
+        T: Into<f64>,
+    {
+        assert!(k >= 0., "Noise factor must be positive!");
+
+        let diffs: Vec<f64> = self
+            .differences()
+            .iter()
+            .map(|(slope, diff)| match slope {
+                Slope::Falling => -to_f64(diff),
+                _ => to_f64(diff),
+            })
+            .collect();
+
+        let median = |mut v: Vec<f64>| {
+            v.sort_unstable_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
+            let n = v.len();
+            if n % 2 == 1 {
+                v[n / 2]
+            } else {
+                (v[n / 2 - 1] + v[n / 2]) / 2.
+            }
+        };
+
+        if !diffs.is_empty() {
+            let center = median(diffs.clone());
+            let mad = median(diffs.iter().map(|d| (d - center).abs()).collect());
+            self.min_prominence_f64 = Some(k * mad);
+            self.as_f64 = Some(to_f64);
+        }
+        self
+    }
+
+    /// Set the minimum difference between a peak and each of its neighbours (default: zero, which
+    /// makes any local maximum a candidate). For a plateau the neighbours are the samples just
+    /// before and after it.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use find_peaks::PeakFinder;
+    /// let y = [0., 2., 2., 1., 3., 0.];
+    ///
+    /// let ps = PeakFinder::new(&y).find_peaks();
+    ///
+    /// // the flat top makes a plateau, its neighbours are lower
+    /// assert_eq!(ps[1].position, 1..3);
+    /// assert_eq!(ps[1].left_diff, 2.);
+    /// ```
+    pub fn with_min_difference(&mut self, difference: T) -> &mut Self {
+        let zero = self.zero_like(&difference);
+        assert!(zero.le(&difference), "Difference must be positive!");
+
+        self.difference.lower = Some(difference);
+        self
+    }
+
+    /// Set the maximum difference between a peak and each of its neighbours. The lower bound
+    /// stays at its default of zero unless set, see `with_min_difference`.
+    pub fn with_max_difference(&mut self, difference: T) -> &mut Self {
+        let zero = self.zero_like(&difference);
+        assert!(zero.le(&difference), "Difference must be positive!");
+
+        self.difference.upper = Some(difference);
+        self
+    }
+
+    /// Set both difference bounds at once, see `with_height_range`. Unlike the per-side
+    /// ranges of `with_left_difference_range`, they apply to both sides.
+    pub fn with_difference_range(&mut self, lo: T, hi: T) -> &mut Self {
+        self.difference = self.difference_range(lo, hi);
+        self
+    }
+
+    /// Set the minimum difference between a peak and each of its neighbours to `frac` times the
+    /// height of the peak (above `with_height_reference`), checked in `f64` on top of the absolute
+    /// difference bounds. The outer side of an edge peak is not checked.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use find_peaks::PeakFinder;
+    /// let y = [0., 2., 0., 0., 9., 8., 0., 20., 0.];
+    ///
+    /// let ps = PeakFinder::new(&y).with_min_difference_relative(0.5).find_peaks();
+    ///
+    /// // the step of 1. from 9. is too shallow, the step of 2. from 2. is not
+    /// assert_eq!(ps.iter().map(|p| p.middle_position()).collect::<Vec<_>>(), vec![7, 1]);
+    /// ```
+    pub fn with_min_difference_relative(&mut self, frac: f64) -> &mut Self
+    where
+        T: Into<f64>,
+    {
+        assert!(frac >= 0., "Relative difference must be positive!");
+
+        self.min_relative_difference = Some(frac);
+        self.as_f64 = Some(to_f64);
+        self
+    }
+
+    /// Exclude the samples where `mask` is `true`, e.g. known artifacts: a peak containing a masked
+    /// sample is dropped, and the valley searches stop at masked samples as at an edge (the peak is
+    /// then `bounded_by_edge`). `find_peaks_persistence` ignores the mask for the prominence.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use find_peaks::PeakFinder;
+    /// let y = [0., 2., 0., 9., 0., 1., 3., 0.];
+    /// let mask = [false, false, false, true, false, false, false, false];
+    ///
+    /// let ps = PeakFinder::new(&y)
+    ///     .with_exclusion_mask(&mask)
+    ///     .with_min_prominence(0.)
+    ///     .find_peaks();
+    ///
+    /// assert_eq!(ps.len(), 2);
+    /// // the glitch at 3 bounds the valley search, so the base of 6 is at 4
+    /// assert_eq!(ps[0].position, 6..7);
+    /// assert_eq!(ps[0].prominence, Some(3.));
+    /// assert_eq!(ps[0].valley_index, Some(4));
+    /// ```
+    pub fn with_exclusion_mask(&mut self, mask: &'a [bool]) -> &mut Self {
+        assert_eq!(
+            mask.len(),
+            self.y_data.len(),
+            "Mask must have the same length as the data!"
+        );
+
+        self.mask = Some(Cow::Borrowed(mask));
+        self
+    }
+
+    /// Set separate minimum differences for the left and the right neighbour of a peak, e.g. to
+    /// require a steep rise but allow a gentle fall. A peak is kept only if both given
+    /// thresholds are met; they apply on top of the common bounds of `with_min_difference` and
+    /// `with_max_difference`. The outer side of an edge peak is not checked.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use find_peaks::PeakFinder;
+    /// let y = [0., 3., 2., 0., 1., 0., 4., 0.];
+    ///
+    /// let ps = PeakFinder::new(&y).with_side_thresholds(Some(2.), None).find_peaks();
+    ///
+    /// // the peak at 4 rises by only 1
+    /// let middles: Vec<usize> = ps.iter().map(|p| p.middle_position()).collect();
+    /// assert_eq!(middles, vec![6, 1]);
+    /// ```
+    pub fn with_side_thresholds(&mut self, left: Option<T>, right: Option<T>) -> &mut Self {
+        for threshold in left.iter().chain(&right) {
+            let zero = self.zero_like(threshold);
+            assert!(zero.le(threshold), "Side thresholds must be positive!");
+        }
+
+        self.side_thresholds = (left, right);
+        self
+    }
+
+    /// Keep only the peaks whose difference to the left neighbour is between `lo` and `hi`
+    /// (inclusive), on top of the common difference bounds; the left side of a left edge peak is
+    /// not checked. See `with_right_difference_range` for the other side.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use find_peaks::PeakFinder;
+    /// let y = [0., 3., 2., 0., 1., 0., 4., 0.];
+    ///
+    /// // a rise of at least 2. and a fall of at most 1.
+    /// let ps = PeakFinder::new(&y)
+    ///     .with_left_difference_range(2., 10.)
+    ///     .with_right_difference_range(0., 1.)
+    ///     .find_peaks();
+    ///
+    /// assert_eq!(ps.len(), 1);
+    /// assert_eq!(ps[0].position, 1..2);
+    /// ```
+    pub fn with_left_difference_range(&mut self, lo: T, hi: T) -> &mut Self {
+        self.left_difference = self.difference_range(lo, hi);
+        self
+    }
+
+    /// Like `with_left_difference_range`, for the difference to the right neighbour.
+    pub fn with_right_difference_range(&mut self, lo: T, hi: T) -> &mut Self {
+        self.right_difference = self.difference_range(lo, hi);
+        self
+    }
+
+    /// Keep only the peaks rising for at least `r` steps in a row before them, e.g. to reject
+    /// jagged noise bumps. Equal samples break the run unless `with_run_plateaus` is set; a left
+    /// edge peak is not checked, but a run cut short by the left edge is too short. See
+    /// `with_min_fall_run` for the other side.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use find_peaks::PeakFinder;
+    /// let y = [0., 1., 2., 3., 0., 2., 1., 3., 0.];
+    ///
+    /// // the peak at 7 jumps up from 1. after a fall
+    /// let ps = PeakFinder::new(&y).with_min_rise_run(3).find_peaks();
+    ///
+    /// assert_eq!(ps.len(), 1);
+    /// assert_eq!(ps[0].position, 3..4);
+    /// ```
+    pub fn with_min_rise_run(&mut self, r: usize) -> &mut Self {
+        assert!(r > 0, "Run length must be positive!");
+        self.min_runs.0 = Some(r);
+        self
+    }
+
+    /// Like `with_min_rise_run`, for the samples after the peak falling for at least `f` steps
+    /// in a row.
+    pub fn with_min_fall_run(&mut self, f: usize) -> &mut Self {
+        assert!(f > 0, "Run length must be positive!");
+        self.min_runs.1 = Some(f);
+        self
+    }
+
+    /// Let equal neighbouring samples continue the runs of `with_min_rise_run` and
+    /// `with_min_fall_run` (default: `false`), each counting as a step.
+    pub fn with_run_plateaus(&mut self, allowed: bool) -> &mut Self {
+        self.run_plateaus = allowed;
+        self
+    }
+
+    fn difference_range(&self, lo: T, hi: T) -> Limits<T> {
+        let zero = self.zero_like(&lo);
+        assert!(zero.le(&lo), "Difference must be positive!");
+        assert!(
+            lo <= hi,
+            "The lower end of the difference range must not exceed the upper end!"
+        );
+
+        Limits {
+            lower: Some(lo),
+            upper: Some(hi),
+        }
+    }
+
+    pub fn with_min_plateau_size(&mut self, size: usize) -> &mut Self {
+        self.plateau_size.lower = Some(size);
+        self
+    }
+
+    pub fn with_max_plateau_size(&mut self, size: usize) -> &mut Self {
+        self.plateau_size.upper = Some(size);
+        self
+    }
+
+    /// Set both plateau size bounds at once, see `with_height_range`.
+    pub fn with_plateau_size_range(&mut self, lo: usize, hi: usize) -> &mut Self {
+        assert!(
+            lo <= hi,
+            "The lower end of the plateau size range must not exceed the upper end!"
+        );
+
+        self.with_min_plateau_size(lo).with_max_plateau_size(hi)
+    }
+
+    /// Set the minimum width of a peak's plateau measured through `x_data`, i.e. the
+    /// x-distance between its first and last sample. A single-sample peak has zero width.
+    pub fn with_min_width_x(&mut self, width: S) -> &mut Self {
+        let zero = width.clone() - width.clone();
+        assert!(zero.le(&width), "Width must be positive!");
+
+        self.width_x.lower = Some(width);
+        self
+    }
+
+    /// Set the maximum width of a peak's plateau measured through `x_data`.
+    pub fn with_max_width_x(&mut self, width: S) -> &mut Self {
+        let zero = width.clone() - width.clone();
+        assert!(zero.le(&width), "Width must be positive!");
+
+        self.width_x.upper = Some(width);
+        self
+    }
+
+    /// Keep only the peaks whose middle position has an x-value in `[lo, hi]`.
+    ///
+    /// The properties of the peaks are still computed on the whole data, so a peak near the edge
+    /// of the range gets the same prominence as without the range.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use find_peaks::PeakFinder;
+    /// let x = [0., 1., 2., 3., 4., 5., 6.];
+    /// let y = [0., 5., 0., 1., 2., 3., 0.];
+    ///
+    /// let ps = PeakFinder::new_with_x(&y, &x)
+    ///     .with_x_range(3., 6.)
+    ///     .with_min_prominence(0.)
+    ///     .find_peaks();
+    ///
+    /// assert_eq!(ps.len(), 1);
+    /// assert_eq!(ps[0].middle_position(), 5);
+    /// // measured from the valley at x = 2., outside the range
+    /// assert_eq!(ps[0].prominence, Some(3.));
+    /// ```
+    pub fn with_x_range(&mut self, lo: S, hi: S) -> &mut Self {
+        assert!(
+            lo <= hi,
+            "The lower end of the x range must not exceed the upper end!"
+        );
+
+        self.x_range = Limits {
+            lower: Some(lo),
+            upper: Some(hi),
+        };
+        self
+    }
+
+    /// Set the minimum distance between the middle positions of peaks, in the units of `x_data`
+    /// (in samples for a finder made without x-values). The peaks are visited from the highest (see
+    /// `with_distance_priority`), and each one removes the lower peaks closer to it; a removed peak
+    /// removes no others.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use find_peaks::PeakFinder;
+    /// let y = [0., 3., 0., 2., 0.];
+    /// let t = [0., 1., 5., 9., 10.];
+    ///
+    /// // two samples, but eight seconds apart
+    /// let ps = PeakFinder::new_with_x(&y, &t).with_min_distance(5.).find_peaks();
+    /// assert_eq!(ps.len(), 2);
+    /// let ps = PeakFinder::new(&y).with_min_distance(5).find_peaks();
+    /// assert_eq!(ps.len(), 1);
+    /// ```
+    pub fn with_min_distance(&mut self, distance: S) -> &mut Self {
+        let zero = distance.clone() - distance.clone();
+        assert!(zero.le(&distance), "Distance must be positive!");
+
+        self.distance.lower = Some(distance);
+        self
+    }
+
+    /// Choose which of two peaks closer than the distance bounds allow is kept (default:
+    /// `DistancePriority::Height`, as in scipy). With `DistancePriority::Prominence` the
+    /// prominence is computed for every peak reaching the distance bounds. The returned peaks are
+    /// still sorted by `with_sort_order`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use find_peaks::{DistancePriority, PeakFinder};
+    /// let y = [9., 9.5, 9., 0., 4., 0.];
+    ///
+    /// let ps = PeakFinder::new(&y)
+    ///     .with_min_distance(4)
+    ///     .with_distance_priority(DistancePriority::Prominence)
+    ///     .find_peaks();
+    ///
+    /// assert_eq!(ps.len(), 1);
+    /// assert_eq!(ps[0].position, 4..5);
+    /// assert_eq!(ps[0].prominence, Some(4.));
+    /// ```
+    pub fn with_distance_priority(&mut self, priority: DistancePriority) -> &mut Self {
+        self.distance_priority = priority;
+        self
+    }
+
+    /// Apply the distance bounds, and the `with_filter` filters before them, ahead of the
+    /// prominence bounds (default: `false`), as scipy does. Peaks of low prominence then still
+    /// remove their lower neighbours.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use find_peaks::PeakFinder;
+    /// let y = [0., 5., 4.5, 4.5, 4.5, 4.8, 0., 2., 0.];
+    ///
+    /// let mut fp = PeakFinder::new(&y);
+    /// fp.with_min_prominence(1.).with_min_distance(3);
+    /// assert_eq!(fp.find_peaks().len(), 2);
+    ///
+    /// // the 4.8 of little prominence removes the 2.
+    /// let ps = fp.with_distance_first(true).find_peaks();
+    /// assert_eq!(ps.len(), 1);
+    /// assert_eq!(ps[0].position, 1..2);
+    /// ```
+    pub fn with_distance_first(&mut self, distance_first: bool) -> &mut Self {
+        self.distance_first = distance_first;
+        self
+    }
+
+    /// Detect the peaks on the data filtered by a difference of Gaussians of standard deviations
+    /// `sigma1` and `sigma2` (in samples), which enhances the peaks between the two scales and
+    /// removes a slowly varying baseline. As in scipy's `gaussian_filter1d` the kernels are
+    /// truncated at four standard deviations and the data reflected at the edges (wrapped for
+    /// `with_circular`); the filtered values are converted back with `FromF64`.
+    ///
+    /// The bounds and properties apply to the filtered data, but `height` and `valley_value` are
+    /// the original samples (not relative to `with_height_reference`), and the peaks are sorted,
+    /// selected and labeled by them.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use find_peaks::PeakFinder;
+    /// // two broad humps and a narrow spike on a rising baseline
+    /// let y: Vec<f64> = (0..200)
+    ///     .map(|i| {
+    ///         let hump = |c: f64| (-((i as f64 - c) / 8.).powi(2) / 2.).exp();
+    ///         0.05 * i as f64 + hump(60.) + hump(140.) + if i == 100 { 0.5 } else { 0. }
+    ///     })
+    ///     .collect();
+    ///
+    /// let ps = PeakFinder::new(&y)
+    ///     .with_dog(4., 16.)
+    ///     .with_min_prominence(0.2)
+    ///     .find_peaks();
+    ///
+    /// let mut middles: Vec<usize> = ps.iter().map(|p| p.middle_position()).collect();
+    /// middles.sort();
+    /// assert_eq!(middles, vec![60, 140]);
+    /// assert!(ps.iter().all(|p| p.height == Some(y[p.position.start])));
+    /// ```
+    pub fn with_dog(&mut self, sigma1: f64, sigma2: f64) -> &mut Self
+    where
+        T: Into<f64> + FromF64,
+    {
+        assert!(sigma1 > 0., "Sigma must be positive!");
+        assert!(
+            sigma1 < sigma2,
+            "The second sigma must be greater than the first one!"
+        );
+
+        self.dog = Some((sigma1, sigma2));
+        self.as_f64 = Some(to_f64);
+        self.from_f64 = Some(T::from_f64);
+        self
+    }
+
+    /// Detect the peaks on the logarithm to `base` of the data, for spectra spanning orders of
+    /// magnitude, computed in `f64` (so `T` should be a float type). Samples without a logarithm
+    /// are clamped to the smallest positive sample. `height` and `valley_value` are the linear
+    /// samples (not relative to `with_height_reference`), the other properties those of the
+    /// logarithms.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use find_peaks::PeakFinder;
+    /// // a line of 1000 over a background of 100, and one of 0.1 over 0.01
+    /// let y: [f64; 7] = [100., 1000., 100., 0., 0.01, 0.1, 0.01];
+    ///
+    /// let ps = PeakFinder::new(&y)
+    ///     .with_log_amplitude(10.)
+    ///     .with_min_prominence(0.9)
+    ///     .find_peaks();
+    ///
+    /// assert_eq!(ps.len(), 2);
+    /// assert_eq!(ps[0].height, Some(1000.));
+    /// assert_eq!(ps[1].height, Some(0.1));
+    /// assert!((ps[1].prominence.unwrap() - 1.).abs() < 1e-12);
+    /// ```
+    pub fn with_log_amplitude(&mut self, base: f64) -> &mut Self
+    where
+        T: Into<f64> + FromF64,
+    {
+        assert!(
+            base > 0. && base != 1.,
+            "Logarithm base must be positive and not 1!"
+        );
+
+        self.log_amplitude = Some(base);
+        self.as_f64 = Some(to_f64);
+        self.from_f64 = Some(T::from_f64);
+        self
+    }
+
+    /// Find the peaks of the absolute value of the data (default: `false`), the excursions of a
+    /// bipolar signal in either direction. `height` and `valley_value` are the signed samples (not
+    /// relative to `with_height_reference`), and the peaks are sorted, selected and labeled by
+    /// them. The minimum of a signed integer type (e.g. `i8::MIN`) has no absolute value in the
+    /// type, and negating it overflows (a panic in debug builds), so convert such data to a wider
+    /// type first.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use find_peaks::PeakFinder;
+    /// let y = [0., 2., -1., -3., 0., 1., 0.];
+    ///
+    /// let ps = PeakFinder::new(&y).with_abs(true).with_min_prominence(1.5).find_peaks();
+    ///
+    /// // the positive peak at 1 is only 1 above the valley of the absolute values at 2
+    /// assert_eq!(ps.len(), 1);
+    /// assert_eq!(ps[0].position, 3..4);
+    /// assert_eq!(ps[0].height, Some(-3.));
+    /// assert_eq!(ps[0].prominence, Some(3.));
+    /// ```
+    pub fn with_abs(&mut self, abs: bool) -> &mut Self {
+        self.abs = abs;
+        self
+    }
+
+    /// Detect the peaks on the data linearly interpolated at `n` evenly spaced x-values, for
+    /// non-uniform, increasing `x_data`; the bounds in samples count the samples of the grid. The
+    /// `position` and `valley_index` of the peaks are mapped back to the nearest original samples,
+    /// the other properties are those of the grid, as are the positions seen by `with_filter`
+    /// before the distance bounds. The mask applies through the nearest original samples.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use find_peaks::PeakFinder;
+    /// let x = [0., 1., 2., 2.5, 3., 3.5, 4., 5.];
+    /// let y = [0., 4., 0., 1., 2., 1., 0., 0.];
+    ///
+    /// // a sample every half unit
+    /// let ps = PeakFinder::new_with_x(&y, &x).with_resample(11).find_peaks();
+    ///
+    /// assert_eq!(ps[0].position, 1..2);
+    /// assert_eq!(ps[1].position, 4..5);
+    /// assert_eq!(ps[1].height, Some(2.));
+    /// ```
+    pub fn with_resample(&mut self, n: usize) -> &mut Self
+    where
+        T: Into<f64> + FromF64,
+        S: AsF64 + FromF64,
+    {
+        assert!(n >= 2, "The resampled data must have at least two samples!");
+
+        self.resample = Some(n);
+        self.as_f64 = Some(to_f64);
+        self.from_f64 = Some(T::from_f64);
+        self.x_as_f64 = Some(S::as_f64);
+        self.x_from_f64 = Some(S::from_f64);
+        self
+    }
+
+    /// Choose the order of equally high peaks (default: `TieBreak::LeftmostFirst`). It decides
+    /// which of two equally high peaks too close to each other is kept, and their order in the
+    /// returned peaks sorted by height.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use find_peaks::{PeakFinder, TieBreak};
+    /// let y = [0., 2., 0., 2., 0.];
+    ///
+    /// let mut fp = PeakFinder::new(&y);
+    /// fp.with_min_distance(3);
+    /// assert_eq!(fp.find_peaks()[0].position, 1..2);
+    ///
+    /// fp.with_tie_break(TieBreak::RightmostFirst);
+    /// assert_eq!(fp.find_peaks()[0].position, 3..4);
+    /// ```
+    pub fn with_tie_break(&mut self, tie_break: TieBreak) -> &mut Self {
+        self.tie_break = tie_break;
+        self
+    }
+
+    /// Scan the data from the last sample to the first one (default: `Direction::Forward`), as some
+    /// reference implementations do: the peaks are the same, but the ties decided by the scan order
+    /// go the other way (e.g. the `valley_index` of equally low valleys). The returned properties
+    /// are those of the forward data. Only `find_peaks` and the methods built on it scan reversed.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use find_peaks::{Direction, PeakFinder};
+    /// let y = [1., 0., 2., 5., 2., 0., 1.];
+    ///
+    /// let mut fp = PeakFinder::new(&y);
+    /// fp.with_min_prominence(0.);
+    /// assert_eq!(fp.find_peaks()[0].valley_index, Some(1));
+    ///
+    /// fp.with_scan_direction(Direction::Reverse);
+    /// assert_eq!(fp.find_peaks()[0].valley_index, Some(5));
+    /// ```
+    pub fn with_scan_direction(&mut self, direction: Direction) -> &mut Self {
+        self.direction = direction;
+        self
+    }
+
+    /// Treat the data as periodic (default: `false`), so that the first and the last sample are
+    /// neighbours. The position of a peak across the seam ends after the last index. The valley
+    /// searches and the distance and width bounds go around the seam, with the period of the evenly
+    /// spaced x-values; `with_edge_peaks` has no effect. Only `find_peaks` and
+    /// `find_peaks_with_stats` use this setting.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use find_peaks::PeakFinder;
+    /// let y = [4., 1., 0., 1., 3., 5.];
+    ///
+    /// let ps = PeakFinder::new(&y).with_circular(true).find_peaks();
+    ///
+    /// assert_eq!(ps.len(), 1);
+    /// assert_eq!(ps[0].position, 5..6);
+    /// ```
+    pub fn with_circular(&mut self, circular: bool) -> &mut Self
+    where
+        S: std::ops::Add<Output = S>,
+    {
+        let x = &self.x_data;
+        self.circular = match (circular, x.first(), x.get(1), x.last()) {
+            (true, Some(first), Some(second), Some(last)) => {
+                let step = self.x_distance(second, first);
+                let span = self.x_distance(last, first);
+                Some((span + step, x.len()))
+            }
+            _ => None,
+        };
+        self
+    }
+
+    /// Set the minimum number of samples between the middle positions of peaks, independent of
+    /// `x_data`. Like for the distance bounds, the higher peaks are kept; if the distance bounds
+    /// are set too, a peak has to satisfy all of them.
+    pub fn with_min_sample_gap(&mut self, n: usize) -> &mut Self {
+        self.min_sample_gap = Some(n);
+        self
+    }
+
+    /// Set the minimum distance between peaks to the fraction `f` of the span of the x-values,
+    /// `f * (x_data.last() - x_data.first())`, so that the same setting works for data of
+    /// different lengths or resolutions. The distance is computed in `f64` by every call of
+    /// `find_peaks` and applied together with the other distance bounds.
+    pub fn with_min_distance_fraction(&mut self, f: f64) -> &mut Self
+    where
+        S: AsF64,
+    {
+        assert!(f > 0., "Distance fraction must be positive!");
+
+        self.min_distance_fraction = Some(f);
+        self.x_as_f64 = Some(S::as_f64);
+        self
+    }
+
+    /// Set the maximum distance between peaks (in x-units, see `with_min_distance`).
+    pub fn with_max_distance(&mut self, distance: S) -> &mut Self {
+        let zero = distance.clone() - distance.clone();
+        assert!(zero.le(&distance), "Distance must be positive!");
+
+        self.distance.upper = Some(distance);
+        self
+    }
+
+    /// Set both distance bounds at once (in x-units, see `with_min_distance` and
+    /// `with_height_range`).
+    pub fn with_distance_range(&mut self, lo: S, hi: S) -> &mut Self {
+        assert!(
+            lo <= hi,
+            "The lower end of the distance range must not exceed the upper end!"
+        );
+
+        self.with_min_distance(lo).with_max_distance(hi)
+    }
+
+    /// Merge neighbouring peaks closer than `distance` (in x-units) into one, spanning all of them
+    /// with the height of the highest one, instead of applying the distance bounds to them. If the
+    /// prominences are computed, the one of the merged peak is computed for its span.
+    pub fn with_merge_distance(&mut self, distance: S) -> &mut Self {
+        let zero = distance.clone() - distance.clone();
+        assert!(zero.le(&distance), "Distance must be positive!");
+
+        self.merge_distance = Some(distance);
+        self
+    }
+
+    /// Treat dips less than `tolerance` deep between two local maxima of the same value as part of
+    /// one plateau. The merged peak has the differences of its outer sides. The hysteresis and the
+    /// other detection methods such as `find_peaks_derivative` do not merge plateaus.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use find_peaks::PeakFinder;
+    /// let y = [0., 3., 3., 2.9, 3., 3., 0.];
+    ///
+    /// assert_eq!(PeakFinder::new(&y).find_peaks().len(), 2);
+    ///
+    /// let ps = PeakFinder::new(&y)
+    ///     .with_merge_plateau_tolerance(0.5)
+    ///     .find_peaks();
+    /// assert_eq!(ps.len(), 1);
+    /// assert_eq!(ps[0].position, 1..6);
+    /// ```
+    pub fn with_merge_plateau_tolerance(&mut self, tolerance: T) -> &mut Self {
+        let zero = tolerance.clone() - tolerance.clone();
+        assert!(zero.le(&tolerance), "Plateau tolerance must be positive!");
+
+        self.plateau_tolerance = Some(tolerance);
+        self
+    }
+
+    /// Split a plateau into two peaks at its center when the larger of its two differences is more
+    /// than `threshold` times the smaller one. Each half keeps the difference of its side and has a
+    /// zero one at the split, which the per-side settings see; the difference bounds are checked
+    /// before splitting. Single-sample, edge and hysteresis peaks are never split.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use find_peaks::PeakFinder;
+    /// let y = [0., 1., 5., 5., 5., 5., 0., 3.];
+    ///
+    /// assert_eq!(PeakFinder::new(&y).find_peaks().len(), 1);
+    ///
+    /// // a rise of 4 and a fall of 5 are alike
+    /// let mut fp = PeakFinder::new(&y);
+    /// fp.with_split_asymmetric_plateaus(2.);
+    /// assert_eq!(fp.find_peaks().len(), 1);
+    ///
+    /// // a rise of 1 and a fall of 5 are not
+    /// let y = [0., 4., 5., 5., 5., 5., 0., 3.];
+    /// let ps = PeakFinder::new(&y).with_split_asymmetric_plateaus(2.).find_peaks();
+    /// let positions: Vec<_> = ps.iter().map(|p| p.position.clone()).collect();
+    /// assert_eq!(positions, vec![2..4, 4..6]);
+    /// ```
+    pub fn with_split_asymmetric_plateaus(&mut self, threshold: f64) -> &mut Self
+    where
+        T: Into<f64>,
+    {
+        assert!(threshold >= 1., "Split threshold must be at least 1!");
+
+        self.split_plateaus = Some(threshold);
+        self.as_f64 = Some(to_f64);
+        self
+    }
+
+    /// Detect the peaks with a Schmitt trigger instead of the neighbour comparison: a peak is the
+    /// highest sample (or plateau) between a rise above `high` and the next fall below `low`. The
+    /// difference bounds and `with_edge_peaks` are not used, the other bounds are.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use find_peaks::PeakFinder;
+    /// let y = [0., 6., 5., 6.5, 5., 2., 4., 0.5, 7., 0.];
+    ///
+    /// let ps = PeakFinder::new(&y).with_hysteresis(1., 5.5).find_peaks();
+    ///
+    /// assert_eq!(
+    ///    ps.iter().map(|x| x.middle_position()).collect::<Vec<_>>(),
+    ///    vec![8, 3]
+    /// );
+    /// ```
+    pub fn with_hysteresis(&mut self, low: T, high: T) -> &mut Self {
+        assert!(
+            low.le(&high),
+            "Lower hysteresis level must not exceed the upper one!"
+        );
+
+        self.hysteresis = Some((low, high));
+        self
+    }
+
+    /// Consuming variant of `with_min_height`.
+    pub fn min_height(mut self, h: T) -> Self {
+        self.with_min_height(h);
+        self
+    }
+
+    /// Consuming variant of `with_max_height`.
+    pub fn max_height(mut self, h: T) -> Self {
+        self.with_max_height(h);
+        self
+    }
+
+    /// Consuming variant of `with_min_prominence`.
+    pub fn min_prominence(mut self, prominence: T) -> Self {
+        self.with_min_prominence(prominence);
+        self
+    }
+
+    /// Consuming variant of `with_max_prominence`.
+    pub fn max_prominence(mut self, prominence: T) -> Self {
+        self.with_max_prominence(prominence);
+        self
+    }
+
+    /// Consuming variant of `with_min_difference`.
+    pub fn min_difference(mut self, difference: T) -> Self {
+        self.with_min_difference(difference);
+        self
+    }
+
+    /// Consuming variant of `with_max_difference`.
+    pub fn max_difference(mut self, difference: T) -> Self {
+        self.with_max_difference(difference);
+        self
+    }
+
+    /// Consuming variant of `with_min_plateau_size`.
+    pub fn min_plateau_size(mut self, size: usize) -> Self {
+        self.with_min_plateau_size(size);
+        self
+    }
+
+    /// Consuming variant of `with_max_plateau_size`.
+    pub fn max_plateau_size(mut self, size: usize) -> Self {
+        self.with_max_plateau_size(size);
+        self
+    }
+
+    /// Consuming variant of `with_min_distance`.
+    pub fn min_distance(mut self, distance: S) -> Self {
+        self.with_min_distance(distance);
+        self
+    }
+
+    /// Consuming variant of `with_max_distance`.
+    pub fn max_distance(mut self, distance: S) -> Self {
+        self.with_max_distance(distance);
+        self
+    }
+
+    /// Clear every bound of the form `with_min_*`/`with_max_*` (or a range) -- height,
+    /// prominence, difference, per-side difference, plateau size, width, x range, distance and
+    /// apex height -- for reusing the finder with other bounds, e.g. when filters are toggled in
+    /// an interactive tool. The minimum difference goes back to its default of zero (see
+    /// `with_min_difference`), as set by `with_zero` if it was. The data and all the other
+    /// settings are kept; `set_config` replaces those of a `PeakConfig` as well.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use find_peaks::PeakFinder;
+    /// let y = [0., 3., 0., 1., 0., 2., 0.];
+    ///
+    /// let mut fp = PeakFinder::new(&y);
+    /// let all = fp.find_peaks();
+    /// fp.with_min_height(2.5).with_min_distance(3);
+    /// assert_eq!(fp.find_peaks().len(), 1);
+    ///
+    /// fp.reset();
+    /// assert_eq!(fp.find_peaks(), all);
+    /// ```
+    pub fn reset(&mut self) -> &mut Self {
+        self.height = Limits::empty();
+        self.prominence = Limits::empty();
+        self.difference = self.default_difference();
+        self.left_difference = Limits::empty();
+        self.right_difference = Limits::empty();
+        self.plateau_size = Limits::empty();
+        self.width_x = Limits::empty();
+        self.x_range = Limits::empty();
+        self.distance = Limits::empty();
+        self.apex_height = Limits::empty();
+        self
+    }
+
+    /// Unset the bound of `with_min_height`.
+    pub fn without_min_height(&mut self) -> &mut Self {
+        self.height.lower = None;
+        self
+    }
+
+    /// Unset the bound of `with_max_height`.
+    pub fn without_max_height(&mut self) -> &mut Self {
+        self.height.upper = None;
+        self
+    }
+
+    /// Unset the bound of `with_min_prominence`.
+    pub fn without_min_prominence(&mut self) -> &mut Self {
+        self.prominence.lower = None;
+        self
+    }
+
+    /// Unset the bound of `with_max_prominence`.
+    pub fn without_max_prominence(&mut self) -> &mut Self {
+        self.prominence.upper = None;
+        self
+    }
+
+    /// Unset the bound of `with_min_difference`, back to its default of zero.
+    pub fn without_min_difference(&mut self) -> &mut Self {
+        self.difference.lower = self.default_difference().lower;
+        self
+    }
+
+    /// Unset the bound of `with_max_difference`.
+    pub fn without_max_difference(&mut self) -> &mut Self {
+        self.difference.upper = None;
+        self
+    }
+
+    /// Unset the bound of `with_min_plateau_size`.
+    pub fn without_min_plateau_size(&mut self) -> &mut Self {
+        self.plateau_size.lower = None;
+        self
+    }
+
+    /// Unset the bound of `with_max_plateau_size`.
+    pub fn without_max_plateau_size(&mut self) -> &mut Self {
+        self.plateau_size.upper = None;
+        self
+    }
+
+    /// Unset the bound of `with_min_width_x`.
+    pub fn without_min_width_x(&mut self) -> &mut Self {
+        self.width_x.lower = None;
+        self
+    }
+
+    /// Unset the bound of `with_max_width_x`.
+    pub fn without_max_width_x(&mut self) -> &mut Self {
+        self.width_x.upper = None;
+        self
+    }
+
+    /// Unset the bound of `with_min_distance`.
+    pub fn without_min_distance(&mut self) -> &mut Self {
+        self.distance.lower = None;
+        self
+    }
+
+    /// Unset the bound of `with_max_distance`.
+    pub fn without_max_distance(&mut self) -> &mut Self {
+        self.distance.upper = None;
+        self
+    }
+
+    /// Snapshot of the current settings.
+    pub fn config(&self) -> PeakConfig<T, S> {
+        PeakConfig {
+            height: self.height.clone(),
+            prominence: self.prominence.clone(),
+            difference: self.difference.clone(),
+            side_thresholds: self.side_thresholds.clone(),
+            left_difference: self.left_difference.clone(),
+            right_difference: self.right_difference.clone(),
+            min_runs: self.min_runs,
+            run_plateaus: self.run_plateaus,
+            plateau_size: self.plateau_size.clone(),
+            width_x: self.width_x.clone(),
+            x_range: self.x_range.clone(),
+            distance: self.distance.clone(),
+            edge_peaks: self.edge_peaks,
+            prominence_window: self.prominence_window,
+            prominence_max_scan: self.prominence_max_scan,
+            local_baseline: self.local_baseline,
+            prominence_reference: self.prominence_reference.clone(),
+            merge_distance: self.merge_distance.clone(),
+            plateau_tolerance: self.plateau_tolerance.clone(),
+            hysteresis: self.hysteresis.clone(),
+            min_sample_gap: self.min_sample_gap,
+            local_window: self.local_window,
+            saturation_level: self.saturation_level.clone(),
+            drop_clipped: self.drop_clipped,
+            compute_all: self.compute_all,
+            rank: self.compute_rank,
+            max_peaks: self.max_peaks,
+            target_peak_count: self.target_peak_count,
+            expected_peaks: self.expected_peaks,
+            sort_order: self.sort_order,
+            distance_priority: self.distance_priority,
+            distance_first: self.distance_first,
+            tie_break: self.tie_break,
+            direction: self.direction,
+            abs: self.abs,
+            min_peaks: self.min_peaks,
+            classification_thresholds: self.classification_thresholds,
+            split_plateaus: self.split_plateaus,
+            local_contrast: self.local_contrast,
+            min_relative_prominence: self.min_relative_prominence,
+            min_relative_difference: self.min_relative_difference,
+            min_prominence_ratio: self.min_prominence_ratio,
+            dog: self.dog,
+            log_amplitude: self.log_amplitude,
+            resample: self.resample,
+            score_weights: self.score_weights,
+            apex_height: self.apex_height.clone(),
+            symmetry: self.compute_symmetry,
+            widths: self.compute_widths,
+            sharpness: self.compute_sharpness,
+            min_distance_fraction: self.min_distance_fraction,
+            circular: self.circular.is_some(),
+            conversions: F64Conversions {
+                as_f64: self.as_f64,
+                from_f64: self.from_f64,
+                x_as_f64: self.x_as_f64,
+                x_from_f64: self.x_from_f64,
+            },
+        }
+    }
+
+    /// Replace the settings by those of `config`.
+    ///
+    /// # Panics
+    ///
+    /// If `config` has settings computed in `f64` but neither the config nor this finder has
+    /// the conversions they need, see `PeakConfig::with_f64_conversions`.
+    pub fn set_config(&mut self, config: PeakConfig<T, S>) -> &mut Self
+    where
+        S: std::ops::Add<Output = S>,
+    {
+        self.height = config.height;
+        self.prominence = config.prominence;
+        self.difference = config.difference;
+        self.side_thresholds = config.side_thresholds;
+        self.left_difference = config.left_difference;
+        self.right_difference = config.right_difference;
+        self.min_runs = config.min_runs;
+        self.run_plateaus = config.run_plateaus;
+        self.plateau_size = config.plateau_size;
+        self.width_x = config.width_x;
+        self.x_range = config.x_range;
+        self.distance = config.distance;
+        self.edge_peaks = config.edge_peaks;
+        self.prominence_window = config.prominence_window;
+        self.prominence_max_scan = config.prominence_max_scan;
+        self.local_baseline = config.local_baseline;
+        self.prominence_reference = config.prominence_reference;
+        self.merge_distance = config.merge_distance;
+        self.plateau_tolerance = config.plateau_tolerance;
+        self.hysteresis = config.hysteresis;
+        self.min_sample_gap = config.min_sample_gap;
+        self.local_window = config.local_window;
+        self.saturation_level = config.saturation_level;
+        self.drop_clipped = config.drop_clipped;
+        self.compute_all = config.compute_all;
+        self.compute_rank = config.rank;
+        self.max_peaks = config.max_peaks;
+        self.target_peak_count = config.target_peak_count;
+        self.expected_peaks = config.expected_peaks;
+        self.sort_order = config.sort_order;
+        self.distance_priority = config.distance_priority;
+        self.distance_first = config.distance_first;
+        self.tie_break = config.tie_break;
+        self.direction = config.direction;
+        self.abs = config.abs;
+        self.min_peaks = config.min_peaks;
+        self.classification_thresholds = config.classification_thresholds;
+        self.split_plateaus = config.split_plateaus;
+        self.local_contrast = config.local_contrast;
+        self.min_relative_prominence = config.min_relative_prominence;
+        self.min_relative_difference = config.min_relative_difference;
+        self.min_prominence_ratio = config.min_prominence_ratio;
+        self.dog = config.dog;
+        self.log_amplitude = config.log_amplitude;
+        self.resample = config.resample;
+        self.score_weights = config.score_weights;
+        self.apex_height = config.apex_height;
+        self.compute_symmetry = config.symmetry;
+        self.compute_widths = config.widths;
+        self.compute_sharpness = config.sharpness;
+        self.min_distance_fraction = config.min_distance_fraction;
+        self.with_circular(config.circular);
+
+        let conversions = config.conversions;
+        self.as_f64 = conversions.as_f64.or(self.as_f64);
+        self.from_f64 = conversions.from_f64.or(self.from_f64);
+        self.x_as_f64 = conversions.x_as_f64.or(self.x_as_f64);
+        self.x_from_f64 = conversions.x_from_f64.or(self.x_from_f64);
+
+        // the settings computed in `f64`, by the conversions they need
+        let converts_back =
+            self.dog.is_some() || self.log_amplitude.is_some() || self.resample.is_some();
+        let converts = converts_back
+            || self.compute_widths
+            || self.compute_symmetry
+            || self.split_plateaus.is_some()
+            || self.local_contrast.is_some()
+            || self.min_relative_prominence.is_some()
+            || self.min_relative_difference.is_some()
+            || self.min_prominence_ratio.is_some()
+            || self.score_weights.is_some()
+            || !self.apex_height.is_empty();
+        let converts_x =
+            self.compute_widths || self.min_distance_fraction.is_some() || self.resample.is_some();
+        let missing = (converts && self.as_f64.is_none())
+            || (converts_back && self.from_f64.is_none())
+            || (converts_x && self.x_as_f64.is_none())
+            || (self.resample.is_some() && self.x_from_f64.is_none());
+        assert!(
+            !missing,
+            "The config needs the conversions to f64, see `PeakConfig::with_f64_conversions`!"
+        );
+        self
+    }
+
+    /// Replace the y-values by `y` of the same length, keeping the x-values and all the
+    /// settings, e.g. to analyze a stream of equally long frames with one finder.
+    ///
+    /// The settings computed from the data when they were set (`with_height_reference`,
+    /// `with_auto_prominence`) keep their values from the earlier data; set them again to
+    /// recompute them. The reference of `new_with_reference` is dropped, the peaks are then
+    /// those of `y` itself.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use find_peaks::PeakFinder;
+    /// let frames = [[0., 2., 0., 1., 0.], [0., 1., 0., 3., 0.]];
+    ///
+    /// let mut fp = PeakFinder::new(&frames[0]);
+    /// fp.with_min_height(1.5);
+    /// for frame in &frames {
+    ///     fp.set_y_data(frame);
+    ///     assert_eq!(fp.find_peaks().len(), 1);
+    /// }
+    /// ```
+    pub fn set_y_data(&mut self, y: &'a [T]) -> &mut Self {
+        assert_eq!(
+            y.len(),
+            self.y_data.len(),
+            "The new data must have the same length!"
+        );
+
+        self.zero = y.first().map(|y| y.clone() - y.clone());
+        self.y_data = Cow::Borrowed(y);
+        self.measured = None;
+        self
+    }
+
+    /// Attach a label computed by `labeler` to every returned peak, e.g. to tag the peaks with
+    /// the source of the data. The labeler is called once per peak after all the bounds were
+    /// applied.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use find_peaks::PeakFinder;
+    /// let y = [1., 2., 3., 0., 5., 0.];
+    /// let source = String::from("run 7");
+    ///
+    /// let ps = PeakFinder::new(&y)
+    ///     .with_labeler(move |p| format!("{}: {}", source, p.middle_position()))
+    ///     .find_peaks();
+    ///
+    /// assert_eq!(ps[0].label.as_deref(), Some("run 7: 4"));
+    /// ```
+    pub fn with_labeler<F>(&mut self, labeler: F) -> &mut Self
+    where
+        F: Fn(&Peak<T>) -> String + Send + Sync + 'static,
+    {
+        self.labeler = Some(Arc::new(labeler));
+        self
+    }
+
+    /// Measure the distance between two peaks with the symmetric `metric` of their x-values, e.g. a
+    /// ratio for logarithmic frequencies, for the distance bounds and the merge distance. The
+    /// x-values need not be monotonic then, but each peak is compared with every higher kept one.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use find_peaks::PeakFinder;
+    /// let x = [10., 20., 40., 80., 160., 320., 640.];
+    /// let y = [0., 2., 0., 3., 0., 1., 0.];
+    ///
+    /// // distances in octaves
+    /// let ps = PeakFinder::new_with_x(&y, &x)
+    ///     .with_distance_metric(|a: &f64, b: &f64| (a / b).log2().abs())
+    ///     .with_min_distance(3.)
+    ///     .find_peaks();
+    ///
+    /// assert_eq!(ps.len(), 1);
+    /// assert_eq!(ps[0].position, 3..4);
+    /// ```
+    pub fn with_distance_metric<F>(&mut self, metric: F) -> &mut Self
+    where
+        F: Fn(&S, &S) -> S + Send + Sync + 'static,
+    {
+        self.distance_metric = Some(Arc::new(metric));
+        self
+    }
+
+    /// Keep only the peaks for which `filter` returns `true` for the peak and the data. It runs
+    /// after all the other bounds on the peaks of `y_data` (a peak across the seam of circular
+    /// data ends after the last index), or before the distance bounds if `before_distance` is set,
+    /// then on the data scanned (e.g. rotated or reversed). It sees the properties computed so far
+    /// (the symmetry and widths only after the distance bounds).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use find_peaks::PeakFinder;
+    /// let y = [0., 3., 2., 0., 2., 0., 4., 0.];
+    ///
+    /// // keep peaks whose left difference exceeds twice their right difference
+    /// let ps = PeakFinder::new(&y)
+    ///     .with_filter(|p, _| p.left_diff > 2. * p.right_diff, false)
+    ///     .find_peaks();
+    ///
+    /// assert_eq!(ps.len(), 1);
+    /// assert_eq!(ps[0].position, 1..2);
+    /// ```
+    pub fn with_filter<F>(&mut self, filter: F, before_distance: bool) -> &mut Self
+    where
+        F: Fn(&Peak<T>, &[T]) -> bool + Send + Sync + 'static,
+    {
+        self.filter = Some((Arc::new(filter), before_distance));
+        self
+    }
+
+    /// Reserve room for `n` candidate peaks before the scan (default: none), to avoid growing the
+    /// vector step by step on data known to be dense with peaks. It is only a hint; the peaks are
+    /// separated by lower samples, so `(y_data.len() + 1) / 2` is always enough.
+    pub fn with_expected_peaks(&mut self, n: usize) -> &mut Self {
+        self.expected_peaks = Some(n);
+        self
+    }
+
+    /// Compute the height and the prominence of every returned peak, even if no bound needs
+    /// them (default: `false`). Without bounds all the peaks pass, so the properties are always
+    /// `Some` at the cost of computing the prominences.
+    pub fn with_compute_all(&mut self, compute_all: bool) -> &mut Self {
+        self.compute_all = compute_all;
+        self
+    }
+
+    /// Store the rank of each returned peak in `Peak::rank` (default: `false`): `0` for the most
+    /// prominent peak, or the highest if not all the peaks have a prominence. Equal ones are
+    /// ordered by position; the labeler sees the rank.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use find_peaks::{PeakFinder, SortOrder};
+    /// let y = [0., 3., 2.5, 2.7, 0., 2., 0.];
+    ///
+    /// let ps = PeakFinder::new(&y)
+    ///     .with_min_prominence(0.)
+    ///     .with_rank(true)
+    ///     .with_sort_order(SortOrder::Position)
+    ///     .find_peaks();
+    ///
+    /// // the 2.7 is higher but less prominent than the 2.
+    /// assert_eq!(ps.iter().map(|p| p.rank).collect::<Vec<_>>(), vec![Some(0), Some(2), Some(1)]);
+    /// ```
+    pub fn with_rank(&mut self, rank: bool) -> &mut Self {
+        self.compute_rank = rank;
+        self
+    }
+
+    /// Keep only the `n` highest of the peaks passing all the other bounds and filters (default:
+    /// all of them), with ties taken by `with_tie_break`; the ranks and labels are computed for
+    /// these peaks only.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use find_peaks::{PeakFinder, SortOrder};
+    /// let y = [0., 3., 0., 5., 0., 1., 0., 4., 0.];
+    ///
+    /// let ps = PeakFinder::new(&y)
+    ///     .with_max_peaks(2)
+    ///     .with_sort_order(SortOrder::Position)
+    ///     .find_peaks();
+    ///
+    /// assert_eq!(ps.iter().map(|p| p.middle_position()).collect::<Vec<_>>(), vec![3, 7]);
+    /// ```
+    pub fn with_max_peaks(&mut self, n: usize) -> &mut Self {
+        assert!(n > 0, "Maximum number of peaks must be positive!");
+        self.max_peaks = Some(n);
+        self
+    }
+
+    /// Relax the minimum distance when it keeps fewer than `n` peaks: the largest smaller distance
+    /// keeping at least `n` is found by bisection (or the smallest one if none does). The selection
+    /// stays greedy, so more than `n` peaks may be kept; without a minimum distance this does
+    /// nothing.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use find_peaks::PeakFinder;
+    /// let y = [0., 5., 0., 4., 0., 3., 0., 0., 2., 0.];
+    ///
+    /// let mut fp = PeakFinder::new(&y);
+    /// fp.with_min_distance(10);
+    /// assert_eq!(fp.find_peaks().len(), 1);
+    ///
+    /// // the distance is relaxed to 3, which keeps the peaks at 1, 5 and 8
+    /// let ps = fp.with_target_peak_count(3).find_peaks();
+    /// let middles: Vec<usize> = ps.iter().map(|p| p.middle_position()).collect();
+    /// assert_eq!(middles, vec![1, 5, 8]);
+    /// ```
+    pub fn with_target_peak_count(&mut self, n: usize) -> &mut Self {
+        assert!(n > 0, "Target number of peaks must be positive!");
+        self.target_peak_count = Some(n);
+        self
+    }
+
+    /// Configure the finder like scipy's `find_peaks` with its default arguments, to set the
+    /// bounds on top of: no peaks at the edges, the prominence measured from the local valleys
+    /// without a window, the distance bounds applied before the prominence bounds and keeping
+    /// the higher peak, and the peaks sorted by position. The heights, prominences and widths
+    /// are computed for all the peaks (as by `with_compute_all` and `with_widths`).
+    ///
+    /// The properties returned by scipy are then found in the peaks as follows:
+    ///
+    /// | scipy                                 | `Peak`                                        |
+    /// |---------------------------------------|-----------------------------------------------|
+    /// | `peaks`                               | `center(PlateauCenter::Floor)`                |
+    /// | `peak_heights`                        | `height`                                      |
+    /// | `left_thresholds`, `right_thresholds` | `left_diff`, `right_diff`                     |
+    /// | `prominences`                         | `prominence`                                  |
+    /// | `left_bases`, `right_bases`           | `valley_index` (only the lower one of them)   |
+    /// | `widths`                              | `left_width` + `right_width`                  |
+    /// | `plateau_sizes`                       | `position.len()`                              |
+    /// | `left_edges`, `right_edges`           | `position.start`, `position.end - 1`          |
+    ///
+    /// The distances are still measured between the `middle_position`s of the peaks, which
+    /// for plateaus of even size is one sample right of scipy's peak.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use find_peaks::{PeakFinder, PlateauCenter};
+    /// let y = [0., 1., 0., 2., 2., 0., 3., 1., 2., 0.];
+    ///
+    /// let ps = PeakFinder::new(&y).scipy_compatible().find_peaks();
+    ///
+    /// // scipy: peaks [1, 3, 6, 8], prominences [1., 2., 3., 1.], widths [1., 2., 1.25, 0.75]
+    /// let peaks: Vec<usize> = ps.iter().map(|p| p.center(PlateauCenter::Floor)).collect();
+    /// assert_eq!(peaks, vec![1, 3, 6, 8]);
+    /// let prominences: Vec<f64> = ps.iter().map(|p| p.prominence.unwrap()).collect();
+    /// assert_eq!(prominences, vec![1., 2., 3., 1.]);
+    /// let widths: Vec<f64> = ps
+    ///     .iter()
+    ///     .map(|p| p.left_width.unwrap() + p.right_width.unwrap())
+    ///     .collect();
+    /// assert_eq!(widths, vec![1., 2., 1.25, 0.75]);
+    /// ```
+    pub fn scipy_compatible(&mut self) -> &mut Self
+    where
+        T: Into<f64>,
+        S: AsF64,
+    {
+        self.edge_peaks = false;
+        self.prominence_reference = ProminenceRef::LocalValleys;
+        self.prominence_window = None;
+        self.prominence_max_scan = None;
+        self.local_baseline = None;
+        self.distance_priority = DistancePriority::Height;
+        self.distance_first = true;
+        self.sort_order = SortOrder::Position;
+        self.compute_all = true;
+        self.with_widths()
+    }
+
+    /// Start from settings for the R-peaks of an electrocardiogram sampled at `sample_rate_hz`,
+    /// to adjust from there: a minimum prominence of 0.5 and at least 200 ms between peaks
+    /// (`with_min_sample_gap`, so in samples whatever the x-values are), the shortest interval
+    /// of a heart rate of 300 beats per minute.
+    ///
+    /// The prominence assumes the data are in millivolts with the R-peaks pointing up, rising
+    /// about 1 mV above the rest of the beat; for data in other units (e.g. raw ADC counts) or
+    /// with smaller R-peaks, set `with_min_prominence` afterwards, and negate the data of
+    /// inverted leads. T waves of a slow heart, further than 200 ms from the R-peaks, can still
+    /// pass the prominence; raise it or the sample gap to drop them.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use find_peaks::PeakFinder;
+    /// // two beats 0.5 s apart at 100 Hz, with a smaller wave 0.1 s after the first R-peak
+    /// let mut y = vec![0.; 100];
+    /// y[20] = 1.2;
+    /// y[30] = 0.8;
+    /// y[70] = 1.1;
+    ///
+    /// let ps = PeakFinder::new(&y).ecg_preset(100.).find_peaks();
+    ///
+    /// assert_eq!(ps.iter().map(|p| p.middle_position()).collect::<Vec<_>>(), vec![20, 70]);
+    /// ```
+    pub fn ecg_preset(&mut self, sample_rate_hz: f64) -> &mut Self
+    where
+        T: FromF64,
+    {
+        assert!(sample_rate_hz > 0., "Sample rate must be positive!");
+
+        self.with_min_prominence(T::from_f64(0.5))
+            .with_min_sample_gap((0.2 * sample_rate_hz).ceil() as usize)
+    }
+
+    /// Use `zero` as the additive identity of the data instead of `y_data[0] - y_data[0]`, for
+    /// types where that is not a usable zero (or to have one for empty data). A lower difference
+    /// bound that is still the default is replaced as well.
+    pub fn with_zero(&mut self, zero: T) -> &mut Self {
+        // an unordered derived zero (NaN) can not have been set through the bound setters
+        let is_default = match (&self.difference.lower, &self.zero) {
+            (Some(lower), Some(old)) => lower == old || lower.partial_cmp(lower).is_none(),
+            (None, None) => true,
+            _ => false,
+        };
+        if is_default {
+            self.difference.lower = Some(zero.clone());
+        }
+        self.zero = Some(zero);
+        self
+    }
+
+    /// Set the order of the returned peaks (default: `SortOrder::Height`).
+    ///
+    /// `SortOrder::Position` gives the order of scipy's `find_peaks`, so that the results can
+    /// be compared directly.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use find_peaks::{PeakFinder, SortOrder};
+    /// let y = [1., 2., 3., 0., 5., 0.];
+    ///
+    /// let ps = PeakFinder::new(&y)
+    ///     .with_sort_order(SortOrder::Position)
+    ///     .find_peaks();
+    ///
+    /// assert_eq!(ps.iter().map(|p| p.middle_position()).collect::<Vec<_>>(), vec![2, 4]);
+    /// ```
+    pub fn with_sort_order(&mut self, order: SortOrder) -> &mut Self {
+        self.sort_order = order;
+        self
+    }
+
+    /// Also consider the first and the last sample as peak candidates (default: `false`), if they
+    /// are greater than their interior neighbour. The difference on the outer side is zero and not
+    /// checked. An edge plateau is one peak covering all of it; constant data still have no peaks.
+    pub fn with_edge_peaks(&mut self, edge_peaks: bool) -> &mut Self {
+        self.edge_peaks = edge_peaks;
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        find_peaks_by_height, find_peaks_by_prominence, nearest_peak, peaks_in_range,
+        refine_positions, remap_positions, write_peaks_csv, ClassificationThresholds, CostEstimate,
+        DetectionStats, Direction, DistancePriority, ExtremumKind, GlobalExtrema, HeightRef,
+        Limits, Peak, PeakColumns, PeakConfig, PeakError, PeakExt, PeakFinder, PeakShape,
+        PlateauCenter, ProminenceRef, ScoreWeights, Slope, SortOrder, TieBreak,
+    };
+
+    /// The fields of a `Peak` that the tests compare, the others are checked where they matter.
+    #[derive(Debug, PartialEq)]
+    struct BasePeak<T> {
+        position: std::ops::Range<usize>,
+        left_diff: T,
+        right_diff: T,
+        height: Option<T>,
+        prominence: Option<T>,
+    }
+
+    fn base<T: Clone>(ps: &[Peak<T>]) -> Vec<BasePeak<T>> {
+        ps.iter()
+            .map(|p| BasePeak {
+                position: p.position.clone(),
+                left_diff: p.left_diff.clone(),
+                right_diff: p.right_diff.clone(),
+                height: p.height.clone(),
+                prominence: p.prominence.clone(),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn findpeaks() {
+        let y = [1., 2., 3., 0., 5., 0.];
+        let mut fp = PeakFinder::new(&y);
+        fp.with_min_height(0.);
+        let ps = fp.find_peaks();
+        assert_eq!(
+            base(&ps),
+            vec![
+                BasePeak {
+                    position: 4..5,
+                    left_diff: 5.,
+                    right_diff: 5.,
+                    height: Some(5.),
+                    prominence: None
+                },
+                BasePeak {
+                    position: 2..3,
+                    left_diff: 1.,
+                    right_diff: 3.,
+                    height: Some(3.),
+                    prominence: None
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn proms() {
+        let y = [1., 2., 3., 0., 5., 0.];
+        let mut fp = PeakFinder::new(&y);
+        fp.with_min_height(0.);
+        fp.with_min_prominence(1.);
+        let ps = fp.find_peaks();
+        assert_eq!(
+            base(&ps),
+            vec![
+                BasePeak {
+                    position: 4..5,
+                    left_diff: 5.,
+                    right_diff: 5.,
+                    height: Some(5.),
+                    prominence: Some(5.)
+                },
+                BasePeak {
+                    position: 2..3,
+                    left_diff: 1.,
+                    right_diff: 3.,
+                    height: Some(3.),
+                    prominence: Some(2.)
+                }
+            ]
+        );
+    }
+
+    #[test]
+    fn plateaus() {
+        let y = [1., 2., 3., 3., 3., 0., 5., 5., 0.];
+        let mut fp = PeakFinder::new(&y);
+        fp.with_min_height(0.);
+        fp.with_min_prominence(0.);
+
+        let ps = fp.find_peaks();
+
+        assert_eq!(
+            base(&ps),
+            vec![
+                BasePeak {
+                    position: 6..8,
+                    left_diff: 5.,
+                    right_diff: 5.,
+                    height: Some(5.),
+                    prominence: Some(5.)
+                },
+                BasePeak {
+                    position: 2..5,
+                    left_diff: 1.,
+                    right_diff: 3.,
+                    height: Some(3.),
+                    prominence: Some(2.)
+                }
+            ]
+        );
+
+        fp.with_min_plateau_size(3);
+        let ps = fp.find_peaks();
+
+        assert_eq!(
+            base(&ps),
+            vec![BasePeak {
+                position: 2..5,
+                left_diff: 1.,
+                right_diff: 3.,
+                height: Some(3.),
+                prominence: Some(2.)
+            }]
+        );
+    }
+
+    #[test]
+    fn plateau_with_diff() {
+        let y = [1., 2., 3., 3., 3., 0., 5., 5., 0.];
+        let mut fp = PeakFinder::new(&y);
+        fp.with_min_prominence(0.);
+        fp.with_min_height(0.);
+
+        fp.with_min_difference(4.);
+        let ps = fp.find_peaks();
+
+        assert_eq!(
+            base(&ps),
+            vec![BasePeak {
+                position: 6..8,
+                left_diff: 5.,
+                right_diff: 5.,
+                height: Some(5.),
+                prominence: Some(5.)
+            }]
+        );
+    }
+
+    #[test]
+    fn with_x() {
+        let y = [1., 2., 3., 0., 5., 0.];
+        let x: Vec<usize> = (1..(y.len() + 1)).collect();
+        let ps = PeakFinder::new_with_x(&y, &x)
+            .with_min_height(0.)
+            .with_min_distance(2)
+            .find_peaks();
+        assert_eq!(
+            base(&ps),
+            vec![
+                BasePeak {
+                    position: 4..5,
+                    left_diff: 5.,
+                    right_diff: 5.,
+                    height: Some(5.),
+                    prominence: None
+                },
+                BasePeak {
+                    position: 2..3,
+                    left_diff: 1.,
+                    right_diff: 3.,
+                    height: Some(3.),
+                    prominence: None
+                }
+            ]
+        );
+    }
+
+    #[test]
+    fn empty_data() {
+        let y: Vec<u8> = vec![];
+        let ps = PeakFinder::new(&y).with_min_prominence(1).find_peaks();
+        let ps2 = PeakFinder::new(&y).with_min_distance(1).find_peaks();
+        assert_eq!(ps, Vec::new());
+        assert_eq!(ps2, Vec::new());
+    }
+
+    #[test]
+    fn single_point() {
+        let y: Vec<u32> = vec![1];
+        let ps = PeakFinder::new(&y).find_peaks();
+
+        assert_eq!(ps, vec![]);
+    }
+
+    #[test]
+    fn two_points() {
+        let y: Vec<u32> = vec![2, 2];
+        let ps = PeakFinder::new(&y).find_peaks();
+        let ps2 = PeakFinder::new(&y)
+            .with_min_prominence(3)
+            .with_min_distance(1)
+            .find_peaks();
+
+        assert_eq!(ps, vec![]);
+        assert_eq!(ps2, vec![]);
+    }
+
+    #[test]
+    fn three_points() {
+        // TODO unsigned subtraction may fail -> try with u32
+        let y: Vec<i32> = vec![2, 3, 2];
+        let ps = PeakFinder::new(&y).with_min_height(0).find_peaks();
+        let ps2 = PeakFinder::new(&y).with_min_prominence(2).find_peaks();
+        let ps3 = PeakFinder::new(&y)
+            .with_min_prominence(2)
+            .with_min_distance(1)
+            .find_peaks();
+
+        assert_eq!(
+            base(&ps),
+            vec![BasePeak {
+                position: 1..2,
+                left_diff: 1,
+                right_diff: 1,
+                height: Some(3),
+                prominence: None
+            },]
+        );
+        assert_eq!(ps2, vec![]);
+        assert_eq!(ps3, vec![]);
+    }
+
+    #[test]
+    fn edge_peaks() {
+        let y: Vec<i32> = vec![1, 2, 3, 4, 5];
+        let ps = PeakFinder::new(&y).find_peaks();
+        let ps2 = PeakFinder::new(&y).with_edge_peaks(true).find_peaks();
+
+        assert_eq!(ps, vec![]);
+        assert_eq!(
+            base(&ps2),
+            vec![BasePeak {
+                position: 4..5,
+                left_diff: 1,
+                right_diff: 0,
+                height: Some(5),
+                prominence: None,
+            }]
+        );
+
+        let y = [5., 3., 4., 1.];
+        let ps = PeakFinder::new(&y)
+            .with_edge_peaks(true)
+            .with_min_prominence(0.)
+            .find_peaks();
+
+        assert_eq!(
+            base(&ps),
+            vec![
+                BasePeak {
+                    position: 0..1,
+                    left_diff: 0.,
+                    right_diff: 2.,
+                    height: Some(5.),
+                    prominence: Some(4.),
+                },
+                BasePeak {
+                    position: 2..3,
+                    left_diff: 1.,
+                    right_diff: 3.,
+                    height: Some(4.),
+                    prominence: Some(1.),
+                }
+            ]
+        );
+        // the valley searches of both run into the edge
+        assert!(ps.iter().all(|p| p.bounded_by_edge));
+    }
+
+    #[test]
+    fn relative_prominence() {
+        let y = [0., 10., 9.5, 20., 0.];
+        let ps = PeakFinder::new(&y)
+            .with_min_relative_prominence(0.1)
+            .find_peaks();
+
+        assert_eq!(
+            base(&ps),
+            vec![BasePeak {
+                position: 3..4,
+                left_diff: 10.5,
+                right_diff: 20.,
+                height: Some(20.),
+                prominence: Some(20.),
+            }]
+        );
+    }
+
+    #[test]
+    fn width_x() {
+        let y = [0., 2., 2., 0., 3., 3., 0.];
+        let x = [0., 1., 1.5, 3., 4., 6., 7.];
+        let ps = PeakFinder::new_with_x(&y, &x)
+            .with_min_width_x(1.)
+            .find_peaks();
+
+        assert_eq!(
+            base(&ps),
+            vec![BasePeak {
+                position: 4..6,
+                left_diff: 3.,
+                right_diff: 3.,
+                height: Some(3.),
+                prominence: None,
+            }]
+        );
+
+        let ps = PeakFinder::new_with_x(&y, &x)
+            .with_max_width_x(1.)
+            .find_peaks();
+
+        assert_eq!(
+            ps.iter().map(|p| p.position.clone()).collect::<Vec<_>>(),
+            vec![1..3]
+        );
+    }
+
+    #[test]
+    fn extrema() {
+        let y = [1., 2., 2., 0., 0., 3., 1.];
+        let es = PeakFinder::new(&y).find_extrema();
+
+        assert_eq!(
+            es.iter()
+                .map(|e| (
+                    e.peak.position.clone(),
+                    e.peak.left_diff,
+                    e.peak.right_diff,
+                    e.kind
+                ))
+                .collect::<Vec<_>>(),
+            vec![
+                (1..3, 1., 2., ExtremumKind::Maximum),
+                (3..5, 2., 3., ExtremumKind::Minimum),
+                (5..6, 3., 2., ExtremumKind::Maximum),
+            ]
+        );
+        assert!(es.iter().all(|e| e.peak.height.is_none()));
+    }
+
+    #[test]
+    fn persistence() {
+        // pseudo-random data without ties
+        let mut state = 12345u64;
+        let y: Vec<f64> = (0..200)
+            .map(|_| {
+                state = state.wrapping_mul(6364136223846793005).wrapping_add(1);
+                (state >> 33) as f64 / 1e6
+            })
+            .collect();
+
+        let mut ps = PeakFinder::new(&y).with_min_prominence(0.).find_peaks();
+        let mut ps2 = PeakFinder::new(&y).find_peaks_persistence();
+        // the persistence sweep finds no valleys
+        for p in &mut ps {
+            p.valley_index = None;
+            p.valley_value = None;
+            p.left_prominence = None;
+            p.right_prominence = None;
+            p.bounded_by_edge = false;
+        }
+        ps.sort_by_key(|p| p.position.start);
+        ps2.sort_by_key(|p| p.position.start);
+
+        assert!(!ps.is_empty());
+        assert_eq!(ps, ps2);
+
+        let y = [1., 2., 3., 3., 3., 0., 5., 5., 0.];
+        let ps = PeakFinder::new(&y)
+            .with_min_prominence(3.)
+            .find_peaks_persistence();
+
+        assert_eq!(
+            base(&ps),
+            vec![BasePeak {
+                position: 6..8,
+                left_diff: 5.,
+                right_diff: 5.,
+                height: Some(5.),
+                prominence: Some(5.),
+            }]
+        );
+    }
+
+    #[test]
+    fn persistence_pipeline() {
+        let y = [0., 4., 1., 3., 0., 5., 2., 2.5, 0.];
+        let mut fp = PeakFinder::new(&y);
+        fp.with_max_peaks(2)
+            .with_rank(true)
+            .with_labeler(|p| format!("{}", p.position.start));
+
+        let ps = fp.find_peaks_persistence();
+        assert_eq!(ps.len(), 2);
+        assert_eq!(ps[0].position, 5..6);
+        assert_eq!(ps[0].prominence, Some(5.));
+        assert_eq!(ps[0].rank, Some(0));
+        assert_eq!(ps[1].label, Some("1".to_string()));
+
+        // on the absolute values, as find_peaks
+        let y = [0., -4., -1., 3., 0.];
+        let ps = PeakFinder::new(&y).with_abs(true).find_peaks_persistence();
+        assert_eq!(ps.len(), 2);
+        assert_eq!(ps[1].height, Some(-4.));
+        assert_eq!(ps[1].prominence, Some(4.));
+    }
+
+    #[test]
+    fn prominence_through_equal_heights() {
+        // the valley search of the peak at 1 continues past the equally high sample at 3
+        let y = [0., 3., 1., 3., 0.5, 4., 2.];
+        let mut ps = PeakFinder::new(&y).with_min_prominence(0.).find_peaks();
+        ps.sort_by_key(|p| p.position.start);
+
+        assert_eq!(
+            ps.iter()
+                .map(|p| (p.position.start, p.prominence.unwrap()))
+                .collect::<Vec<_>>(),
+            vec![(1, 2.5), (3, 2.5), (5, 2.)]
+        );
+    }
+
+    #[test]
+    fn prominence_window() {
+        let y = [5., 0., 1., 3., 1., 2., 0.5];
+        let ps = PeakFinder::new(&y).with_min_prominence(2.).find_peaks();
+        let ps2 = PeakFinder::new(&y)
+            .with_min_prominence(2.)
+            .with_prominence_window(2)
+            .find_peaks();
+
+        assert_eq!(ps.len(), 1);
+        assert_eq!(ps[0].prominence, Some(2.5));
+        assert_eq!(
+            base(&ps2),
+            vec![BasePeak {
+                position: 3..4,
+                left_diff: 2.,
+                right_diff: 2.,
+                height: Some(3.),
+                prominence: Some(2.),
+            }]
+        );
+        assert_eq!(ps2[0].valley_index, Some(2));
+    }
+
+    #[test]
+    fn stats() {
+        let y = [1., 2., 3., 3., 3., 0., 5., 5., 0., 1., 0.];
+        let (ps, stats) = PeakFinder::new(&y)
+            .with_min_difference(1.)
+            .with_min_plateau_size(2)
+            .with_min_prominence(2.)
+            .with_min_distance(5)
+            .find_peaks_with_stats();
+
+        assert_eq!(
+            ps,
+            PeakFinder::new(&y)
+                .with_min_difference(1.)
+                .with_min_plateau_size(2)
+                .with_min_prominence(2.)
+                .with_min_distance(5)
+                .find_peaks()
+        );
+        assert_eq!(
+            stats,
+            DetectionStats {
+                local_maxima: 3,
+                after_difference: 3,
+                after_mask: 3,
+                after_sides: 3,
+                after_runs: 3,
+                after_plateau_size: 2,
+                after_x_range: 2,
+                after_width_x: 2,
+                after_local_window: 2,
+                after_local_contrast: 2,
+                after_clipped: 2,
+                after_apex_height: 2,
+                after_height: 2,
+                after_prominence: 2,
+                after_prominence_ratio: 2,
+                after_distance: 1,
+                suppressed_by_distance: 1,
+                after_filter: 1,
+                after_max_peaks: 1,
+            }
+        );
+    }
+
+    #[test]
+    fn stats_finish() {
+        let y = [0., 4., 0., 3., 0., 2., 0., 1., 0.];
+        let mut fp = PeakFinder::new(&y);
+        fp.with_filter(|p, data| data[p.position.start] > 1., false)
+            .with_max_peaks(2);
+
+        let (ps, stats) = fp.find_peaks_with_stats();
+        assert_eq!(ps.len(), 2);
+        assert_eq!(
+            (
+                stats.after_distance,
+                stats.after_filter,
+                stats.after_max_peaks
+            ),
+            (4, 3, 2)
+        );
+
+        // the outer finisher counts for the derived data too
+        let (_, stats) = fp.with_circular(true).find_peaks_with_stats();
+        assert_eq!((stats.after_filter, stats.after_max_peaks), (3, 2));
+    }
+
+    #[test]
+    fn derivative() {
+        let y: Vec<u8> = vec![1, 2, 3, 3, 3, 0, 5, 5, 0];
+        let ps = PeakFinder::new(&y)
+            .with_min_prominence(0)
+            .find_peaks_derivative();
+
+        assert_eq!(
+            base(&ps),
+            vec![
+                BasePeak {
+                    position: 6..8,
+                    left_diff: 5,
+                    right_diff: 5,
+                    height: Some(5),
+                    prominence: Some(5),
+                },
+                BasePeak {
+                    position: 2..5,
+                    left_diff: 1,
+                    right_diff: 3,
+                    height: Some(3),
+                    prominence: Some(2),
+                }
+            ]
+        );
+
+        // flat steps within a descent are no peaks
+        let y = [5., 4., 4., 3., 3., 1.];
+        assert_eq!(PeakFinder::new(&y).find_peaks_derivative(), vec![]);
+    }
+
+    #[test]
+    fn cwt() {
+        // a narrow and a broad noisy peak
+        let mut state = 42u64;
+        let y: Vec<f64> = (0..200)
+            .map(|i| {
+                state = state.wrapping_mul(6364136223846793005).wrapping_add(1);
+                let noise = ((state >> 33) as f64 / (1u64 << 31) as f64 - 0.5) * 0.2;
+                let x = i as f64;
+                (-((x - 50.) / 3.).powi(2) / 2.).exp()
+                    + (-((x - 140.) / 15.).powi(2) / 2.).exp()
+                    + noise
+            })
+            .collect();
+
+        let widths: Vec<f64> = (1..=20).map(|w| w as f64).collect();
+        let ps = PeakFinder::new(&y).find_peaks_cwt(&widths);
+
+        let positions: Vec<usize> = ps.iter().map(|p| p.middle_position()).collect();
+        assert_eq!(positions.len(), 2, "{:?}", positions);
+        assert!((48..=52).contains(&positions[0]));
+        assert!((135..=145).contains(&positions[1]));
+        assert_eq!(ps[0].height, Some(y[positions[0]]));
+    }
+
+    #[test]
+    fn monotonic() {
+        let empty: Vec<f64> = vec![];
+        let inputs: Vec<Vec<f64>> = vec![
+            empty,
+            vec![1.],
+            vec![1., 2.],
+            vec![2., 1.],
+            vec![1., 2., 3., 4., 5.],
+            vec![5., 4., 3., 2., 1.],
+        ];
+
+        for y in &inputs {
+            let fp = PeakFinder::new(y);
+            assert_eq!(fp.find_peaks(), vec![]);
+            assert_eq!(
+                fp.get_local_extrema(false, &fp.difference, |_| ()).count(),
+                0
+            );
+            assert_eq!(
+                fp.get_local_extrema(true, &fp.difference, |_| ()).count(),
+                0
+            );
+        }
+    }
+
+    #[test]
+    fn config() {
+        let y = [1., 2., 3., 3., 3., 0., 5., 5., 0., 1., 0.];
+        let x: Vec<f64> = (0..y.len()).map(|i| i as f64 * 0.5).collect();
+
+        let mut fp = PeakFinder::new_with_x(&y, &x);
+        fp.with_min_prominence(0.)
+            .with_max_height(4.)
+            .with_min_distance(1.)
+            .with_edge_peaks(true);
+        let config: PeakConfig<f64, f64> = fp.config();
+
+        let mut fp2 = PeakFinder::new_with_x(&y, &x);
+        fp2.set_config(config.clone());
+
+        assert_eq!(fp2.config(), config);
+        assert_eq!(fp2.find_peaks(), fp.find_peaks());
+    }
+
+    #[test]
+    fn config_f64_settings() {
+        let y = [4., 1., 0., 1., 3., 0.5, 2., 1., 5.];
+        let mut fp = PeakFinder::new(&y);
+        fp.with_circular(true)
+            .with_symmetry()
+            .with_widths()
+            .with_min_relative_prominence(0.2)
+            .with_apex_height_range(1., 10.);
+        let config = fp.config();
+        assert!(config.circular);
+
+        let y2 = [0., 2., 1., 3., 0.];
+        let mut fp2 = PeakFinder::new(&y2);
+        fp2.set_config(config.clone());
+        assert_eq!(fp2.config(), config);
+        assert_eq!(
+            PeakFinder::with_config(&y, config).find_peaks(),
+            fp.find_peaks()
+        );
+
+        let ps = fp2.find_peaks();
+        assert!(!ps.is_empty());
+        assert!(ps.iter().all(|p| p.symmetry.is_some()));
+
+        let config = PeakFinder::new(&y2).with_dog(0.5, 1.).config();
+        assert_eq!(
+            PeakFinder::with_config(&y2, config).find_peaks(),
+            PeakFinder::new(&y2).with_dog(0.5, 1.).find_peaks()
+        );
+    }
+
+    #[test]
+    fn config_conversions_eq() {
+        let y = [0., 4., 1., 2., 0.];
+        let config = PeakFinder::new(&y).config();
+
+        assert_eq!(config, PeakFinder::new(&y).config());
+        assert_ne!(config, config.clone().with_f64_conversions());
+    }
+
+    #[cfg(feature = "ndarray")]
+    #[test]
+    fn from_array() {
+        let data = ndarray::arr1(&[0., 1., 5., 2., 0., 3., 4., 0.]);
+        let contiguous = PeakFinder::from_array(data.view()).find_peaks();
+        let slice = PeakFinder::new(data.as_slice().unwrap()).find_peaks();
+        assert_eq!(contiguous, slice);
+
+        // every other sample: [0., 5., 0., 4.]
+        let strided = data.slice(ndarray::s![..;2]);
+        let peaks = PeakFinder::from_array(strided).find_peaks();
+        assert_eq!(peaks.len(), 1);
+        assert_eq!(peaks[0].position, 1..2);
+    }
+
+    #[test]
+    fn peak_indices() {
+        let single = Peak::new(4..5, 1, 1);
+        assert_eq!(single.first_index(), 4);
+        assert_eq!(single.last_index(), 4);
+        assert!(single.contains(4));
+        assert!(!single.contains(5));
+
+        let plateau = Peak::new(2..5, 1, 1);
+        assert_eq!(plateau.first_index(), 2);
+        assert_eq!(plateau.last_index(), 4);
+        assert!(!plateau.contains(1));
+        assert!(plateau.contains(2) && plateau.contains(4));
+        assert!(!plateau.contains(5));
+    }
+
+    #[test]
+    fn merge_distance() {
+        let y = [0, 3, 1, 4, 1, 2, 0, 0, 5, 0];
+        let mut fp = PeakFinder::new(&y);
+        fp.with_min_prominence(0);
+        fp.with_merge_distance(2);
+        let ps = fp.find_peaks();
+        assert_eq!(
+            base(&ps),
+            vec![
+                BasePeak {
+                    position: 8..9,
+                    left_diff: 5,
+                    right_diff: 5,
+                    height: Some(5),
+                    prominence: Some(5),
+                },
+                BasePeak {
+                    position: 1..6,
+                    left_diff: 3,
+                    right_diff: 2,
+                    height: Some(4),
+                    prominence: Some(4),
+                },
+            ]
+        );
+
+        // the merged peaks are not suppressed by the distance bound
+        fp.with_min_distance(4);
+        assert_eq!(fp.find_peaks(), ps);
+    }
+
+    #[test]
+    fn valleys_between() {
+        let y = [2, 5, 1, 1, 3, 3, 0, 4];
+        let fp = PeakFinder::new(&y);
+        let mut ps = fp.find_peaks();
+        ps.sort_by_key(|p| p.position.start);
+        assert_eq!(fp.valleys_between(&ps), vec![2]);
+
+        assert!(fp.valleys_between(&ps[..1]).is_empty());
+        assert!(fp.valleys_between(&[]).is_empty());
+    }
+
+    #[test]
+    fn staircase_plateaus() {
+        let y = [1, 2, 2, 3];
+        assert!(PeakFinder::new(&y).find_peaks().is_empty());
+
+        let y = [3, 2, 2, 1];
+        assert!(PeakFinder::new(&y).find_peaks().is_empty());
+
+        let y = [1, 3, 3, 2];
+        let ps = PeakFinder::new(&y).find_peaks();
+        assert_eq!(ps.len(), 1);
+        assert_eq!(ps[0].position, 1..3);
+
+        // a step within a steep descent stays rejected with the difference bounds
+        let y = [0, 10, 10, 9];
+        assert!(PeakFinder::new(&y)
+            .with_max_difference(5)
+            .find_peaks()
+            .is_empty());
+
+        // no false minima on the flat steps of a rising staircase
+        let y = [1, 2, 2, 3, 3, 4];
+        assert!(PeakFinder::new(&y).find_extrema().is_empty());
+    }
+
+    #[test]
+    fn hysteresis() {
+        let y = [7, 3, 6, 4, 6, 6, 5, 1, 9, 6, 9, 1];
+        let mut fp = PeakFinder::new(&y);
+        fp.with_hysteresis(2, 5);
+        let mut ps = fp.find_peaks();
+        ps.sort_by_key(|p| p.position.start);
+        assert_eq!(
+            base(&ps),
+            vec![
+                BasePeak {
+                    position: 0..1,
+                    left_diff: 0,
+                    right_diff: 4,
+                    height: Some(7),
+                    prominence: None,
+                },
+                BasePeak {
+                    position: 8..9,
+                    left_diff: 8,
+                    right_diff: 3,
+                    height: Some(9),
+                    prominence: None,
+                },
+            ]
+        );
+
+        // a rise without a fall below the lower level is not confirmed
+        let y = [0, 6, 3, 6];
+        assert!(PeakFinder::new(&y)
+            .with_hysteresis(2, 5)
+            .find_peaks()
+            .is_empty());
+    }
+
+    #[test]
+    fn csv() {
+        let y = [0, 6, 7, 7, 5, 0];
+        let ps = PeakFinder::new(&y).with_min_height(0).find_peaks();
+        let mut out = Vec::new();
+        ps[0].write_csv_row(&mut out).unwrap();
+        assert_eq!(out, b"2,4,3,7,\n");
+
+        let mut out = Vec::new();
+        write_peaks_csv::<i32>(&[], &mut out).unwrap();
+        assert_eq!(out, b"start,end,center,height,prominence\n");
+    }
+
+    #[test]
+    fn slopes() {
+        let y = [3, 1, 2, 2, 4, 0];
+        let fp = PeakFinder::new(&y);
+
+        let minimum = Peak::new(1..2, 2, 1);
+        assert_eq!(
+            fp.slopes(&minimum),
+            (Some(Slope::Falling), Some(Slope::Rising))
+        );
+        let shoulder = Peak::new(2..4, 1, 2);
+        assert_eq!(
+            fp.slopes(&shoulder),
+            (Some(Slope::Rising), Some(Slope::Rising))
+        );
+        let edge = Peak::new(0..1, 0, 2);
+        assert_eq!(fp.slopes(&edge), (None, Some(Slope::Falling)));
+        let edge = Peak::new(5..6, 4, 0);
+        assert_eq!(fp.slopes(&edge), (Some(Slope::Falling), None));
+    }
+
+    #[test]
+    fn unsigned() {
+        let y: Vec<u8> = vec![0, 10, 250, 3, 3, 200, 0, 1, 2];
+        let mut fp = PeakFinder::new(&y);
+        fp.with_min_prominence(0);
+        let ps = fp.find_peaks();
+        assert_eq!(
+            ps.iter().map(|p| p.middle_position()).collect::<Vec<_>>(),
+            vec![2, 5]
+        );
+        assert_eq!(ps[1].prominence, Some(197));
+
+        let es = fp.find_extrema();
+        assert_eq!(es.len(), 4);
+        assert_eq!(es[1].peak.position, 3..5);
+        assert_eq!(es[3].peak.left_diff, 200);
+    }
+
+    #[test]
+    fn height_percentile() {
+        let y = [0, 9, 1, 7, 2, 3, 8, 4, 5, 6, 0];
+        let mut fp = PeakFinder::new(&y);
+        fp.with_min_height_percentile(80.);
+        let ps = fp.find_peaks();
+        assert_eq!(
+            ps.iter().map(|p| p.middle_position()).collect::<Vec<_>>(),
+            vec![1, 6, 3]
+        );
+
+        fp.with_min_height_percentile(90.);
+        assert_eq!(fp.find_peaks().len(), 2);
+        fp.with_min_height_percentile(100.);
+        assert_eq!(fp.find_peaks().len(), 1);
+    }
+
+    #[test]
+    fn auto_prominence() {
+        // noise of +-0.1 on a slope with two real peaks
+        let y: Vec<f64> = (0..40)
+            .map(|i| {
+                let noise = if i % 2 == 0 { 0.1 } else { -0.1 };
+                let peak = match i {
+                    10 => 2.,
+                    30 => 3.,
+                    _ => 0.,
+                };
+                0.01 * i as f64 + noise + peak
+            })
+            .collect();
+
+        assert!(PeakFinder::new(&y).find_peaks().len() > 2);
+
+        let mut fp = PeakFinder::new(&y);
+        fp.with_auto_prominence(3.);
+        let ps = fp.find_peaks();
+        assert_eq!(
+            ps.iter().map(|p| p.middle_position()).collect::<Vec<_>>(),
+            vec![30, 10]
+        );
+    }
+
+    #[test]
+    fn symmetry() {
+        let y = [0., 1., 2., 3., 4., 2., 0., 1., 3., 5.];
+        let mut fp = PeakFinder::new(&y);
+        fp.with_symmetry().with_edge_peaks(true);
+        let mut ps = fp.find_peaks();
+        ps.sort_by_key(|p| p.position.start);
+
+        // half prominence level 2 crossed at 2 and 5
+        assert_eq!(ps[0].symmetry, Some(2. / 1.));
+        // truncated by the right edge
+        assert_eq!(ps[1].position, 9..10);
+        assert_eq!(ps[1].symmetry, None);
+    }
+
+    #[test]
+    fn distance_fraction() {
+        let y = [0., 3., 0., 2., 0., 0., 0., 0., 0., 0., 0., 1., 0.];
+        let mut fp = PeakFinder::new(&y);
+        fp.with_min_distance_fraction(0.25);
+        let ps = fp.find_peaks();
+        assert_eq!(
+            ps.iter().map(|p| p.middle_position()).collect::<Vec<_>>(),
+            vec![1, 11]
+        );
+
+        // the same relative distance on a finer x grid
+        let x: Vec<f64> = (0..y.len()).map(|i| i as f64 * 0.1).collect();
+        let mut fp = PeakFinder::new_with_x(&y, &x);
+        fp.with_min_distance_fraction(0.25);
+        assert_eq!(fp.find_peaks(), ps);
+    }
+
+    #[test]
+    fn sample_gap() {
+        let y = [0., 3., 0., 2., 0., 1., 0., 0., 2.5, 0.];
+        // the x-values are far apart from the fifth sample on
+        let x = [0., 1., 2., 3., 4., 100., 101., 102., 103., 104.];
+        let mut fp = PeakFinder::new_with_x(&y, &x);
+        fp.with_min_sample_gap(3);
+        let middles =
+            |ps: Vec<Peak<f64>>| ps.iter().map(|p| p.middle_position()).collect::<Vec<_>>();
+        assert_eq!(middles(fp.find_peaks()), vec![1, 8, 5]);
+
+        fp.with_min_distance(10.);
+        assert_eq!(middles(fp.find_peaks()), vec![1, 8]);
+    }
+
+    #[test]
+    fn circular() {
+        // a plateau across the seam
+        let y = [5, 1, 0, 2, 1, 0, 3, 5];
+        let mut fp = PeakFinder::new(&y);
+        fp.with_circular(true).with_min_prominence(0);
+        let ps = fp.find_peaks();
+        assert_eq!(
+            base(&ps),
+            vec![
+                BasePeak {
+                    position: 7..9,
+                    left_diff: 2,
+                    right_diff: 4,
+                    height: Some(5),
+                    prominence: Some(5),
+                },
+                BasePeak {
+                    position: 3..4,
+                    left_diff: 2,
+                    right_diff: 1,
+                    height: Some(2),
+                    prominence: Some(2),
+                },
+            ]
+        );
+
+        // the distance is taken around the seam, from 8 to 0 are 2 samples
+        let y = [4, 0, 0, 0, 0, 0, 1, 0, 3, 0];
+        let mut fp = PeakFinder::new(&y);
+        fp.with_circular(true).with_min_distance(3);
+        let ps = fp.find_peaks();
+        assert_eq!(
+            ps.iter().map(|p| p.middle_position()).collect::<Vec<_>>(),
+            vec![0, 6]
+        );
+    }
+
+    #[test]
+    fn circular_finish() {
+        let y = [3., 0., 1., 4., 1., 0., 2.];
+        let mut fp = PeakFinder::new(&y);
+        fp.with_circular(true)
+            .with_labeler(|p| format!("{}", p.position.start))
+            .with_filter(|p, data| data.len() == 7 && p.position.end <= 7, false);
+
+        let ps = fp.find_peaks();
+        let labels: Vec<_> = ps.iter().map(|p| p.label.clone()).collect();
+        assert_eq!(labels, vec![Some("3".to_string()), Some("0".to_string())]);
+
+        let ps = fp.with_max_peaks(1).find_peaks();
+        assert_eq!(ps.len(), 1);
+        assert_eq!(ps[0].position, 3..4);
+    }
+
+    #[test]
+    fn cluster_peaks() {
+        let y = [0., 1., 0., 1., 0., 1., 0., 0., 0., 2., 0.];
+        let x = [0., 1., 2., 3., 4., 5., 6., 7., 8., 9., 10.];
+        let fp = PeakFinder::new_with_x(&y, &x);
+        let ps = fp.find_peaks();
+
+        // a chain of peaks each within the gap of the next is one cluster
+        let clusters = fp.cluster_peaks(&ps, 2.);
+        assert_eq!(clusters.len(), 2);
+        assert_eq!(clusters[0].len(), 3);
+        assert_eq!(clusters[1][0].position, 9..10);
+
+        assert_eq!(fp.cluster_peaks(&ps, 1.).len(), 4);
+        assert!(fp.cluster_peaks(&[], 1.).is_empty());
+    }
+
+    #[test]
+    fn envelope_peaks() {
+        // a carrier of period 4 modulated with a period of 24 samples
+        let y: Vec<f64> = (0..60)
+            .map(|i| {
+                let t = i as f64;
+                (1.5 + (2. * std::f64::consts::PI * t / 24.).sin())
+                    * (2. * std::f64::consts::PI * t / 4.).sin()
+            })
+            .collect();
+
+        assert_eq!(PeakFinder::new(&y).find_peaks().len(), 15);
+
+        let mut ps: Vec<usize> = PeakFinder::new(&y)
+            .find_envelope_peaks()
+            .iter()
+            .map(|p| p.middle_position())
+            .collect();
+        ps.sort_unstable();
+        assert_eq!(ps, vec![5, 29, 53]);
+    }
+
+    #[test]
+    fn peak_indices_only() {
+        let y = [5, 1, 0, 2, 1, 0, 3, 6, 0, 4, 0];
+        let mut fp = PeakFinder::new(&y);
+        fp.with_min_height(3);
+        assert_eq!(fp.find_peak_indices(), vec![7, 9]);
+
+        fp.with_circular(true);
+        assert_eq!(fp.find_peak_indices(), vec![7, 0, 9]);
+    }
+
+    #[test]
+    fn custom_zero() {
+        // a missing first sample makes the derived zero NaN, which rejects every difference
+        let y = [f64::NAN, 1., 3., 1., 0., 2., 0.];
+        assert!(PeakFinder::new(&y).find_peaks().is_empty());
+
+        let mut fp = PeakFinder::new(&y);
+        fp.with_zero(0.);
+        assert_eq!(fp.find_peak_indices(), vec![2, 5]);
+
+        // a bound set before is kept
+        let y = [0., 1., 3., 1., 0., 1., 0.];
+        let mut fp = PeakFinder::new(&y);
+        fp.with_min_difference(1.5).with_zero(0.);
+        assert_eq!(fp.find_peak_indices(), vec![2]);
+    }
+
+    #[test]
+    fn compute_all() {
+        let y = [1., 2., 3., 0., 5., 0.];
+        let mut fp = PeakFinder::new(&y);
+        fp.with_compute_all(true);
+        let ps = fp.find_peaks();
+        assert_eq!(ps.len(), 2);
+        assert!(ps
+            .iter()
+            .all(|p| p.height.is_some() && p.prominence.is_some()));
+        assert_eq!(ps[1].prominence, Some(2.));
+
+        fp.with_compute_all(false);
+        assert!(fp.find_peaks().iter().all(|p| p.prominence.is_none()));
+    }
+
+    #[test]
+    fn labeler() {
+        let y = [1., 2., 3., 0., 5., 0.];
+        let mut fp = PeakFinder::new(&y);
+        assert!(fp.find_peaks().iter().all(|p| p.label.is_none()));
+
+        let source = String::from("a");
+        fp.with_labeler(move |p| format!("{}{}", source, p.position.start));
+        let labels: Vec<_> = fp.find_peaks().into_iter().map(|p| p.label).collect();
+        assert_eq!(labels, vec![Some("a4".to_string()), Some("a2".to_string())]);
+    }
+
+    #[test]
+    fn distance_selection() {
+        use super::select_by_distance;
+
+        let mut state: u64 = 7;
+        let mut next = move |m: u64| {
+            state = state.wrapping_mul(6364136223846793005).wrapping_add(1);
+            ((state >> 33) % m) as usize
+        };
+
+        for _ in 0..50 {
+            let mut middles: Vec<usize> = (0..200).filter(|_| next(3) == 0).collect();
+            // any height order
+            for i in (1..middles.len()).rev() {
+                middles.swap(i, next(i as u64 + 1));
+            }
+            let (lower, upper) = (next(10), 100 + next(100));
+
+            let apart = |i: usize, j: usize| i.abs_diff(j) >= lower;
+            let near = |i: usize, j: usize| i.abs_diff(j) <= upper;
+
+            let mut kept: Vec<usize> = Vec::new();
+            let expected: Vec<bool> = middles
+                .iter()
+                .map(|&i| {
+                    let inside = kept.iter().all(|&k| apart(k, i) && near(k, i));
+                    if inside {
+                        kept.push(i);
+                    }
+                    inside
+                })
+                .collect();
+
+            assert_eq!(select_by_distance(&middles, apart, near), expected);
+        }
+    }
+
+    #[test]
+    fn nearest() {
+        let y = [0., 2., 0., 0., 0., 3., 0., 0., 1., 0.];
+        let x = [0., 1., 2., 3., 4., 5., 6., 7., 8., 90.];
+        let fp = PeakFinder::new_with_x(&y, &x);
+        let ps = fp.find_peaks();
+
+        assert_eq!(nearest_peak(&ps, 0).unwrap().middle_position(), 1);
+        assert_eq!(nearest_peak(&ps, 3).unwrap().middle_position(), 5);
+        assert_eq!(nearest_peak(&ps, 9).unwrap().middle_position(), 8);
+        assert!(nearest_peak::<f64>(&[], 3).is_none());
+
+        assert_eq!(fp.nearest_peak_x(&ps, 6.4).unwrap().middle_position(), 5);
+        assert_eq!(fp.nearest_peak_x(&ps, 6.5).unwrap().middle_position(), 5);
+        assert_eq!(fp.nearest_peak_x(&ps, 50.).unwrap().middle_position(), 8);
+    }
+
+    #[test]
+    fn centroid() {
+        let y = [0., 1., 3., 1., 0., 2., 2., 0.];
+        let x = [0., 1., 2., 3., 4., 5., 6., 10.];
+
+        let merged = Peak::new(1..4, 1., 1.);
+        assert_eq!(merged.centroid(&y), 2.);
+        let skewed = Peak::new(2..4, 1., 1.);
+        assert_eq!(skewed.centroid(&y), (2. * 3. + 3.) / 4.);
+
+        // a plateau falls back to its geometric center
+        let plateau = Peak::new(5..7, 2., 2.);
+        assert_eq!(plateau.centroid(&y), 5.5);
+        assert_eq!(plateau.centroid_x(&y, &x), 5.5);
+
+        let weighted = Peak::new(6..8, 2., 2.);
+        assert_eq!(weighted.centroid_x(&y, &x), (2. * 6. + 0. * 10.) / 2.);
+    }
+
+    #[test]
+    fn validate() {
+        let y = [1., 2., 3., 0., 5., 0.];
+        let mut fp = PeakFinder::new(&y);
+        assert_eq!(fp.validate(), Ok(()));
+
+        fp.with_min_height(2.).with_max_height(2.);
+        assert_eq!(fp.try_find_peaks().unwrap().len(), 0);
+
+        fp.with_min_distance(3).with_max_distance(1);
+        let err = fp.try_find_peaks().unwrap_err();
+        assert_eq!(err, PeakError::InvertedLimits("distance"));
+        assert_eq!(
+            err.to_string(),
+            "The minimum distance is greater than the maximum distance!"
+        );
+    }
+
+    #[test]
+    fn local_maxima() {
+        let y = [3., 2., 3., 0., 5., 5., 1., 2.];
+        let mut fp = PeakFinder::new(&y);
+        fp.with_min_height(4.).with_min_difference(2.);
+        let positions: Vec<_> = fp.local_maxima().into_iter().map(|p| p.position).collect();
+        assert_eq!(positions, vec![2..3, 4..6]);
+
+        fp.with_edge_peaks(true);
+        assert_eq!(fp.local_maxima().len(), 4);
+        assert_eq!(fp.find_peaks_with_stats().1.local_maxima, 4);
+    }
+
+    #[test]
+    fn prominence_reference() {
+        let y = [2, 4, 3, 6, 1, 5, 2];
+        let mut fp = PeakFinder::new(&y);
+        fp.with_compute_all(true);
+        let proms = |fp: &PeakFinder<i32, usize>| {
+            let mut ps = fp.find_peaks();
+            ps.sort_by_key(|p| p.position.start);
+            ps.into_iter()
+                .map(|p| p.prominence.unwrap())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(proms(&fp), vec![1, 4, 3]);
+
+        fp.with_prominence_reference(ProminenceRef::GlobalMin);
+        assert_eq!(proms(&fp), vec![3, 5, 4]);
+
+        fp.with_prominence_reference(ProminenceRef::Fixed(4));
+        assert_eq!(proms(&fp), vec![0, 2, 1]);
+    }
+
+    #[test]
+    fn edge_plateaus() {
+        for (y, position, left_diff, right_diff) in
+            [([1, 2, 3, 3], 2..4, 1, 0), ([3, 3, 2, 1], 0..2, 0, 1)]
+        {
+            assert_eq!(PeakFinder::new(&y).find_peaks(), vec![]);
+            assert_eq!(
+                base(&PeakFinder::new(&y).with_edge_peaks(true).find_peaks()),
+                vec![BasePeak {
+                    position,
+                    left_diff,
+                    right_diff,
+                    height: Some(3),
+                    prominence: None,
+                }]
+            );
+        }
+    }
+
+    #[test]
+    fn refine_decimated() {
+        let y = [0, 1, 2, 5, 5, 3, 1, 0, 2, 6, 1, 0];
+        let decimated: Vec<i32> = y.iter().step_by(3).copied().collect(); // [0, 5, 1, 6]
+
+        let mut ps = PeakFinder::new(&decimated)
+            .with_edge_peaks(true)
+            .find_peaks();
+        ps.sort_by_key(|p| p.position.start);
+        assert_eq!(ps.len(), 2);
+
+        let refined = refine_positions(&y, &ps, 3, 2);
+        assert_eq!(
+            base(&refined),
+            vec![
+                BasePeak {
+                    position: 3..5,
+                    left_diff: 3,
+                    right_diff: 2,
+                    height: Some(5),
+                    prominence: None,
+                },
+                BasePeak {
+                    position: 9..10,
+                    left_diff: 4,
+                    right_diff: 5,
+                    height: Some(6),
+                    prominence: None,
+                }
+            ]
+        );
+    }
+
+    #[test]
+    fn x_range() {
+        let x = [0, 10, 20, 30, 40, 50, 60, 70];
+        let y = [0, 4, 1, 3, 2, 5, 1, 0];
+        let mut fp = PeakFinder::new_with_x(&y, &x);
+        fp.with_min_prominence(0);
+
+        let mut ps = fp.with_x_range(10, 30).find_peaks();
+        ps.sort_by_key(|p| p.position.start);
+        assert_eq!(
+            ps.iter()
+                .map(|p| (p.middle_position(), p.prominence))
+                .collect::<Vec<_>>(),
+            vec![(1, Some(3)), (3, Some(1))]
+        );
+
+        // the valleys outside the range still set the prominence
+        let ps = fp.with_x_range(25, 35).find_peaks();
+        assert_eq!(ps.len(), 1);
+        assert_eq!(ps[0].prominence, Some(1));
+    }
+
+    #[test]
+    fn width_at_height() {
+        let x = [0, 2, 4, 6, 8, 10];
+        let y = [1, 3, 5, 5, 3, 4];
+
+        let ps = PeakFinder::new_with_x(&y, &x).find_peaks();
+        assert_eq!(ps[0].position, 2..4);
+
+        assert_eq!(ps[0].width_at_height(&y, &x, 5), Some((4., 6.)));
+        assert_eq!(ps[0].width_at_height(&y, &x, 4), Some((3., 7.)));
+        // no descent to 1 on the right
+        assert_eq!(ps[0].width_at_height(&y, &x, 1), Some((0., 10.)));
+        assert_eq!(ps[0].width_at_height(&y, &x, 6), None);
+    }
+
+    #[test]
+    fn duration_x() {
+        use std::time::Duration;
+
+        let x: Vec<Duration> = (0..9).map(|i| Duration::from_secs(2 * i)).collect();
+        let y = [0., 3., 0., 2., 0., 0., 4., 1., 0.];
+
+        let ps = PeakFinder::new_with_x(&y, &x)
+            .with_min_distance(Duration::from_secs(5))
+            .with_min_width_x(Duration::ZERO)
+            .with_merge_distance(Duration::from_secs(1))
+            .find_peaks();
+
+        assert_eq!(
+            ps.iter().map(|p| p.middle_position()).collect::<Vec<_>>(),
+            vec![6, 1]
+        );
+    }
+
+    #[test]
+    fn sort_order() {
+        let y = [0., 2., 0., 5., 0., 1., 3., 0., 4., 0.];
+        let mut fp = PeakFinder::new(&y);
+        fp.with_min_prominence(0.);
+        let middles = |fp: &PeakFinder<f64, usize>| {
+            fp.find_peaks()
+                .iter()
+                .map(|p| p.middle_position())
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(middles(&fp), vec![3, 8, 6, 1]);
+
+        fp.with_sort_order(SortOrder::Position);
+        assert_eq!(middles(&fp), vec![1, 3, 6, 8]);
+        assert_eq!(
+            fp.find_peaks_persistence()
+                .iter()
+                .map(|p| p.middle_position())
+                .collect::<Vec<_>>(),
+            vec![1, 3, 6, 8]
+        );
+    }
+
+    #[test]
+    fn prominence_as_scipy() {
+        // scipy's `_peak_prominences` loop
+        fn reference(x: &[i32], peak: usize) -> i32 {
+            let mut left_min = x[peak];
+            let mut i = peak;
+            while x[i] <= x[peak] {
+                left_min = left_min.min(x[i]);
+                if i == 0 {
+                    break;
+                }
+                i -= 1;
+            }
+            let mut right_min = x[peak];
+            let mut i = peak;
+            while i < x.len() && x[i] <= x[peak] {
+                right_min = right_min.min(x[i]);
+                i += 1;
+            }
+            x[peak] - left_min.max(right_min)
+        }
+
+        // a slightly higher sample just beyond a deeper valley ends the search at 6
+        let y = [0, 6, 2, 5, 1, 5, 0];
+        let mut ps = PeakFinder::new(&y).with_min_prominence(0).find_peaks();
+        ps.sort_by_key(|p| p.position.start);
+        assert_eq!(
+            ps.iter().map(|p| p.prominence.unwrap()).collect::<Vec<_>>(),
+            vec![6, 3, 4]
+        );
+
+        let mut state: u64 = 11;
+        let mut next = move || {
+            state = state.wrapping_mul(6364136223846793005).wrapping_add(1);
+            ((state >> 33) % 6) as i32
+        };
+        for _ in 0..50 {
+            let y: Vec<i32> = (0..100).map(|_| next()).collect();
+            for p in PeakFinder::new(&y).with_min_prominence(0).find_peaks() {
+                assert_eq!(p.prominence, Some(reference(&y, p.middle_position())));
+            }
+        }
+    }
+
+    #[test]
+    fn plateau_max_difference() {
+        let y = [1., 2., 3., 3., 3., 0., 5., 5., 0.];
+        let mut fp = PeakFinder::new(&y);
+
+        // both sides of the plateau 2..5 (1. and 3.) are within the bounds
+        let ps = fp.with_max_difference(3.).find_peaks();
+        assert_eq!(
+            base(&ps),
+            vec![BasePeak {
+                position: 2..5,
+                left_diff: 1.,
+                right_diff: 3.,
+                height: Some(3.),
+                prominence: None,
+            }]
+        );
+
+        assert_eq!(fp.with_max_difference(5.).find_peaks().len(), 2);
+        assert_eq!(fp.with_max_difference(2.).find_peaks(), vec![]);
+    }
+
+    #[test]
+    fn from_iterator() {
+        use std::iter::FromIterator;
+
+        let y = [1., 2., 3., 0., 5., 0.];
+        let mut fp = PeakFinder::from_iter(y.iter().copied());
+        fp.with_min_prominence(1.);
+
+        assert_eq!(
+            fp.find_peaks(),
+            PeakFinder::new(&y).with_min_prominence(1.).find_peaks()
+        );
+    }
+
+    #[test]
+    fn shortcuts() {
+        let y = [0, 4, 1, 3, 2, 5, 1, 0];
+
+        let ps = find_peaks_by_prominence(&y, 2);
+        assert_eq!(
+            ps.iter()
+                .map(|p| (p.middle_position(), p.prominence))
+                .collect::<Vec<_>>(),
+            vec![(5, Some(5)), (1, Some(3))]
+        );
+
+        let ps = find_peaks_by_height(&y, 4);
+        assert_eq!(
+            ps.iter()
+                .map(|p| (p.middle_position(), p.height))
+                .collect::<Vec<_>>(),
+            vec![(5, Some(5)), (1, Some(4))]
+        );
+    }
+
+    #[test]
+    fn shoulders() {
+        // a shoulder on each flank of the peak at 5 and a flat stair on the right one
+        let y = [0, 4, 6, 7, 9, 12, 9, 8, 7, 4, 4, 1, 0];
+        let ss = PeakFinder::new(&y).find_shoulders();
+
+        assert_eq!(
+            base(&ss),
+            vec![
+                BasePeak {
+                    position: 3..4,
+                    left_diff: 1,
+                    right_diff: 2,
+                    height: Some(7),
+                    prominence: None,
+                },
+                BasePeak {
+                    position: 6..7,
+                    left_diff: 3,
+                    right_diff: 1,
+                    height: Some(9),
+                    prominence: None,
+                },
+                BasePeak {
+                    position: 9..11,
+                    left_diff: 3,
+                    right_diff: 3,
+                    height: Some(4),
+                    prominence: None,
+                }
+            ]
+        );
+
+        assert!(ss.iter().all(|p| p.is_shoulder));
+        assert_eq!(
+            PeakFinder::new(&y)
+                .with_min_height(5)
+                .find_shoulders()
+                .len(),
+            2
+        );
+        assert!(PeakFinder::new(&y)
+            .find_peaks()
+            .iter()
+            .all(|p| !p.is_shoulder));
+    }
+
+    #[test]
+    fn plateau_center() {
+        let y = [0, 2, 2, 2, 0, 1, 0, 3, 3, 0];
+        let mut ps = PeakFinder::new(&y).find_peaks();
+        ps.sort_by_key(|p| p.position.start);
+
+        let centers = |mode| ps.iter().map(|p| p.center(mode)).collect::<Vec<_>>();
+        assert_eq!(centers(PlateauCenter::Floor), vec![2, 5, 7]);
+        assert_eq!(centers(PlateauCenter::Ceil), vec![2, 5, 8]);
+        assert_eq!(centers(PlateauCenter::Left), vec![1, 5, 7]);
+        assert_eq!(centers(PlateauCenter::Right), vec![3, 5, 8]);
+        assert_eq!(
+            ps.iter().map(|p| p.middle_position()).collect::<Vec<_>>(),
+            centers(PlateauCenter::Ceil)
+        );
+    }
+
+    #[test]
+    fn classify() {
+        let y = [
+            0., 0., 10., 0., 1., 2., 4., 6., 7., 7.2, 7., 6., 4., 2., 1., 0., 8., 8.1, 0.,
+        ];
+        let mut fp = PeakFinder::new(&y);
+        fp.with_min_prominence(0.);
+        let shapes = |fp: &PeakFinder<f64, usize>| {
+            let mut ps = fp.find_peaks();
+            ps.sort_by_key(|p| p.position.start);
+            ps.iter().map(|p| fp.classify(p)).collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            shapes(&fp),
+            vec![PeakShape::Sharp, PeakShape::Broad, PeakShape::Sharp]
+        );
+
+        fp.with_classification_thresholds(ClassificationThresholds {
+            noise_prominence_ratio: 0.1,
+            max_sharp_width: 8.,
+        });
+        assert_eq!(
+            shapes(&fp),
+            vec![PeakShape::Sharp, PeakShape::Sharp, PeakShape::Sharp]
+        );
+
+        let y = [0., 6., 10., 8., 7., 6., 3., 0.];
+        let fp = PeakFinder::new(&y);
+        assert_eq!(fp.classify(&fp.find_shoulders()[0]), PeakShape::Shoulder);
+
+        // a prominence of 0.2 at a height of 8.
+        let y = [0., 8., 7.8, 8.1, 0.];
+        let fp = PeakFinder::new(&y);
+        let p = fp
+            .find_peaks()
+            .into_iter()
+            .find(|p| p.position == (1..2))
+            .unwrap();
+        assert_eq!(fp.classify(&p), PeakShape::Noise);
+    }
+
+    #[test]
+    fn local_window() {
+        let y = [0, 4, 1, 3, 1, 2, 2, 0, 6];
+        let mut fp = PeakFinder::new(&y);
+        let middles = |fp: &PeakFinder<i32, usize>| {
+            let mut m: Vec<usize> = fp
+                .find_peaks()
+                .iter()
+                .map(|p| p.middle_position())
+                .collect();
+            m.sort_unstable();
+            m
+        };
+
+        assert_eq!(middles(&fp), vec![1, 3, 6]);
+        assert_eq!(middles(fp.with_local_window(1)), vec![1, 3, 6]);
+        // the window of the plateau 5..7 reaches the 6 at the edge
+        assert_eq!(middles(fp.with_local_window(2)), vec![1]);
+    }
+
+    #[test]
+    fn from_deque() {
+        use std::collections::VecDeque;
+
+        let y = [0., 2., 0., 5., 1., 3., 0.];
+        // wrapped around the end of its buffer
+        let mut ring: VecDeque<f64> = VecDeque::with_capacity(y.len());
+        ring.extend(&y[..3]);
+        for _ in 0..3 {
+            ring.pop_front();
+        }
+        ring.extend(&y[..]);
+
+        let ps = PeakFinder::from_deque(&mut ring)
+            .with_min_prominence(0.)
+            .find_peaks();
+        assert_eq!(ps, PeakFinder::new(&y).with_min_prominence(0.).find_peaks());
+    }
+
+    #[test]
+    fn suppressed_peaks() {
+        let y = [0, 5, 0, 4, 0, 3, 0, 0, 0, 2, 0, 4, 0];
+        let mut fp = PeakFinder::new(&y);
+        fp.with_min_distance(3);
+        let middles = |ps: &[Peak<i32>]| ps.iter().map(|p| p.middle_position()).collect::<Vec<_>>();
+
+        let (ps, suppressed) = fp.find_peaks_with_suppressed();
+        assert_eq!(ps, fp.find_peaks());
+        assert_eq!(middles(&ps), vec![1, 11, 5]);
+        assert_eq!(middles(&suppressed), vec![3, 9]);
+        assert_eq!(suppressed[0].height, Some(4));
+
+        let (_, suppressed) = fp.with_circular(true).find_peaks_with_suppressed();
+        assert_eq!(middles(&suppressed), vec![3, 9]);
+
+        let (ps, suppressed) = PeakFinder::new(&y).find_peaks_with_suppressed();
+        assert_eq!(ps.len(), 5);
+        assert_eq!(suppressed, vec![]);
+    }
+
+    #[test]
+    fn troughs() {
+        let y: [u8; 9] = [9, 3, 7, 1, 8, 6, 7, 2, 9];
+        let mut fp = PeakFinder::new(&y);
+        fp.with_min_prominence(0);
+        let troughs = |fp: &PeakFinder<u8, usize>| {
+            let mut ts = fp.find_troughs();
+            ts.sort_by_key(|t| t.position.start);
+            ts.iter()
+                .map(|t| {
+                    (
+                        t.middle_position(),
+                        t.height.unwrap(),
+                        t.prominence.unwrap(),
+                    )
+                })
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            troughs(&fp),
+            vec![(1, 3, 4), (3, 1, 8), (5, 6, 1), (7, 2, 6)]
+        );
+
+        fp.with_max_height(5);
+        assert_eq!(troughs(&fp), vec![(1, 3, 4), (3, 1, 8), (7, 2, 6)]);
+        fp.with_min_height(2);
+        assert_eq!(troughs(&fp), vec![(1, 3, 4), (7, 2, 6)]);
+        fp.with_min_height(10);
+        assert_eq!(troughs(&fp), vec![]);
+    }
+
+    #[cfg(feature = "simd")]
+    #[test]
+    fn simd_scan() {
+        let mut state: u64 = 3;
+        let mut next = move |m: u64| {
+            state = state.wrapping_mul(6364136223846793005).wrapping_add(1);
+            (state >> 33) % m
+        };
+
+        for _ in 0..200 {
+            // monotonic runs of random lengths, with repeated, infinite and NaN samples
+            let mut y: Vec<f64> = Vec::new();
+            let mut v = 0.;
+            while y.len() < 300 {
+                let (len, up) = (next(40), next(2) == 0);
+                for _ in 0..len {
+                    v += match next(20) {
+                        0 => 0.,
+                        _ if up => 1. + next(5) as f64,
+                        _ => -1. - next(5) as f64,
+                    };
+                    y.push(match next(200) {
+                        0 => f64::NAN,
+                        1 => f64::INFINITY,
+                        _ => v,
+                    });
+                }
+            }
+            let y32: Vec<f32> = y.iter().map(|&v| v as f32).collect();
+
+            let mut fp = PeakFinder::new(&y);
+            let mut fp32 = PeakFinder::new(&y32);
+            assert_eq!(fp.clone().with_simd().find_extrema(), fp.find_extrema());
+            assert_eq!(fp32.clone().with_simd().find_extrema(), fp32.find_extrema());
+
+            fp.with_min_prominence(1.).with_min_difference(2.);
+            fp32.with_zero(1.);
+            assert_eq!(fp.clone().with_simd().find_peaks(), fp.find_peaks());
+            assert_eq!(fp32.clone().with_simd().find_peaks(), fp32.find_peaks());
+        }
+    }
+
+    #[test]
+    fn widths() {
+        let y = [0., 1., 2., 3., 4., 2., 0., 1., 3., 5.];
+        let x: Vec<f64> = (0..y.len()).map(|i| i as f64 / 2.).collect();
+        let mut fp = PeakFinder::new_with_x(&y, &x);
+        fp.with_widths()
+            .with_edge_peaks(true)
+            .with_sort_order(SortOrder::Position);
+        let ps = fp.find_peaks();
+
+        // half prominence level 2 crossed at 2 and 5, in x 1. and 2.5
+        assert_eq!(ps[0].position, 4..5);
+        assert_eq!((ps[0].left_width, ps[0].right_width), (Some(1.), Some(0.5)));
+        assert_eq!(ps[0].symmetry, None);
+        // level 2.5 crossed at 7.75, the right side is clamped to the edge
+        assert_eq!(ps[1].position, 9..10);
+        assert_eq!(
+            (ps[1].left_width, ps[1].right_width),
+            (Some(0.625), Some(0.))
+        );
+
+        // in samples with the default x-values, from the middle of the plateau
+        let y = [0., 2., 2., 0.];
+        let ps = PeakFinder::new(&y).with_widths().find_peaks();
+        assert_eq!((ps[0].left_width, ps[0].right_width), (Some(1.), Some(1.)));
+    }
+
+    #[test]
+    fn height_reference() {
+        let y: [u8; 7] = [100, 103, 100, 101, 100, 105, 100];
+        let heights = |ps: Vec<Peak<u8>>| ps.iter().map(|p| p.height).collect::<Vec<_>>();
+
+        let mut fp = PeakFinder::new(&y);
+        fp.with_height_reference(HeightRef::AboveGlobalMin);
+        assert_eq!(heights(fp.find_peaks()), vec![Some(5), Some(3), Some(1)]);
+        fp.with_min_height(2);
+        assert_eq!(heights(fp.find_peaks()), vec![Some(5), Some(3)]);
+        fp.with_height_reference(HeightRef::Absolute);
+        assert_eq!(
+            heights(fp.find_peaks()),
+            vec![Some(105), Some(103), Some(101)]
+        );
+
+        // the mean is 709 / 7, the peak at 101 is below it
+        let y = y.map(f64::from);
+        let ps = PeakFinder::new(&y)
+            .with_height_reference(HeightRef::AboveMean)
+            .find_peaks();
+        assert_eq!(ps.len(), 2);
+        assert_eq!(ps[0].height, Some(105. - 709. / 7.));
+    }
+
+    #[test]
+    fn distance_priority() {
+        // a tall peak on a drifting baseline and a lower but more prominent one
+        let y = [9., 9.5, 9., 0., 4., 0., 3., 0.];
+        let mut fp = PeakFinder::new(&y);
+        fp.with_min_distance(4);
+        let middles =
+            |ps: Vec<Peak<f64>>| ps.iter().map(|p| p.middle_position()).collect::<Vec<_>>();
+
+        assert_eq!(middles(fp.find_peaks()), vec![1, 6]);
+        fp.with_distance_priority(DistancePriority::Prominence);
+        let (ps, suppressed) = fp.find_peaks_with_suppressed();
+        assert_eq!(ps[0].prominence, Some(4.));
+        assert_eq!(middles(ps), vec![4]);
+        // still sorted by height
+        assert_eq!(middles(suppressed), vec![1, 6]);
+    }
+
+    #[test]
+    fn multiscale() {
+        let y = [0., 3., 0., 0., 6., 5.5, 6.5, 0.];
+        let fine = PeakFinder::new(&y).with_max_height(6.).config();
+        let coarse = PeakFinder::new(&y).with_min_prominence(1.).config();
+        let fp = PeakFinder::new(&y);
+        let middles =
+            |ps: Vec<Peak<f64>>| ps.iter().map(|p| p.middle_position()).collect::<Vec<_>>();
+
+        // the peak at 4 (prominence 0.5) is a duplicate of the one at 6 (prominence 6.5)
+        let ps = fp.find_peaks_multiscale(&[fine.clone(), coarse.clone()], 2);
+        assert!(ps.iter().all(|p| p.prominence.is_some()));
+        assert_eq!(middles(ps), vec![6, 1]);
+        let ps = fp.find_peaks_multiscale(&[fine, coarse], 1);
+        assert_eq!(middles(ps), vec![6, 4, 1]);
+
+        assert!(fp.find_peaks_multiscale(&[], 1).is_empty());
+    }
+
+    #[test]
+    fn clipped() {
+        let y: [u16; 9] = [0, 4095, 4095, 0, 700, 0, 4095, 4095, 0];
+        let mut fp = PeakFinder::new(&y);
+        fp.with_saturation_level(4095)
+            .with_sort_order(SortOrder::Position);
+        let ps = fp.find_peaks();
+        assert_eq!(
+            ps.iter().map(|p| p.clipped).collect::<Vec<_>>(),
+            vec![true, false, true]
+        );
+
+        fp.with_drop_clipped(true);
+        let (ps, stats) = fp.find_peaks_with_stats();
+        assert_eq!(ps.len(), 1);
+        assert_eq!(ps[0].position, 4..5);
+        assert_eq!((stats.after_width_x, stats.after_height), (3, 1));
+
+        // a merged peak is clipped if any of its parts is
+        fp.with_drop_clipped(false).with_merge_distance(4);
+        let ps = fp.find_peaks();
+        assert_eq!(ps.len(), 1);
+        assert!(ps[0].clipped);
+    }
+
+    #[test]
+    fn from_fn() {
+        let x: Vec<f64> = (0..400)
+            .map(|i| i as f64 * std::f64::consts::PI / 100.)
+            .collect();
+        let mut fp = PeakFinder::from_fn(&x, |x| x.sin());
+        fp.with_sort_order(SortOrder::Position);
+
+        // the maxima of the sine at pi / 2 and 5 pi / 2
+        let ps = fp.find_peaks();
+        assert_eq!(
+            ps.iter().map(|p| p.middle_position()).collect::<Vec<_>>(),
+            vec![50, 250]
+        );
+    }
+
+    #[test]
+    fn constant_data() {
+        for n in [2, 3, 5] {
+            let y = vec![5; n];
+            for edge_peaks in [false, true] {
+                let mut fp = PeakFinder::new(&y);
+                fp.with_edge_peaks(edge_peaks).with_compute_all(true);
+                assert_eq!(fp.find_peaks(), vec![]);
+                assert_eq!(fp.find_peaks_derivative(), vec![]);
+                assert_eq!(fp.find_peaks_persistence(), vec![]);
+                assert_eq!(fp.find_troughs(), vec![]);
+                assert_eq!(fp.local_maxima(), vec![]);
+            }
+        }
+    }
+
+    #[test]
+    fn prominence_of() {
+        let y = [0., 3., 1., 2., 1.5, 5., 0.];
+        let mut fp = PeakFinder::new(&y);
+        fp.with_compute_all(true);
+
+        for p in fp.find_peaks() {
+            assert_eq!(Some(fp.prominence_of(p.position)), p.prominence);
+        }
+        // a range that is no peak of the data
+        assert_eq!(fp.prominence_of(3..5), 1.);
+        fp.with_prominence_window(3);
+        assert_eq!(fp.prominence_of(1..2), 2.);
+        fp.with_prominence_reference(ProminenceRef::Fixed(1.));
+        assert_eq!(fp.prominence_of(1..2), 2.);
+    }
+
+    #[test]
+    fn peaks_x() {
+        let y = [0., 1., 3., 3., 0., 2., 0.];
+        let x = [10, 20, 30, 40, 50, 60, 70];
+        let ps = PeakFinder::new_with_x(&y, &x).find_peaks_x();
+        assert_eq!(ps.iter().map(|(x, _)| *x).collect::<Vec<_>>(), vec![40, 60]);
+        assert_eq!(ps[0].1.position, 2..4);
+
+        // the plateau across the seam ends after the last index
+        let y = [3., 0., 1., 0., 3.];
+        let ps = PeakFinder::new(&y).with_circular(true).find_peaks_x();
+        assert_eq!(ps[0].1.position, 4..6);
+        assert_eq!(ps[0].0, 0);
+    }
+
+    #[test]
+    fn min_peaks() {
+        let y = [0., 3., 0., 2., 0., 1., 0.];
+        let mut fp = PeakFinder::new(&y);
+        fp.with_min_peaks(3);
+        assert_eq!(fp.try_find_peaks().unwrap().len(), 3);
+
+        // checked after the distance bounds
+        fp.with_min_distance(3);
+        let err = fp.try_find_peaks().unwrap_err();
+        assert_eq!(
+            err,
+            PeakError::TooFewPeaks {
+                found: 2,
+                expected: 3
+            }
+        );
+        assert_eq!(
+            err.to_string(),
+            "Found 2 peaks, but at least 3 are required!"
+        );
+        assert_eq!(fp.find_peaks().len(), 2);
+    }
+
+    #[test]
+    fn prominence_contour() {
+        let y = [5, 1, 3, 2, 6, 2, 4, 0, 4];
+        let mut fp = PeakFinder::new(&y);
+        fp.with_sort_order(SortOrder::Position);
+        let ps = fp.find_peaks();
+
+        // the right valley at 2 is the higher one, the left line reaches down to 1
+        assert_eq!(ps[0].position, 2..3);
+        assert_eq!(fp.prominence_contour(&ps[0]), (1, 3, 2));
+        // the left valley at 1 is the higher one, the right line reaches to the minimum at 7
+        assert_eq!(fp.prominence_contour(&ps[1]), (1, 7, 1));
+        // the prominence is computed if missing
+        fp.with_min_prominence(0);
+        assert_eq!(fp.find_peaks()[1].prominence, Some(5));
+    }
+
+    #[test]
+    fn user_filter() {
+        let y = [0., 5., 0., 4., 0., 0., 0., 3., 0.];
+        let middles =
+            |ps: Vec<Peak<f64>>| ps.iter().map(|p| p.middle_position()).collect::<Vec<_>>();
+        let below_5 = |p: &Peak<f64>, y: &[f64]| y[p.position.start] < 5.;
+
+        let mut fp = PeakFinder::new(&y);
+        fp.with_min_distance(3);
+        assert_eq!(middles(fp.find_peaks()), vec![1, 7]);
+        // the peak at 3 is suppressed by the one at 1 before that is filtered out
+        fp.with_filter(below_5, false);
+        assert_eq!(middles(fp.find_peaks()), vec![7]);
+        fp.with_filter(below_5, true);
+        assert_eq!(middles(fp.find_peaks()), vec![3, 7]);
+
+        // the heights are relative to the height reference at both stages
+        let y = y.map(|y| y + 10.);
+        let mut fp = PeakFinder::new(&y);
+        fp.with_min_distance(3)
+            .with_compute_all(true)
+            .with_height_reference(HeightRef::AboveGlobalMin);
+        for (before_distance, n) in [(true, 2), (false, 1)] {
+            fp.with_filter(|p, _| p.height < Some(5.), before_distance);
+            assert_eq!(fp.find_peaks().len(), n);
+        }
+    }
+
+    #[test]
+    fn non_monotonic_x() {
+        let y = [0., 2., 0., 1., 0.];
+        let x = [0., 1., 2., 1.5, 1.];
+        let mut fp = PeakFinder::new_with_x(&y, &x);
+        // the x-values are not used for distances
+        assert_eq!(fp.validate(), Ok(()));
+
+        fp.with_min_distance(1.);
+        let err = fp.try_find_peaks().unwrap_err();
+        assert_eq!(err, PeakError::NonMonotonicX { index: 3 });
+        assert_eq!(
+            err.to_string(),
+            "The x-values are not monotonic at index 3!"
+        );
+
+        // a NaN compares false both ways, so it is found before the order is checked
+        let x_nan = [0., 1., f64::NAN, 3., 4.];
+        let mut fp = PeakFinder::new_with_x(&y, &x_nan);
+        assert_eq!(fp.validate(), Ok(()));
+        fp.with_min_distance(1.);
+        let err = fp.try_find_peaks().unwrap_err();
+        assert_eq!(err, PeakError::UnorderedX { index: 2 });
+        assert_eq!(
+            err.to_string(),
+            "The x-value at index 2 is not comparable (NaN)!"
+        );
+        fp.with_distance_metric(|a: &f64, b: &f64| (a - b).abs());
+        assert_eq!(fp.validate(), Err(PeakError::UnorderedX { index: 2 }));
+
+        let x = [4., 3., 3., 1., 0.];
+        assert_eq!(
+            PeakFinder::new_with_x(&y, &x)
+                .with_merge_distance(1.)
+                .validate(),
+            Ok(())
+        );
+    }
+
+    #[cfg(feature = "rayon")]
+    #[test]
+    fn batch() {
+        let signals: Vec<Vec<f64>> = (0..50)
+            .map(|k| {
+                (0..200)
+                    .map(|i| ((i * (k + 1)) as f64 / 37.).sin())
+                    .collect()
+            })
+            .collect();
+        let slices: Vec<&[f64]> = signals.iter().map(|y| y.as_slice()).collect();
+        let config = PeakFinder::new(&signals[0])
+            .with_min_prominence(0.5)
+            .config();
+
+        let batch = super::find_peaks_batch(&slices, &config);
+        for (y, peaks) in slices.iter().zip(batch) {
+            assert_eq!(
+                peaks,
+                PeakFinder::with_config(y, config.clone()).find_peaks()
+            );
+        }
+    }
+
+    #[test]
+    fn tiny_inputs() {
+        let inputs: [&[u8]; 5] = [&[], &[1], &[1, 1], &[1, 2], &[2, 1]];
+
+        for y in inputs {
+            let mut fp = PeakFinder::new(y);
+            fp.with_edge_peaks(true);
+            assert_eq!(
+                fp.get_local_extrema(false, &fp.difference, |_| ()).count(),
+                0
+            );
+            assert_eq!(
+                fp.get_local_extrema(true, &fp.difference, |_| ()).count(),
+                0
+            );
+
+            // only the higher sample of two different ones is an edge peak
+            let edge = fp.get_edge_maxima(&fp.difference);
+            let last = y.len().saturating_sub(1);
+            let expected = match y {
+                [a, b] if a > b => (Some(0..1), None),
+                [a, b] if a < b => (None, Some(last..y.len())),
+                _ => (None, None),
+            };
+            assert_eq!(
+                (edge.0.map(|p| p.position), edge.1.map(|p| p.position)),
+                expected
+            );
+            assert_eq!(
+                fp.local_maxima().len(),
+                expected.0.iter().chain(&expected.1).count()
+            );
+        }
+    }
+
+    #[test]
+    fn valleys() {
+        let y = [1., 4., 2., 3., 1.5, 5., 2.5, 6., 3.];
+        let mut fp = PeakFinder::new(&y);
+        fp.with_min_prominence(0.)
+            .with_sort_order(SortOrder::Position);
+        let valleys = |ps: Vec<Peak<f64>>| {
+            ps.iter()
+                .map(|p| (p.valley_index, p.valley_value))
+                .collect::<Vec<_>>()
+        };
+
+        // the lower of the two valleys, not the one the prominence is measured from
+        assert_eq!(
+            valleys(fp.find_peaks()),
+            vec![
+                (Some(0), Some(1.)),
+                (Some(4), Some(1.5)),
+                (Some(0), Some(1.)),
+                (Some(0), Some(1.))
+            ]
+        );
+        // within the prominence window
+        fp.with_prominence_window(3);
+        assert_eq!(valleys(fp.find_peaks())[2], (Some(4), Some(1.5)));
+
+        // and across the seam of circular data
+        let y = [3., 0.5, 4., 1., 2., 0.];
+        let ps = PeakFinder::new(&y)
+            .with_min_prominence(0.)
+            .with_circular(true)
+            .find_peaks();
+        assert_eq!(ps[0].position, 2..3);
+        assert_eq!(
+            (ps[0].valley_index, ps[0].valley_value),
+            (Some(5), Some(0.))
+        );
+        assert_eq!(ps[1].position, 0..1);
+        assert_eq!(
+            (ps[1].valley_index, ps[1].valley_value),
+            (Some(5), Some(0.))
+        );
+
+        // no valleys without prominences
+        assert!(PeakFinder::new(&y).find_peaks()[0].valley_index.is_none());
+    }
+
+    #[test]
+    fn windowed() {
+        let y = [0., 2., 0., 1., 0., 3., 0., 1., 0., 4.];
+        let mut fp = PeakFinder::new(&y);
+        fp.with_min_prominence(0.)
+            .with_sort_order(SortOrder::Position);
+        let windows = fp.find_peaks_windowed(4, 3);
+
+        let ranges: Vec<_> = windows.iter().map(|(r, _)| r.clone()).collect();
+        assert_eq!(ranges, vec![0..4, 3..7, 6..10]);
+        let middles: Vec<Vec<usize>> = windows
+            .iter()
+            .map(|(_, ps)| ps.iter().map(|p| p.middle_position()).collect())
+            .collect();
+        // the peak at 3 is on the edge of the second window
+        assert_eq!(middles, vec![vec![1], vec![5], vec![7]]);
+
+        // the prominence is measured within the window
+        let (_, ps) = &windows[1];
+        assert_eq!(ps[0].prominence, Some(3.));
+        assert_eq!(ps[0].valley_index, Some(4));
+
+        // a window longer than the data
+        let windows = fp.find_peaks_windowed(20, 5);
+        assert_eq!(windows.len(), 1);
+        assert_eq!(windows[0].1, fp.find_peaks());
+
+        // each window is a `find_peaks_in` of its range, also with a maximum number of peaks
+        fp.with_max_peaks(1);
+        for (range, ps) in fp.find_peaks_windowed(6, 4) {
+            assert_eq!(ps, fp.find_peaks_in(range));
+        }
+    }
+
+    #[test]
+    fn prominence_ratio() {
+        // prominences: 1 -> 10, 3 -> 0.5, 5 -> 2, 7 -> 6, 9 -> 0.5
+        let y = [0., 10., 8., 8.5, 0., 2., 0., 6., 5., 5.5, 0.];
+        let middles =
+            |ps: Vec<Peak<f64>>| ps.iter().map(|p| p.middle_position()).collect::<Vec<_>>();
+
+        let mut fp = PeakFinder::new(&y);
+        fp.with_sort_order(SortOrder::Position);
+        assert_eq!(middles(fp.find_peaks()), vec![1, 3, 5, 7, 9]);
+
+        // 3 and 7 are equally near to 5, the more prominent 7 counts
+        fp.with_min_prominence_ratio(0.1);
+        assert_eq!(middles(fp.find_peaks()), vec![1, 5, 7]);
+        fp.with_min_prominence_ratio(0.4);
+        assert_eq!(middles(fp.find_peaks()), vec![1, 7]);
+
+        // the ratio is applied after the absolute bound
+        fp.with_min_prominence_ratio(0.1).with_min_prominence(7.);
+        assert_eq!(middles(fp.find_peaks()), vec![1]);
+    }
+
+    #[test]
+    fn remap() {
+        let y = [0., 2., 2., 2., 0., 3., 3., 0.];
+        let old_x: Vec<usize> = (0..8).collect();
+        let new_x: Vec<usize> = (0..8).map(|i| 100 + i * i).collect();
+        let ps = PeakFinder::new(&y).find_peaks();
+
+        let remapped = remap_positions(&ps, &old_x, &new_x);
+        let centers: Vec<f64> = remapped.iter().map(|(x, _)| *x).collect();
+        assert_eq!(centers, vec![130.5, 104.]);
+        let peaks: Vec<Peak<f64>> = remapped.into_iter().map(|(_, p)| p).collect();
+        assert_eq!(peaks, ps);
+    }
+
+    #[test]
+    fn default_difference() {
+        let y = [1., 1., 0., 2., 2., 1., 3., 3., 3.];
+        let mut fp = PeakFinder::new(&y);
+        fp.with_edge_peaks(true).with_max_difference(2.5);
+        let ps = fp.find_peaks();
+        assert_eq!(ps.len(), 3);
+
+        let mut config = fp.config();
+        assert_eq!(config.difference.lower, Some(0.));
+        config.difference.lower = None;
+        assert_eq!(PeakFinder::with_config(&y, config).find_peaks(), ps);
+    }
+
+    #[test]
+    fn side_thresholds() {
+        let y = [3., 1., 0., 4., 3.5, 0., 1., 0.2, 2., 1.9];
+        let middles =
+            |ps: Vec<Peak<f64>>| ps.iter().map(|p| p.middle_position()).collect::<Vec<_>>();
+
+        let mut fp = PeakFinder::new(&y);
+        fp.with_edge_peaks(true)
+            .with_sort_order(SortOrder::Position);
+        assert_eq!(middles(fp.find_peaks()), vec![0, 3, 6, 8]);
+
+        // a steep rise and a gentle fall
+        fp.with_side_thresholds(Some(1.5), Some(0.1));
+        assert_eq!(middles(fp.find_peaks()), vec![0, 3, 8]);
+        // the left edge has no left side, 8 falls by only 0.1
+        fp.with_side_thresholds(Some(1.5), Some(0.5));
+        assert_eq!(middles(fp.find_peaks()), vec![0, 3]);
+        fp.with_side_thresholds(None, Some(2.5));
+        assert_eq!(middles(fp.find_peaks()), vec![]);
+    }
+
+    #[test]
+    fn expected_peaks() {
+        let y: Vec<f64> = (0..100).map(|i| (i % 2) as f64).collect();
+        let mut fp = PeakFinder::new(&y);
+        let ps = fp.find_peaks();
+        assert_eq!(ps.len(), 49);
+
+        assert_eq!(fp.with_expected_peaks(50).find_peaks(), ps);
+        // a hint that is too small is only a hint
+        assert_eq!(fp.with_expected_peaks(10).find_peaks(), ps);
+    }
+
+    #[test]
+    fn columns() {
+        let y = [0., 2., 2., 0., 3., 1., 1.5, 0.];
+        let mut fp = PeakFinder::new(&y);
+        fp.with_min_prominence(0.)
+            .with_sort_order(SortOrder::Position);
+        let columns = fp.find_peaks_columns();
+
+        assert_eq!(columns.len(), 3);
+        assert_eq!(columns.positions, vec![1..3, 4..5, 6..7]);
+        assert_eq!(columns.centers, vec![2, 4, 6]);
+        assert_eq!(columns.prominences, vec![Some(2.), Some(3.), Some(0.5)]);
+        assert_eq!(columns.right_diffs, vec![2., 2., 1.5]);
+        assert_eq!(Vec::<Peak<f64>>::from(columns), fp.find_peaks());
+
+        assert!(PeakColumns::<f64>::from(Vec::new()).is_empty());
+    }
+
+    #[test]
+    fn tie_break() {
+        let y = [0., 3., 0., 3., 0., 1., 0., 3., 0.];
+        let positions =
+            |ps: Vec<Peak<f64>>| ps.into_iter().map(|p| p.position.start).collect::<Vec<_>>();
+
+        let mut fp = PeakFinder::new(&y);
+        assert_eq!(positions(fp.find_peaks()), vec![1, 3, 7, 5]);
+        fp.with_min_distance(3);
+        assert_eq!(positions(fp.find_peaks()), vec![1, 7]);
+
+        fp.with_tie_break(TieBreak::RightmostFirst);
+        assert_eq!(positions(fp.find_peaks()), vec![7, 3]);
+    }
+
+    #[test]
+    fn abs() {
+        let y = [1., -2., 0.5, 3., -4., 0., -1.];
+        let mut fp = PeakFinder::new(&y);
+        fp.with_abs(true)
+            .with_min_height(0.)
+            .with_min_distance(2)
+            .with_sort_order(SortOrder::Position);
+        let (ps, suppressed) = fp.find_peaks_with_suppressed();
+
+        // the absolute values are [1, 2, 0.5, 3, 4, 0, 1]
+        assert_eq!(ps.len(), 2);
+        assert_eq!((ps[0].position.clone(), ps[0].height), (1..2, Some(-2.)));
+        assert_eq!((ps[1].position.clone(), ps[1].height), (4..5, Some(-4.)));
+        assert_eq!(suppressed.len(), 0);
+
+        fp.with_min_distance(4);
+        let (ps, suppressed) = fp.find_peaks_with_suppressed();
+        assert_eq!(ps.len(), 1);
+        assert_eq!(suppressed[0].height, Some(-2.));
+
+        fp.with_abs(false);
+        assert_eq!(fp.find_peaks()[0].position, 3..4);
+    }
+
+    #[test]
+    fn transformed_order() {
+        let y = [0., 2., 0., -3., 0., 1., 0.];
+        let mut fp = PeakFinder::new(&y);
+        fp.with_abs(true).with_min_height(0.5);
+        let heights = |ps: Vec<Peak<f64>>| ps.iter().map(|p| p.height).collect::<Vec<_>>();
+
+        // sorted by the signed heights, not by the absolute values
+        assert_eq!(
+            heights(fp.find_peaks()),
+            vec![Some(2.), Some(1.), Some(-3.)]
+        );
+        fp.with_max_peaks(1);
+        assert_eq!(heights(fp.find_peaks()), vec![Some(2.)]);
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "overflow")]
+    fn abs_signed_min() {
+        PeakFinder::new(&[0i8, 1, -128, 1, 0])
+            .with_abs(true)
+            .find_peaks();
+    }
+
+    #[test]
+    fn abs_signed_min_wider() {
+        let y: Vec<i16> = [0i8, 1, -128, 1, 0].iter().map(|&y| y.into()).collect();
+
+        let ps = PeakFinder::new(&y).with_abs(true).find_peaks();
+        assert_eq!(ps.len(), 1);
+        assert_eq!((ps[0].position.clone(), ps[0].height), (2..3, Some(-128)));
+    }
+
+    #[test]
+    fn bounded_by_edge() {
+        let y = [1., 3., 0., 2., 0.5, 4., 1., 1.5, 0.];
+        let mut fp = PeakFinder::new(&y);
+        fp.with_min_prominence(0.)
+            .with_sort_order(SortOrder::Position);
+        let bounded: Vec<bool> = fp.find_peaks().iter().map(|p| p.bounded_by_edge).collect();
+        // 1 has no higher sample to the left, 5 none at all, 7 none to the right
+        assert_eq!(bounded, vec![true, false, true, true]);
+
+        // within the window the searches of 1 and 7 still reach the edges, 5 is stopped short
+        fp.with_prominence_window(4);
+        let bounded: Vec<bool> = fp.find_peaks().iter().map(|p| p.bounded_by_edge).collect();
+        assert_eq!(bounded, vec![true, false, false, true]);
+    }
+
+    #[test]
+    fn dog() {
+        // a broad hump split by the seam of circular data, with ripples on it
+        let y: Vec<f64> = (0..100)
+            .map(|i| {
+                let d = (i as f64 - 50.).abs();
+                (-((50. - d) / 6.).powi(2) / 2.).exp() + 0.05 * (i % 2) as f64
+            })
+            .collect();
+        let mut fp = PeakFinder::new(&y);
+        fp.with_min_prominence(0.01);
+        assert!(fp.find_peaks().len() > 10);
+
+        // the ripples are filtered out, the halves of the hump are at the edges
+        fp.with_dog(2., 8.).with_min_prominence(0.3);
+        assert_eq!(fp.find_peaks(), vec![]);
+
+        fp.with_circular(true);
+        let ps = fp.find_peaks();
+        assert_eq!(ps.len(), 1);
+        assert_eq!(ps[0].position, 0..1);
+        assert_eq!(ps[0].height, Some(y[0]));
+
+        // reflecting at the edges keeps a constant level flat
+        let y = [3u32; 20];
+        assert!(PeakFinder::new(&y)
+            .with_dog(1., 3.)
+            .with_edge_peaks(true)
+            .find_peaks()
+            .is_empty());
+    }
+
+    #[test]
+    fn score() {
+        // a sharp and a broad peak of the same height and prominence, and one at the edge
+        let y = [0., 4., 0., 1., 2., 3., 4., 3., 2., 1., 0., 2., 3.];
+        let mut fp = PeakFinder::new(&y);
+        fp.with_edge_peaks(true)
+            .with_sort_order(SortOrder::Position);
+        assert!(fp.find_peaks().iter().all(|p| p.score.is_none()));
+
+        fp.with_score(ScoreWeights::default());
+        let scores: Vec<Option<f64>> = fp.find_peaks().iter().map(|p| p.score).collect();
+        // widths at half prominence: 1 and 4 samples, unknown at the edge
+        assert_eq!(scores, vec![Some(1.), Some(0.75), Some(0.75)]);
+
+        fp.with_score(ScoreWeights {
+            height: 0.,
+            prominence: 0.,
+            sharpness: 1.,
+        });
+        let scores: Vec<Option<f64>> = fp.find_peaks().iter().map(|p| p.score).collect();
+        assert_eq!(scores, vec![Some(1.), Some(0.25), None]);
+    }
+
+    #[test]
+    fn distance_metric() {
+        // angles in degrees, wrapping around in the middle of the data
+        let x = [340., 350., 355., 0., 5., 10., 90., 100., 110.];
+        let y = [0., 1., 3., 0., 2., 0., 0., 1., 0.];
+        let angle = |a: &f64, b: &f64| {
+            let d = (a - b).abs() % 360.;
+            d.min(360. - d)
+        };
+
+        let mut fp = PeakFinder::new_with_x(&y, &x);
+        fp.with_min_distance(20.);
+        assert_eq!(
+            fp.try_find_peaks(),
+            Err(PeakError::NonMonotonicX { index: 3 })
+        );
+
+        fp.with_distance_metric(angle);
+        let ps = fp.try_find_peaks().unwrap();
+        // 5 is only 10 degrees from 355
+        let middles: Vec<usize> = ps.iter().map(|p| p.middle_position()).collect();
+        assert_eq!(middles, vec![2, 7]);
+
+        // the metric also applies to the merge distance
+        let mut fp = PeakFinder::new_with_x(&y, &x);
+        fp.with_distance_metric(angle).with_merge_distance(15.);
+        let ps = fp.find_peaks();
+        assert_eq!(ps.len(), 2);
+        assert_eq!(ps[0].position, 2..5);
+    }
+
+    #[test]
+    fn local_maxima_count() {
+        let y = [2., 1., 3., 3., 0., 1., 0.5, 4.];
+        let mut fp = PeakFinder::new(&y);
+        assert!(fp.has_any_local_maxima());
+        assert_eq!(fp.local_maxima_count(), 2);
+        fp.with_edge_peaks(true);
+        assert_eq!(fp.local_maxima_count(), 4);
+        assert_eq!(fp.local_maxima_count(), fp.local_maxima().len());
+
+        // the bounds do not apply
+        fp.with_min_height(10.).with_min_difference(5.);
+        assert!(fp.find_peaks().is_empty());
+        assert_eq!(fp.local_maxima_count(), 4);
+
+        for y in [&[][..], &[1.], &[1., 2., 3.], &[2., 2.]] {
+            let fp = PeakFinder::new(y);
+            assert!(!fp.has_any_local_maxima());
+            assert_eq!(fp.local_maxima_count(), 0);
+        }
+    }
+
+    #[test]
+    fn segments() {
+        let y = [1., 0., 3., 3., 1., 4., 2., 2., 5., 0.];
+        let segments = PeakFinder::new(&y)
+            .with_min_prominence(0.)
+            .segment_by_peaks();
+
+        let ranges: Vec<_> = segments.iter().map(|(range, _)| range.clone()).collect();
+        assert_eq!(ranges, vec![0..4, 4..6, 6..10]);
+        let positions: Vec<_> = segments.iter().map(|(_, p)| p.position.clone()).collect();
+        assert_eq!(positions, vec![2..4, 5..6, 8..9]);
+        // unlike the segment, the prominence base of the peak at 5 reaches back to 1
+        assert_eq!(segments[1].1.valley_index, Some(1));
+
+        assert_eq!(PeakFinder::new(&[0., 1., 2.]).segment_by_peaks(), vec![]);
+        let segments = PeakFinder::new(&[0., 1., 0.]).segment_by_peaks();
+        assert_eq!(segments[0].0, 0..3);
+    }
+
+    #[test]
+    fn prominence_shortcut() {
+        // low bumps on a high baseline, with some dips to the global minimum
+        let mut state = 987u64;
+        let y: Vec<f64> = (0..300)
+            .map(|i| {
+                state = state.wrapping_mul(6364136223846793005).wrapping_add(1);
+                let noise = (state >> 40) as f64 / (1u64 << 24) as f64;
+                if i % 50 == 0 {
+                    0.
+                } else {
+                    10. + noise
+                }
+            })
+            .collect();
+
+        for &min in &[0.5, 10., 10.5] {
+            let fp = PeakFinder::new(&y);
+            let mut expected: Vec<_> = fp
+                .local_maxima()
+                .into_iter()
+                .filter(|p| fp.prominence_of(p.position.clone()) >= min)
+                .map(|p| p.position)
+                .collect();
+            let mut found: Vec<_> = PeakFinder::new(&y)
+                .with_min_prominence(min)
+                .find_peaks()
+                .into_iter()
+                .map(|p| p.position)
+                .collect();
+            expected.sort_by_key(|r| r.start);
+            found.sort_by_key(|r| r.start);
+            assert_eq!(found, expected);
+            assert!(!found.is_empty());
+        }
+    }
+
+    #[test]
+    fn debug() {
+        let y = [1., 2., 3., 0., 5., 0.];
+        let mut fp = PeakFinder::new(&y);
+        fp.with_min_prominence(1.).with_max_distance(3);
+
+        assert_eq!(
+            format!("{:?}", fp),
+            "PeakFinder { len: 6, height: Limits { lower: None, upper: None }, \
+             prominence: Limits { lower: Some(1.0), upper: None }, \
+             difference: Limits { lower: Some(0.0), upper: None }, \
+             plateau_size: Limits { lower: None, upper: None }, \
+             width_x: Limits { lower: None, upper: None }, \
+             x_range: Limits { lower: None, upper: None }, \
+             distance: Limits { lower: None, upper: Some(3) }, .. }"
+        );
+    }
+
+    #[test]
+    fn exclusion_mask() {
+        let y = [1., 0., 1., 3., 0.5, 4., 2.];
+        let mut mask = [false; 7];
+
+        let mut fp = PeakFinder::new(&y);
+        fp.with_min_prominence(0.)
+            .with_sort_order(SortOrder::Position);
+        let ps = fp.find_peaks();
+        assert_eq!(ps[0].prominence, Some(2.5));
+        assert_eq!(ps[0].valley_index, Some(1));
+
+        // the masked valley is not passed, the mask bounds the search like an edge
+        mask[1] = true;
+        fp.with_exclusion_mask(&mask);
+        let ps = fp.find_peaks();
+        assert_eq!(ps.len(), 2);
+        assert_eq!(ps[0].prominence, Some(2.));
+        assert_eq!(ps[0].valley_index, Some(4));
+        assert!(ps[0].bounded_by_edge);
+
+        // a masked peak is dropped, its neighbours are still compared with it
+        mask[1] = false;
+        mask[5] = true;
+        mask[4] = true;
+        let ps = PeakFinder::new(&y).with_exclusion_mask(&mask).find_peaks();
+        let middles: Vec<_> = ps.iter().map(|p| p.middle_position()).collect();
+        assert_eq!(middles, vec![3]);
+    }
+
+    #[test]
+    fn local_baseline() {
+        let y = [0., 1., 2., 6., 4., 5., 6., 7., 12., 9., 10., 11.];
+        let proms = |ps: Vec<Peak<f64>>| ps.iter().map(|p| p.prominence).collect::<Vec<_>>();
+
+        let mut fp = PeakFinder::new(&y);
+        fp.with_min_prominence(0.);
+        assert_eq!(proms(fp.find_peaks()), vec![Some(3.), Some(2.)]);
+
+        fp.with_local_baseline(2);
+        let ps = fp.find_peaks();
+        assert_eq!(ps[0].valley_index, None);
+        assert_eq!(proms(ps.clone()), vec![Some(5.), Some(4.)]);
+        // the prominence window is ignored
+        fp.with_prominence_window(2);
+        assert_eq!(fp.find_peaks(), ps);
+    }
+
+    #[test]
+    fn differences() {
+        let y: [u32; 5] = [3, 1, 1, 5, 0];
+        assert_eq!(
+            PeakFinder::new(&y).differences(),
+            vec![
+                (Slope::Falling, 2),
+                (Slope::Flat, 0),
+                (Slope::Rising, 4),
+                (Slope::Falling, 5)
+            ]
+        );
+        assert!(PeakFinder::new(&[1u32]).differences().is_empty());
+
+        let diffs = PeakFinder::new(&[0., f64::NAN, 1.]).differences();
+        assert_eq!(diffs[0].0, Slope::Flat);
+        assert!(diffs[1].1.is_nan());
+    }
+
+    #[test]
+    fn distance_in_x_units() {
+        let y = [0., 5., 0., 4., 0., 0., 3., 0., 0., 2., 0.];
+        let t = [
+            0., 1., 2., 100., 100.2, 100.4, 100.6, 100.8, 101., 110., 111.,
+        ];
+        let middles =
+            |ps: Vec<Peak<f64>>| ps.iter().map(|p| p.middle_position()).collect::<Vec<_>>();
+
+        // 1 and 3 are close in samples but far in time, 3 and 6 the other way around
+        let ps = PeakFinder::new_with_x(&y, &t)
+            .with_min_distance(4.)
+            .find_peaks();
+        assert_eq!(middles(ps), vec![1, 3, 9]);
+
+        let ps = PeakFinder::new(&y).with_min_distance(4).find_peaks();
+        assert_eq!(middles(ps), vec![1, 6]);
+    }
+
+    #[test]
+    fn peak_ext() {
+        let y = vec![0, 4, 1, 3, 2, 5, 1, 0];
+        assert_eq!(y.peaks(2), find_peaks_by_prominence(&y, 2));
+
+        let mut fp = PeakFinder::new(&y);
+        fp.with_min_height(4).with_sort_order(SortOrder::Position);
+        assert_eq!(
+            y[..5].peaks_with(&fp.config()),
+            vec![fp.find_peaks()[0].clone()]
+        );
+    }
+
+    #[test]
+    fn global_maximum_prominence() {
+        let y = [2., 1., 3., 0.5, 20., 4., 0., 6., 1.5];
+        let proms = |ps: Vec<Peak<f64>>| {
+            ps.iter()
+                .map(|p| (p.middle_position(), p.prominence.unwrap()))
+                .collect::<Vec<_>>()
+        };
+
+        // above the higher of the minima out to the ends, 0.5 and 0.
+        let mut fp = PeakFinder::new(&y);
+        fp.with_min_prominence(0.);
+        assert_eq!(proms(fp.find_peaks()), vec![(4, 19.5), (7, 4.5), (2, 2.)]);
+        fp.with_prominence_window(100);
+        assert_eq!(proms(fp.find_peaks()), vec![(4, 19.5), (7, 4.5), (2, 2.)]);
+
+        // the only valley of an edge peak is on its inner side
+        let y = [20., 1., 3., 0.];
+        let ps = PeakFinder::new(&y)
+            .with_edge_peaks(true)
+            .with_min_prominence(0.)
+            .find_peaks();
+        assert_eq!(proms(ps), vec![(0, 20.), (2, 2.)]);
+    }
+
+    #[test]
+    fn set_y_data() {
+        let frames = [
+            [0, 3, 1, 4, 0, 2, 0],
+            [5, 0, 2, 0, 1, 3, 2],
+            [0, 0, 0, 1, 0, 0, 0],
+        ];
+        let t = [0., 0.5, 1., 4., 4.5, 5., 9.];
+
+        let mut fp = PeakFinder::new_with_x(&frames[0], &t);
+        fp.with_min_prominence(1).with_min_distance(1.);
+        for frame in &frames {
+            fp.set_y_data(frame);
+            let mut fresh = PeakFinder::new_with_x(frame, &t);
+            fresh.with_min_prominence(1).with_min_distance(1.);
+            assert_eq!(fp.find_peaks(), fresh.find_peaks());
+        }
+    }
+
+    #[test]
+    fn plateau_value() {
+        let y = [0, 2, 2, 2, 0, 1, 0, 3, 3, 0];
+        for p in PeakFinder::new(&y).find_peaks() {
+            let value = p.plateau_value(&y);
+            assert!(y[p.position.clone()].iter().all(|&v| v == value));
+            assert_eq!(Some(value), p.height);
+        }
+    }
+
+    #[test]
+    fn merge_plateau_tolerance() {
+        let y = [0, 3, 3, 2, 3, 3, 0];
+        let positions = |ps: Vec<Peak<i32>>| ps.into_iter().map(|p| p.position).collect::<Vec<_>>();
+
+        let mut fp = PeakFinder::new(&y);
+        fp.with_sort_order(SortOrder::Position);
+        assert_eq!(positions(fp.find_peaks()), vec![1..3, 4..6]);
+        // the dip must be less than the tolerance deep
+        fp.with_merge_plateau_tolerance(1);
+        assert_eq!(positions(fp.find_peaks()), vec![1..3, 4..6]);
+        fp.with_merge_plateau_tolerance(2);
+        assert_eq!(positions(fp.find_peaks()), vec![1..6]);
+
+        // only the outer differences are checked
+        fp.with_min_difference(2);
+        let ps = fp.find_peaks();
+        assert_eq!((ps[0].left_diff, ps[0].right_diff), (3, 3));
+        assert_eq!(
+            positions(PeakFinder::new(&y).with_min_difference(2).find_peaks()),
+            vec![]
+        );
+
+        // maxima of different values stay apart
+        let y = [0, 3, 3, 2, 4, 0];
+        let ps = PeakFinder::new(&y)
+            .with_merge_plateau_tolerance(5)
+            .find_peaks();
+        assert_eq!(ps.len(), 2);
+    }
+
+    #[test]
+    fn length_mismatch() {
+        let y = [0., 2., 0., 1., 0.];
+        let x = [0., 1., 2., 3.];
+        let err = PeakFinder::try_new_with_x(&y, &x).unwrap_err();
+        assert_eq!(err, PeakError::LengthMismatch { y: 5, x: 4 });
+        assert_eq!(err.to_string(), "There are 5 y-values, but 4 x-values!");
+
+        let panic = std::panic::catch_unwind(|| PeakFinder::new_with_x(&y, &x)).unwrap_err();
+        assert_eq!(
+            panic.downcast_ref::<String>().map(String::as_str),
+            Some("There are 5 y-values, but 4 x-values!")
+        );
+    }
+
+    #[test]
+    fn sharpness() {
+        let y = [0., 1., 4., 1., 0., 0., 1., 2., 3., 2., 1., 0.];
+        let sharpness = |fp: &mut PeakFinder<f64, usize>| {
+            fp.with_sort_order(SortOrder::Position);
+            fp.find_peaks()
+                .iter()
+                .map(|p| p.sharpness)
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            sharpness(PeakFinder::new(&y).with_widths()),
+            vec![None, None]
+        );
+        // computed along with the prominence, a spike of width 4 / 3 and a bump of width 3
+        let expected = vec![Some(3.), Some(1.)];
+        assert_eq!(
+            sharpness(PeakFinder::new(&y).with_widths().with_min_prominence(0.)),
+            expected
+        );
+        assert_eq!(sharpness(PeakFinder::new(&y).with_sharpness()), expected);
+
+        // repeated x-values make a zero width
+        let x = [0., 1., 1., 1., 2.];
+        let ps = PeakFinder::new_with_x(&[0., 0., 5., 0., 0.], &x)
+            .with_sharpness()
+            .find_peaks();
+        assert_eq!(
+            ps[0].left_width.map(|l| l + ps[0].right_width.unwrap()),
+            Some(0.)
+        );
+        assert_eq!(ps[0].sharpness, None);
+    }
+
+    #[test]
+    fn scan_direction() {
+        let y = [1., 0., 2., 5., 2., 0., 1., 0.5, 3., 3., 0.];
+        let x: Vec<f64> = (0..11).map(|i| (i * i) as f64).collect();
+
+        let mut fp = PeakFinder::new_with_x(&y, &x);
+        fp.with_min_prominence(0.)
+            .with_widths()
+            .with_symmetry()
+            .with_min_distance(40.)
+            .with_filter(|p, y| y[p.position.start] > 1., false)
+            .with_labeler(|p| p.position.start.to_string());
+        let (forward, forward_suppressed) = fp.find_peaks_with_suppressed();
+        fp.with_scan_direction(Direction::Reverse);
+        let (reverse, reverse_suppressed) = fp.find_peaks_with_suppressed();
+
+        // the equally deep valleys of the peak at 3
+        assert_eq!(forward[0].valley_index, Some(1));
+        assert_eq!(reverse[0].valley_index, Some(5));
+        // the same up to the valleys and rounding
+        let round = |v: Option<f64>| v.map(|v| (v * 1e9).round() / 1e9);
+        let normalized = |ps: Vec<Peak<f64>>| {
+            ps.into_iter()
+                .map(|p| Peak {
+                    valley_index: None,
+                    symmetry: round(p.symmetry),
+                    left_width: round(p.left_width),
+                    right_width: round(p.right_width),
+                    sharpness: round(p.sharpness),
+                    ..p
+                })
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(normalized(reverse), normalized(forward));
+        assert_eq!(reverse_suppressed, forward_suppressed);
+        assert_eq!(forward_suppressed[0].position, 6..7);
+    }
+
+    #[test]
+    fn shape_together() {
+        let y = [0., 3., 1., 6., 5., 2., 0., 4., 4., 1., 2., 0.];
+        let x: Vec<f64> = (0..12).map(|i| (i as f64).sqrt()).collect();
+        let find = |set: &dyn Fn(&mut PeakFinder<f64, f64>)| {
+            let mut fp = PeakFinder::new_with_x(&y, &x);
+            fp.with_min_prominence(0.5)
+                .with_sort_order(SortOrder::Position);
+            set(&mut fp);
+            fp.find_peaks()
+        };
+
+        let all = find(&|fp| {
+            fp.with_symmetry()
+                .with_widths()
+                .with_score(ScoreWeights::default());
+        });
+        let symmetry = find(&|fp| {
+            fp.with_symmetry();
+        });
+        let widths = find(&|fp| {
+            fp.with_widths();
+        });
+        let score = find(&|fp| {
+            fp.with_score(ScoreWeights::default());
+        });
+        for (k, p) in all.iter().enumerate() {
+            assert_eq!(p.symmetry, symmetry[k].symmetry);
+            assert_eq!(
+                (p.left_width, p.right_width, p.sharpness),
+                (
+                    widths[k].left_width,
+                    widths[k].right_width,
+                    widths[k].sharpness
+                )
+            );
+            assert_eq!(p.score, score[k].score);
+        }
+        assert!(all
+            .iter()
+            .all(|p| p.symmetry.is_some() && p.score.is_some()));
+    }
+
+    #[test]
+    fn distance_presorted() {
+        let y = [0, 3, 0, 5, 1, 3, 0, 4, 2, 5, 0, 1, 0];
+        for &tie_break in &[TieBreak::LeftmostFirst, TieBreak::RightmostFirst] {
+            let mut fp = PeakFinder::new(&y);
+            fp.with_tie_break(tie_break);
+            let candidates = fp.find_peaks();
+            for distance in 1..8 {
+                fp.with_min_distance(distance);
+                assert_eq!(
+                    fp.filter_distance_presorted(candidates.clone()),
+                    fp.find_peaks()
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn local_contrast() {
+        let y = [3, 5, 1, 2, 1, 1, 4, 4, 1, 3, 6];
+        let mask = [
+            false, false, false, false, false, false, false, false, false, true, false,
+        ];
+        let mut fp = PeakFinder::new(&y);
+        fp.with_edge_peaks(true)
+            .with_sort_order(SortOrder::Position);
+        let middles = |fp: &PeakFinder<i32, usize>| {
+            fp.find_peaks()
+                .iter()
+                .map(|p| p.middle_position())
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(middles(&fp), vec![1, 3, 7, 10]);
+        // the window of the peak at 1 shrinks to 0..3 (mean 2), the plateau 6..8 is compared
+        // with 4..6 and 8..10 (mean 1.5)
+        assert_eq!(middles(fp.with_min_local_contrast(2, 3)), vec![1, 10]);
+        // the 3 left of the edge peak is masked
+        fp.with_exclusion_mask(&mask);
+        assert_eq!(middles(fp.with_min_local_contrast(2, 4)), vec![10]);
+    }
+
+    #[test]
+    fn distance_first() {
+        // the 4.8 on the shoulder of the 5. has little prominence
+        let y = [0., 5., 4.5, 4.5, 4.5, 4.8, 0., 2., 0.];
+        let mut fp = PeakFinder::new(&y);
+        fp.with_min_prominence(1.).with_min_distance(3);
+        let middles =
+            |ps: Vec<Peak<f64>>| ps.iter().map(|p| p.middle_position()).collect::<Vec<_>>();
+
+        assert_eq!(middles(fp.find_peaks()), vec![1, 7]);
+        fp.with_distance_first(true);
+        let (ps, stats) = fp.find_peaks_with_stats();
+        assert_eq!(middles(ps), vec![1]);
+        assert_eq!((stats.after_distance, stats.after_prominence), (2, 1));
+        assert_eq!(middles(fp.find_peaks_with_suppressed().1), vec![7]);
+    }
+
+    #[test]
+    fn resample() {
+        // the bump at 2.4 is sampled densely
+        let x = [0., 1., 2., 2.2, 2.4, 2.6, 4., 5., 6.];
+        let y = [0., 3., 0., 1., 2., 1., 0., 2., 0.];
+        let mut fp = PeakFinder::new_with_x(&y, &x);
+        fp.with_min_height(1.).with_sort_order(SortOrder::Position);
+        let positions =
+            |ps: Vec<Peak<f64>>| ps.iter().map(|p| p.position.clone()).collect::<Vec<_>>();
+
+        assert_eq!(positions(fp.find_peaks()), vec![1..2, 4..5, 7..8]);
+        // on the unit grid it is a single sample at 3., interpolated between 2.6 and 4.
+        fp.with_resample(7);
+        assert_eq!(positions(fp.find_peaks()), vec![1..2, 7..8]);
+        let ps = fp.with_min_height(0.5).find_peaks();
+        assert_eq!(positions(ps.clone()), vec![1..2, 5..6, 7..8]);
+        assert!((ps[1].height.unwrap() - 1. / 1.4).abs() < 1e-9);
+        let (_, suppressed) = fp.with_min_distance(2.5).find_peaks_with_suppressed();
+        assert_eq!(positions(suppressed), vec![5..6]);
+        fp.with_min_distance(0.);
+        // the grid sample at 3. is masked, as its nearest original sample
+        let mask = [false, false, false, false, false, true, false, false, false];
+        assert_eq!(
+            positions(fp.with_exclusion_mask(&mask).find_peaks()),
+            vec![1..2, 7..8]
+        );
+    }
+
+    #[test]
+    fn plateau_slice() {
+        let y = [0, 2, 2, 2, 0, 1, 0, 3, 3];
+        let mut fp = PeakFinder::new(&y);
+        fp.with_sort_order(SortOrder::Position);
+        let slices = |ps: &[Peak<i32>]| {
+            ps.iter()
+                .map(|p| p.plateau_slice(&y).to_vec())
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(slices(&fp.find_peaks()), vec![vec![2, 2, 2], vec![1]]);
+        // up to the last sample
+        let ps = fp.with_edge_peaks(true).find_peaks();
+        assert_eq!(slices(&ps), vec![vec![2, 2, 2], vec![1], vec![3, 3]]);
+        // the merged peaks span the valley between them
+        let ps = fp.with_merge_distance(8).find_peaks();
+        assert_eq!(slices(&ps), vec![y[1..].to_vec()]);
+    }
+
+    #[test]
+    fn deterministic() {
+        // repeated heights and prominences, and a NaN sample
+        let y = [
+            0.,
+            2.,
+            0.,
+            1.,
+            0.,
+            2.,
+            0.,
+            1.,
+            0.,
+            2.,
+            1.,
+            2.,
+            0.,
+            f64::NAN,
+            0.,
+            1.,
+            0.,
+        ];
+        let mut fp = PeakFinder::new(&y);
+        fp.with_min_distance(3).with_min_prominence(0.);
+        let run = |fp: &PeakFinder<f64, usize>| format!("{:?}", fp.find_peaks_with_suppressed());
+
+        let ps = fp.find_peaks();
+        let middles = ps.iter().map(|p| p.middle_position()).collect::<Vec<_>>();
+        assert_eq!(middles, vec![1, 5, 9, 15]);
+        for priority in [DistancePriority::Height, DistancePriority::Prominence] {
+            fp.with_distance_priority(priority);
+            let first = run(&fp);
+            for _ in 0..100 {
+                assert_eq!(run(&fp), first);
+            }
+        }
+    }
+
+    #[test]
+    fn prominence_max_scan() {
+        let y = [0., 1., 2., 3., 4., 5., 0., 6., 0., 1., 0.5];
+        let mut fp = PeakFinder::new(&y);
+        fp.with_min_prominence(0.)
+            .with_sort_order(SortOrder::Position)
+            .with_prominence_max_scan(2);
+        let props = |fp: &PeakFinder<f64, usize>| {
+            fp.find_peaks()
+                .iter()
+                .map(|p| (p.prominence.unwrap(), p.prominence_capped))
+                .collect::<Vec<_>>()
+        };
+
+        // the 6. is capped on both sides, without changing its prominence; the search of the 1.
+        // ends at the 6. and the edge
+        assert_eq!(props(&fp), vec![(2., true), (6., true), (0.5, false)]);
+        // the same valleys as a window of the same reach, which is not flagged
+        fp.with_prominence_window(4);
+        assert_eq!(props(&fp), vec![(2., false), (6., false), (0.5, false)]);
+        // a tighter window wins
+        fp.with_prominence_window(2);
+        assert_eq!(props(&fp), vec![(1., false), (6., false), (0.5, false)]);
+        // a masked sample ends the search
+        let mut mask = [false; 11];
+        mask[4] = true;
+        fp.with_prominence_window(100).with_exclusion_mask(&mask);
+        assert_eq!(props(&fp)[0], (5., false));
+    }
+
+    #[test]
+    fn rank() {
+        let y = [0, 2, 0, 4, 3, 5, 0, 2, 1, 3, 0];
+        let mut fp = PeakFinder::new(&y);
+        fp.with_rank(true).with_sort_order(SortOrder::Position);
+        let ranks = |fp: &PeakFinder<i32, usize>| {
+            fp.find_peaks()
+                .iter()
+                .map(|p| p.rank.unwrap())
+                .collect::<Vec<_>>()
+        };
+
+        // by height without prominences
+        assert_eq!(ranks(&fp), vec![3, 1, 0, 4, 2]);
+        // the 4 and the second 2 are both of prominence 1
+        fp.with_min_prominence(0);
+        assert_eq!(ranks(&fp), vec![2, 3, 0, 4, 1]);
+        fp.with_tie_break(TieBreak::RightmostFirst);
+        assert_eq!(ranks(&fp), vec![2, 4, 0, 3, 1]);
+        fp.with_min_prominence(2)
+            .with_tie_break(TieBreak::LeftmostFirst);
+        assert_eq!(ranks(&fp), vec![2, 0, 1]);
+
+        let (ps, suppressed) = fp.with_min_distance(5).find_peaks_with_suppressed();
+        assert_eq!(ps[0].rank, Some(0));
+        assert!(suppressed.iter().all(|p| p.rank.is_none()));
+        fp.with_labeler(|p| format!("#{}", p.rank.unwrap()));
+        assert_eq!(fp.find_peaks()[0].label, Some("#0".to_string()));
+    }
+
+    #[test]
+    fn plateau_after_first_sample() {
+        let find = |y: &[i32], edge_peaks: bool| {
+            PeakFinder::new(y)
+                .with_edge_peaks(edge_peaks)
+                .find_peaks()
+                .into_iter()
+                .map(|p| (p.position, p.left_diff, p.right_diff))
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(find(&[2, 3, 3, 0], false), vec![(1..3, 1, 3)]);
+        assert_eq!(find(&[2, 3, 3, 3, 0], false), vec![(1..4, 1, 3)]);
+        assert_eq!(find(&[2, 2, 3, 0], false), vec![(2..3, 1, 3)]);
+        // reaching the edge
+        assert_eq!(find(&[2, 3, 3], false), vec![]);
+        assert_eq!(find(&[2, 3, 3], true), vec![(1..3, 1, 0)]);
+        // starting at the edge
+        assert_eq!(find(&[3, 3, 0], false), vec![]);
+        assert_eq!(find(&[3, 3, 0], true), vec![(0..2, 0, 3)]);
+        // the difference leading onto the plateau is the one of the first two samples
+        let ps = PeakFinder::new(&[2, 3, 3, 0])
+            .with_min_difference(2)
+            .find_peaks();
+        assert!(ps.is_empty());
+    }
+
+    #[test]
+    fn as_tuple() {
+        let y = [0, 2, 2, 0, 1, 0];
+        let ps = PeakFinder::new(&y).find_peaks();
+
+        assert_eq!(ps[0].as_tuple(), (2, Some(2)));
+        let points: Vec<(usize, Option<i32>)> = ps.into_iter().map(Into::into).collect();
+        assert_eq!(points, vec![(2, Some(2)), (4, Some(1))]);
+    }
+
+    #[test]
+    fn reference_signal() {
+        let y: [u8; 7] = [10, 12, 11, 14, 16, 15, 15];
+        let model: [u8; 7] = [10, 10, 11, 12, 16, 15, 14];
+        let mut fp = PeakFinder::new_with_reference(&y, &model);
+        fp.with_min_prominence(1)
+            .with_sort_order(SortOrder::Position);
+
+        // the residual is [0, 2, 0, 2, 0, 0, 1]
+        let ps = fp.find_peaks();
+        assert_eq!(
+            ps.iter().map(|p| p.position.clone()).collect::<Vec<_>>(),
+            vec![1..2, 3..4]
+        );
+        assert_eq!(
+            ps.iter()
+                .map(|p| (p.height, p.prominence))
+                .collect::<Vec<_>>(),
+            vec![(Some(12), Some(2)), (Some(14), Some(2))]
+        );
+        assert_eq!(ps[0].valley_value, Some(10));
+        // the bounds apply to the residual
+        assert!(fp.with_min_height(3).find_peaks().is_empty());
+
+        assert_eq!(
+            PeakFinder::try_new_with_reference(&y, &model[1..]).unwrap_err(),
+            PeakError::ReferenceLengthMismatch { y: 7, reference: 6 }
+        );
+        let result = std::panic::catch_unwind(|| PeakFinder::new_with_reference(&y, &model[1..]));
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn apex_height_range() {
+        // apexes at 6 + 1 / 12, 8 + 1 / 8 and the edge peak's 9
+        let y = [2, 6, 4, 5, 8, 2, 2, 9];
+        let mut fp = PeakFinder::new(&y);
+        fp.with_edge_peaks(true)
+            .with_sort_order(SortOrder::Position);
+        let middles = |fp: &PeakFinder<i32, usize>| {
+            fp.find_peaks()
+                .iter()
+                .map(|p| p.middle_position())
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(middles(fp.with_apex_height_range(6, 8)), vec![1]);
+        assert_eq!(middles(fp.with_apex_height_range(9, 9)), vec![7]);
+        // above the minimum of the data
+        fp.with_height_reference(HeightRef::AboveGlobalMin);
+        assert_eq!(middles(fp.with_apex_height_range(5, 7)), vec![4, 7]);
+    }
+
+    #[test]
+    fn difference_ranges() {
+        // peaks at 1 (rise 3, fall 1), 4 (rise 1, fall 3) and 6 (rise 4, fall 4)
+        let y = [0., 3., 2., 0., 1., -2., 2., -2.];
+        let middles =
+            |ps: Vec<Peak<f64>>| ps.iter().map(|p| p.middle_position()).collect::<Vec<_>>();
+
+        let mut fp = PeakFinder::new(&y);
+        fp.with_sort_order(SortOrder::Position);
+        assert_eq!(middles(fp.find_peaks()), vec![1, 4, 6]);
+
+        // a steep rise and a gentle fall
+        fp.with_left_difference_range(2., 3.5)
+            .with_right_difference_range(0., 1.5);
+        assert_eq!(middles(fp.find_peaks()), vec![1]);
+        // and the mirror image
+        fp.with_left_difference_range(0., 1.5)
+            .with_right_difference_range(2., 3.5);
+        assert_eq!(middles(fp.find_peaks()), vec![4]);
+        fp.with_left_difference_range(0., 10.)
+            .with_right_difference_range(3., 10.);
+        assert_eq!(middles(fp.find_peaks()), vec![4, 6]);
+    }
+
+    #[test]
+    fn max_peaks() {
+        // with equally high peaks, taken by the tie break
+        let y: Vec<f64> = (0..200)
+            .map(|i| ((i * 37 % 23) as f64).sin().round())
+            .collect();
+
+        let mut fp = PeakFinder::new(&y);
+        let all = fp.find_peaks();
+        assert!(all.len() > 10);
+        for &n in &[1, 5, all.len(), all.len() + 3] {
+            assert_eq!(
+                fp.with_max_peaks(n).find_peaks(),
+                all[..n.min(all.len())].to_vec()
+            );
+        }
+
+        fp.with_tie_break(TieBreak::RightmostFirst)
+            .with_sort_order(SortOrder::Position);
+        let mut expected = PeakFinder::new(&y)
+            .with_tie_break(TieBreak::RightmostFirst)
+            .find_peaks();
+        expected.truncate(5);
+        expected.sort_by_key(|p| p.position.start);
+        assert_eq!(fp.with_max_peaks(5).find_peaks(), expected);
+
+        // after the distance bounds
+        fp.with_min_distance(10).with_sort_order(SortOrder::Height);
+        let ps = fp.find_peaks();
+        assert_eq!(ps.len(), 5);
+        assert!(ps.iter().all(|p| ps
+            .iter()
+            .all(|q| p == q || p.middle_position().abs_diff(q.middle_position()) >= 10)));
+    }
+
+    #[test]
+    fn monotonic_runs() {
+        let y = [
+            3., 2., 1., 0., 1., 1., 2., 3., 1., 0., 2., 0., 1., 2., 3., 4.,
+        ];
+        let middles =
+            |ps: Vec<Peak<f64>>| ps.iter().map(|p| p.middle_position()).collect::<Vec<_>>();
+
+        let mut fp = PeakFinder::new(&y);
+        fp.with_edge_peaks(true)
+            .with_sort_order(SortOrder::Position);
+        assert_eq!(middles(fp.find_peaks()), vec![0, 7, 10, 15]);
+
+        // the left edge is not checked, 7 rises through a flat step
+        fp.with_min_fall_run(2);
+        assert_eq!(middles(fp.find_peaks()), vec![0, 7, 15]);
+        fp.with_min_rise_run(3);
+        assert_eq!(middles(fp.find_peaks()), vec![0, 15]);
+        fp.with_run_plateaus(true);
+        assert_eq!(middles(fp.find_peaks()), vec![0, 7, 15]);
+        fp.with_min_fall_run(4).with_min_rise_run(4);
+        assert_eq!(middles(fp.find_peaks()), vec![15]);
+        fp.with_min_fall_run(3).with_min_rise_run(5);
+        assert_eq!(middles(fp.find_peaks()), vec![0]);
+    }
+
+    #[test]
+    fn side_prominences() {
+        let y = [0., 4., 1., 5., 3., 6., 2.];
+        let mut fp = PeakFinder::new(&y);
+        fp.with_min_prominence(0.)
+            .with_edge_peaks(true)
+            .with_sort_order(SortOrder::Position);
+        let sides = |ps: Vec<Peak<f64>>| {
+            ps.iter()
+                .map(|p| (p.left_prominence, p.right_prominence, p.prominence))
+                .collect::<Vec<_>>()
+        };
+
+        // the valleys of 5. are the left edge and the 3. before the 6.
+        assert_eq!(
+            sides(fp.find_peaks()),
+            vec![
+                (Some(4.), Some(3.), Some(3.)),
+                (Some(5.), Some(2.), Some(2.)),
+                (Some(6.), Some(4.), Some(4.)),
+            ]
+        );
+
+        // a right edge peak has no valley on its right
+        let ps = PeakFinder::new(&[3., 0., 1., 2.])
+            .with_min_prominence(0.)
+            .with_edge_peaks(true)
+            .find_peaks();
+        assert_eq!(
+            sides(ps),
+            vec![(None, Some(3.), Some(3.)), (Some(2.), None, Some(2.))]
+        );
+
+        // no valleys above a fixed floor
+        fp.with_prominence_reference(ProminenceRef::Fixed(0.));
+        assert!(fp
+            .find_peaks()
+            .iter()
+            .all(|p| p.left_prominence.is_none() && p.right_prominence.is_none()));
+    }
+
+    #[test]
+    fn in_range() {
+        let y: Vec<f64> = (0..300)
+            .map(|i| ((i * 29 % 17) as f64).sin().round())
+            .collect();
+        let ps = PeakFinder::new(&y)
+            .with_sort_order(SortOrder::Position)
+            .find_peaks();
+        assert!(ps.iter().any(|p| p.position.len() > 1));
+
+        #[allow(clippy::reversed_empty_ranges)]
+        let ranges = [0..0, 0..300, 10..11, 37..150, 150..400, 299..300, 5..2];
+        for r in ranges.iter() {
+            let scanned: Vec<&Peak<f64>> = ps.iter().filter(|p| p.in_range(r)).collect();
+            assert_eq!(peaks_in_range(&ps, r).collect::<Vec<_>>(), scanned);
+        }
+        assert_eq!(peaks_in_range::<f64>(&[], &(0..10)).count(), 0);
+    }
+
+    #[test]
+    fn empty_data_entry_points() {
+        let empty: [f64; 0] = [];
+        let no_x: [usize; 0] = [];
+        let exercise = |fp: &PeakFinder<f64, usize>| {
+            assert!(fp.find_peaks().is_empty());
+            assert!(fp.find_peaks_with_stats().0.is_empty());
+            assert!(fp.find_peaks_with_suppressed().1.is_empty());
+            assert!(fp.find_peaks_x().is_empty());
+            assert!(fp.find_peaks_columns().is_empty());
+            assert!(fp.find_peaks_windowed(3, 1).is_empty());
+            assert!(fp.find_peaks_multiscale(&[fp.config()], 1).is_empty());
+            assert!(fp.local_maxima().is_empty());
+            assert!(fp.differences().is_empty());
+            assert_eq!(fp.local_maxima_count(), 0);
+            assert!(!fp.has_any_local_maxima());
+            assert!(fp.try_find_peaks().unwrap().is_empty());
+            assert!(fp.find_peak_indices().is_empty());
+            assert!(fp.find_peaks_derivative().is_empty());
+            assert!(fp.find_troughs().is_empty());
+            assert!(fp.find_shoulders().is_empty());
+            assert!(fp.find_extrema().is_empty());
+            assert!(fp.find_peaks_persistence().is_empty());
+            assert!(fp.valleys_between(&[]).is_empty());
+            assert!(fp.segment_by_peaks().is_empty());
+            assert!(fp.find_envelope_peaks().is_empty());
+            assert!(fp.nearest_peak_x(&[], 0).is_none());
+            assert!(fp.cluster_peaks(&[], 1).is_empty());
+            assert!(fp.find_peaks_cwt(&[1., 2.]).is_empty());
+            assert!(fp.filter_distance_presorted(Vec::new()).is_empty());
+        };
+
+        let configure: Vec<fn(&mut PeakFinder<f64, usize>)> = vec![
+            |_| {},
+            |fp| {
+                fp.with_edge_peaks(true);
+            },
+            |fp| {
+                fp.with_compute_all(true)
+                    .with_widths()
+                    .with_symmetry()
+                    .with_score(ScoreWeights::default())
+                    .with_rank(true);
+            },
+            |fp| {
+                fp.with_min_prominence(1.).with_local_baseline(3);
+            },
+            |fp| {
+                fp.with_min_prominence(1.)
+                    .with_prominence_reference(ProminenceRef::GlobalMin);
+            },
+            |fp| {
+                fp.with_min_prominence(1.)
+                    .with_prominence_window(5)
+                    .with_prominence_max_scan(2);
+            },
+            |fp| {
+                fp.with_min_relative_prominence(0.5)
+                    .with_auto_prominence(2.)
+                    .with_min_height_percentile(50.);
+            },
+            |fp| {
+                fp.with_hysteresis(0., 1.);
+            },
+            |fp| {
+                fp.with_resample(10);
+            },
+            |fp| {
+                fp.with_dog(1., 2.).with_abs(true);
+            },
+            |fp| {
+                fp.with_circular(true);
+            },
+            |fp| {
+                fp.with_scan_direction(Direction::Reverse);
+            },
+            |fp| {
+                fp.with_local_window(3)
+                    .with_min_local_contrast(2, 1.)
+                    .with_apex_height_range(0., 1.)
+                    .with_min_rise_run(2)
+                    .with_min_fall_run(2);
+            },
+            |fp| {
+                fp.with_min_distance(2)
+                    .with_merge_distance(1)
+                    .with_distance_priority(DistancePriority::Prominence)
+                    .with_max_peaks(3);
+            },
+            |fp| {
+                fp.scipy_compatible()
+                    .with_height_reference(HeightRef::AboveMean);
+            },
+        ];
+
+        for configure in &configure {
+            let mut fp = PeakFinder::new(&empty);
+            configure(&mut fp);
+            exercise(&fp);
+
+            let mut fp = PeakFinder::new_with_x(&empty, &no_x);
+            configure(&mut fp);
+            exercise(&fp);
+
+            let mut fp = PeakFinder::new_with_reference(&empty, &empty);
+            configure(&mut fp);
+            exercise(&fp);
+        }
+    }
+
+    #[test]
+    fn peaks_and_valleys() {
+        // plateaus, staircases and NaNs
+        let mut y: Vec<f64> = (0..300)
+            .map(|i| ((i * 31 % 19) as f64).sin().round())
+            .collect();
+        y[40] = f64::NAN;
+        y[41] = f64::NAN;
+        y[120] = f64::NAN;
+
+        let mut fp = PeakFinder::new(&y);
+        for &(min, max) in &[(0., 10.), (0.5, 10.), (0., 1.5), (1.5, 10.)] {
+            fp.with_min_difference(min).with_max_difference(max);
+            // the same extrema as the scans for each kind
+            let limit = &fp.difference;
+            let maxima: Vec<Peak<f64>> = fp.get_local_extrema(false, limit, |_| ()).collect();
+            let minima: Vec<Peak<f64>> = fp.get_local_extrema(true, limit, |_| ()).collect();
+            assert!(!maxima.is_empty() || min > 1.);
+
+            assert_eq!(fp.find_peaks_and_valleys(), (maxima, minima));
+        }
 
-        self.distance.upper = Some(distance);
-        self
+        // the plateau bounds apply to both
+        fp.with_min_difference(0.)
+            .with_max_difference(10.)
+            .with_min_plateau_size(2);
+        let (maxima, minima) = fp.find_peaks_and_valleys();
+        assert!(maxima.iter().chain(&minima).all(|p| p.position.len() >= 2));
+        assert!(!minima.is_empty());
     }
-}
-
-#[cfg(test)]
-mod tests {
-    use super::{Peak, PeakFinder};
 
     #[test]
-    fn findpeaks() {
-        let y = [1., 2., 3., 0., 5., 0.];
+    fn base_fn() {
+        let y = [0., 1., 2., 6., 4., 5., 6., 7., 12., 9., 10., 11.];
+        let proms = |ps: Vec<Peak<f64>>| ps.iter().map(|p| p.prominence).collect::<Vec<_>>();
+
         let mut fp = PeakFinder::new(&y);
-        fp.with_min_height(0.);
+        fp.with_min_prominence(0.)
+            .with_base_fn(|i, y: &[f64]| y[0] + i as f64);
+        let ps = fp.find_peaks();
+        assert_eq!(ps[0].valley_index, None);
+        assert_eq!(proms(ps.clone()), vec![Some(4.), Some(3.)]);
+        // the other prominence settings are ignored
+        fp.with_local_baseline(2)
+            .with_prominence_window(2)
+            .with_prominence_reference(ProminenceRef::GlobalMin);
+        assert_eq!(fp.find_peaks(), ps);
+
+        // below the base level
+        fp.with_base_fn(|_, y: &[f64]| y.iter().sum::<f64>() / y.len() as f64);
+        assert_eq!(proms(fp.find_peaks())[1], Some(0.));
+
+        // a merged peak is measured at its middle position
+        fp.with_base_fn(|i, _| i as f64).with_merge_distance(5);
         let ps = fp.find_peaks();
+        assert_eq!(ps[0].position, 3..9);
+        assert_eq!(proms(ps), vec![Some(6.)]);
+    }
+
+    #[test]
+    fn cost_estimate() {
+        let y = [3., 1., 2., 0., 2., 1., 2., 0., 4.];
+        let x = [0., 1., 2., 3., 4., 5., 6., 7., 8.];
+        let shuffled = [0., 1., 2., 3., 8., 5., 6., 7., 4.];
+
+        let mut fp = PeakFinder::new_with_x(&y, &x);
+        assert_eq!(fp.estimate_cost().candidates, 3);
+        fp.with_edge_peaks(true).with_min_prominence(1.);
         assert_eq!(
-            ps,
-            vec![
-                Peak {
-                    position: 4..5,
-                    left_diff: 5.,
-                    right_diff: 5.,
-                    height: Some(5.),
-                    prominence: None
-                },
-                Peak {
-                    position: 2..3,
-                    left_diff: 1.,
-                    right_diff: 3.,
-                    height: Some(3.),
-                    prominence: None
-                },
-            ]
+            fp.estimate_cost(),
+            CostEstimate {
+                candidates: 5,
+                quadratic_risk: false
+            }
         );
+
+        // the x-values only matter for the distance bounds
+        let mut fp = PeakFinder::new_with_x(&y, &shuffled);
+        assert!(!fp.estimate_cost().quadratic_risk);
+        fp.with_min_distance(2.);
+        assert!(fp.estimate_cost().quadratic_risk);
+
+        let mut fp = PeakFinder::new_with_x(&y, &x);
+        fp.with_min_distance(2.);
+        assert!(!fp.estimate_cost().quadratic_risk);
+        fp.with_circular(true);
+        assert!(fp.estimate_cost().quadratic_risk);
     }
 
+    #[cfg(feature = "num-complex")]
     #[test]
-    fn proms() {
-        let y = [1., 2., 3., 0., 5., 0.];
+    fn complex_magnitudes() {
+        use num_complex::Complex;
+
+        // a plateau of magnitude 5 with changing phase, and a lower peak of magnitude 2
+        let data = [
+            Complex::new(1., 0.),
+            Complex::new(3., 4.),
+            Complex::new(-4., 3.),
+            Complex::new(0., 1.),
+            Complex::new(0., -2.),
+            Complex::new(0., 0.),
+        ];
+        let ps = PeakFinder::from_complex(&data)
+            .with_min_prominence(0.5)
+            .find_peaks();
+
+        assert_eq!(ps.len(), 2);
+        assert_eq!(ps[0].position, 1..3);
+        assert_eq!(ps[0].prominence, Some(4.));
+        assert_eq!(ps[0].complex_value(&data), &Complex::new(-4., 3.));
+        assert_eq!(ps[1].position, 4..5);
+        assert_eq!(ps[1].prominence, Some(1.));
+        assert_eq!(ps[1].complex_value(&data), &Complex::new(0., -2.));
+    }
+
+    #[test]
+    fn target_peak_count() {
+        let y = [0., 9., 0., 8., 0., 7., 0., 6., 0., 5., 0., 0., 0., 4., 0.];
+        let middles = |fp: &PeakFinder<f64, usize>| {
+            let mut ms: Vec<usize> = fp
+                .find_peaks()
+                .iter()
+                .map(|p| p.middle_position())
+                .collect();
+            ms.sort_unstable();
+            ms
+        };
+
         let mut fp = PeakFinder::new(&y);
-        fp.with_min_height(0.);
-        fp.with_min_prominence(1.);
-        let ps = fp.find_peaks();
+        fp.with_min_distance(20);
+        assert_eq!(middles(&fp), vec![1]);
+        // relaxed to 12, then to 6
+        fp.with_target_peak_count(2);
+        assert_eq!(middles(&fp), vec![1, 13]);
+        fp.with_target_peak_count(3);
+        assert_eq!(middles(&fp), vec![1, 7, 13]);
+        // met without relaxing
+        fp.with_min_distance(4);
+        assert_eq!(middles(&fp), vec![1, 5, 9, 13]);
+        // more than there are peaks
+        fp.with_target_peak_count(10);
+        assert_eq!(middles(&fp), vec![1, 3, 5, 7, 9, 13]);
+
+        // on circular data, 1 and 9 are 7 apart around the end
+        let mut fp = PeakFinder::new(&y);
+        fp.with_circular(true)
+            .with_min_distance(20)
+            .with_target_peak_count(2);
+        assert_eq!(middles(&fp), vec![1, 9]);
+        // and the other bounds stay
+        let mut fp = PeakFinder::new(&y);
+        fp.with_min_distance(20)
+            .with_max_distance(10)
+            .with_target_peak_count(3);
+        assert_eq!(middles(&fp), vec![1, 5, 9]);
+    }
+
+    #[test]
+    fn prominence_calculator() {
+        let y: Vec<f64> = (0..300)
+            .map(|i| ((i * 7 % 17) as f64 + (i as f64 / 20.).sin() * 8.).round())
+            .collect();
+        let mask: Vec<bool> = (0..300).map(|i| i % 97 == 50).collect();
+
+        let setups: Vec<fn(&mut PeakFinder<f64, usize>)> = vec![
+            |_| {},
+            |fp| {
+                fp.with_prominence_window(11);
+            },
+            |fp| {
+                fp.with_prominence_max_scan(4);
+            },
+            |fp| {
+                fp.with_prominence_reference(ProminenceRef::GlobalMin);
+            },
+        ];
+        for (setup, masked) in setups.iter().flat_map(|s| vec![(s, false), (s, true)]) {
+            let mut fp = PeakFinder::new(&y);
+            if masked {
+                fp.with_exclusion_mask(&mask);
+            }
+            setup(&mut fp);
+            let ps = fp.with_min_prominence(0.).find_peaks();
+            assert!(ps.len() > 20);
+
+            let calculator = fp.prominence_calculator();
+            for p in ps {
+                assert_eq!(Some(calculator.prominence_for(p.position)), p.prominence);
+            }
+        }
+    }
+
+    #[test]
+    fn ecg_preset() {
+        // beats at 72 per minute sampled at 250 Hz: R-peaks every 208 samples, a T wave 0.3 s
+        // after each and a noise spike 0.1 s after each
+        let rate = 250.;
+        let y: Vec<f64> = (0..1000)
+            .map(|i| match i % 208 {
+                10 => 1.5,
+                35 => 0.9,
+                85 => 0.6,
+                _ => 0.05 * ((i * 13 % 7) as f64),
+            })
+            .collect();
+        let t: Vec<f64> = (0..1000).map(|i| i as f64 / rate).collect();
+
+        let mut fp = PeakFinder::new_with_x(&y, &t);
+        fp.ecg_preset(rate);
+        let mut middles: Vec<usize> = fp
+            .find_peaks()
+            .iter()
+            .map(|p| p.middle_position())
+            .collect();
+        middles.sort_unstable();
+        // the spikes are within 50 samples of the R-peaks, the T waves are not
         assert_eq!(
-            ps,
-            vec![
-                Peak {
-                    position: 4..5,
-                    left_diff: 5.,
-                    right_diff: 5.,
-                    height: Some(5.),
-                    prominence: Some(5.)
-                },
-                Peak {
-                    position: 2..3,
-                    left_diff: 1.,
-                    right_diff: 3.,
-                    height: Some(3.),
-                    prominence: Some(2.)
-                }
-            ]
+            middles,
+            vec![10, 85, 218, 293, 426, 501, 634, 709, 842, 917]
         );
+
+        fp.with_min_prominence(1.);
+        assert_eq!(fp.find_peaks().len(), 5);
     }
 
     #[test]
-    fn plateaus() {
-        let y = [1., 2., 3., 3., 3., 0., 5., 5., 0.];
+    fn inter_peak_intervals() {
+        let y = [1, 0, 2, 2, 0, 0, 3, 0, 1];
         let mut fp = PeakFinder::new(&y);
-        fp.with_min_height(0.);
-        fp.with_min_prominence(0.);
+        fp.with_edge_peaks(true);
+        let ps = fp.find_peaks();
+        assert_eq!(ps.len(), 4);
 
+        // from the middle position 3 of the plateau at 2..4
+        assert_eq!(fp.inter_peak_intervals(&ps), vec![3, 3, 2]);
+        assert_eq!(fp.inter_peak_intervals(&ps[..1]), vec![]);
+        // decreasing x-values
+        let x = [16, 14, 12, 10, 8, 6, 4, 2, 0];
+        let fp = PeakFinder::new_with_x(&y, &x);
+        assert_eq!(fp.inter_peak_intervals(&ps), vec![6, 6, 4]);
+        // the edge samples are one plateau across the seam, with its middle at 0
+        let mut fp = PeakFinder::new(&y);
+        fp.with_circular(true);
         let ps = fp.find_peaks();
+        assert_eq!(fp.inter_peak_intervals(&ps), vec![3, 3]);
+    }
 
-        assert_eq!(
-            ps,
-            vec![
-                Peak {
-                    position: 6..8,
-                    left_diff: 5.,
-                    right_diff: 5.,
-                    height: Some(5.),
-                    prominence: Some(5.)
-                },
-                Peak {
-                    position: 2..5,
-                    left_diff: 1.,
-                    right_diff: 3.,
-                    height: Some(3.),
-                    prominence: Some(2.)
-                }
-            ]
-        );
+    #[test]
+    fn find_peaks_in() {
+        let y = [0., 5., 0., 2., 4., 1., 6., 0.];
+        let x = [0., 1., 2., 3., 4., 5., 9., 10.];
+        let mut fp = PeakFinder::new_with_x(&y, &x);
+        fp.with_min_prominence(0.)
+            .with_labeler(|p| format!("{}", p.middle_position()));
 
-        fp.with_min_plateau_size(3);
-        let ps = fp.find_peaks();
+        let ps = fp.find_peaks_in(3..6);
+        assert_eq!(ps.len(), 1);
+        assert_eq!(ps[0].position, 4..5);
+        // the left valley is the edge of the range, not the 0. at 2
+        assert_eq!(ps[0].prominence, Some(2.));
+        assert_eq!(ps[0].valley_index, Some(5));
+        assert_eq!(ps[0].label.as_deref(), Some("4"));
+        assert_eq!(fp.find_peaks()[2].prominence, Some(3.));
 
-        assert_eq!(
-            ps,
-            vec![Peak {
-                position: 2..5,
-                left_diff: 1.,
-                right_diff: 3.,
-                height: Some(3.),
-                prominence: Some(2.)
-            }]
-        );
+        // the first sample of the range is an edge, and the distances are in its x-values
+        fp.with_edge_peaks(true).with_min_distance(3.);
+        let middles: Vec<usize> = fp
+            .find_peaks_in(1..8)
+            .iter()
+            .map(|p| p.middle_position())
+            .collect();
+        assert_eq!(middles, vec![6, 1, 4]);
+
+        assert!(fp.find_peaks_in(3..3).is_empty());
+        assert_eq!(fp.find_peaks_in(0..8), fp.find_peaks());
     }
 
     #[test]
-    fn plateau_with_diff() {
-        let y = [1., 2., 3., 3., 3., 0., 5., 5., 0.];
+    fn relative_difference() {
+        // small and large peaks on a baseline of 10
+        let y = [12., 10., 10., 13., 11., 10., 40., 30., 10., 19.];
+        let middles =
+            |ps: Vec<Peak<f64>>| ps.iter().map(|p| p.middle_position()).collect::<Vec<_>>();
+
         let mut fp = PeakFinder::new(&y);
-        fp.with_min_prominence(0.);
-        fp.with_min_height(0.);
+        fp.with_sort_order(SortOrder::Position)
+            .with_edge_peaks(true);
+        assert_eq!(middles(fp.find_peaks()), vec![0, 3, 6, 9]);
 
-        fp.with_min_difference(4.);
+        // the falls of 2 from 12 and 13 are too shallow, the outer sides of the edge peaks are
+        // not checked
+        fp.with_min_difference_relative(0.2);
+        assert_eq!(middles(fp.find_peaks()), vec![6, 9]);
+        // but not above the baseline
+        fp.with_height_reference(HeightRef::AboveGlobalMin);
+        assert_eq!(middles(fp.find_peaks()), vec![0, 3, 6, 9]);
+        // with the absolute bound too
+        fp.with_min_difference(5.);
+        assert_eq!(middles(fp.find_peaks()), vec![6, 9]);
+    }
+
+    #[test]
+    fn distance_suppression_stats() {
+        let y = [0., 3., 0., 2., 0., 0., 0., 0., 4., 0., 1., 0.];
+        let suppressed =
+            |fp: &PeakFinder<f64, usize>| fp.find_peaks_with_stats().1.suppressed_by_distance;
+
+        let mut fp = PeakFinder::new(&y);
+        assert_eq!(suppressed(&fp), 0);
+        // the peaks are at least 2 apart
+        fp.with_min_distance(2);
+        assert_eq!(suppressed(&fp), 0);
+        fp.with_min_distance(3);
+        assert_eq!(suppressed(&fp), 2);
+        assert_eq!(suppressed(&fp), fp.find_peaks_with_suppressed().1.len());
+        // merged peaks are not suppressed
+        fp.with_merge_distance(2);
+        assert_eq!(suppressed(&fp), 0);
+        // nor are the ones dropped before the distance bounds
+        let mut fp = PeakFinder::new(&y);
+        fp.with_min_distance(3).with_min_height(2.5);
+        assert_eq!(suppressed(&fp), 0);
+    }
+
+    #[test]
+    fn log_amplitude() {
+        // lines of 10, 2 and 0.02 over backgrounds a tenth of them, and a negative sample
+        let y = [1., 10., 1., 0.2, 2., 0.2, -1., 0.002, 0.02, 0.002];
+        let heights = |ps: Vec<Peak<f64>>| ps.iter().map(|p| p.height).collect::<Vec<_>>();
+
+        let mut fp = PeakFinder::new(&y);
+        fp.with_min_prominence(0.5);
+        assert_eq!(heights(fp.find_peaks()), vec![Some(10.), Some(2.)]);
+
+        // one decade each, the negative sample is clamped to 0.002
+        fp.with_log_amplitude(10.);
         let ps = fp.find_peaks();
+        assert_eq!(heights(ps.clone()), vec![Some(10.), Some(2.), Some(0.02)]);
+        assert_eq!(ps[2].valley_value, Some(0.002));
+        assert!(ps
+            .iter()
+            .all(|p| (p.prominence.unwrap() - 1.).abs() < 1e-12));
+        // and the heights in decades
+        fp.with_min_height(0.);
+        assert_eq!(heights(fp.find_peaks()), vec![Some(10.), Some(2.)]);
 
-        assert_eq!(
-            ps,
-            vec![Peak {
-                position: 6..8,
-                left_diff: 5.,
-                right_diff: 5.,
-                height: Some(5.),
-                prominence: Some(5.)
-            }]
-        );
+        // no positive sample
+        let y = [0., -1., 0., -2., 0.];
+        let mut fp = PeakFinder::new(&y);
+        fp.with_log_amplitude(2.).with_min_prominence(0.);
+        assert!(fp.find_peaks().is_empty());
     }
 
     #[test]
-    fn with_x() {
-        let y = [1., 2., 3., 0., 5., 0.];
-        let x: Vec<usize> = (1..(y.len() + 1)).collect();
-        let ps = PeakFinder::new_with_x(&y, &x)
-            .with_min_height(0.)
-            .with_min_distance(2)
-            .find_peaks();
-        assert_eq!(
-            ps,
-            vec![
-                Peak {
-                    position: 4..5,
-                    left_diff: 5.,
-                    right_diff: 5.,
-                    height: Some(5.),
-                    prominence: None
+    fn reset() {
+        let y = [0., 3., 0., 1., 1., 0., 2., 0., 0.5];
+        let x = [0., 1., 2., 3., 4., 5., 6., 7., 8.];
+        let mut fp = PeakFinder::new_with_x(&y, &x);
+        fp.with_sort_order(SortOrder::Position)
+            .with_edge_peaks(true);
+        let all = fp.find_peaks();
+        assert_eq!(all.len(), 4);
+
+        type Setter = fn(&mut PeakFinder<f64, f64>);
+        let bounds: Vec<(Setter, Setter)> = vec![
+            (
+                |fp| {
+                    fp.with_min_height(2.5);
                 },
-                Peak {
-                    position: 2..3,
-                    left_diff: 1.,
-                    right_diff: 3.,
-                    height: Some(3.),
-                    prominence: None
-                }
-            ]
+                |fp| {
+                    fp.without_min_height();
+                },
+            ),
+            (
+                |fp| {
+                    fp.with_max_height(0.5);
+                },
+                |fp| {
+                    fp.without_max_height();
+                },
+            ),
+            (
+                |fp| {
+                    fp.with_min_prominence(1.5);
+                },
+                |fp| {
+                    fp.without_min_prominence();
+                },
+            ),
+            (
+                |fp| {
+                    fp.with_max_prominence(0.2);
+                },
+                |fp| {
+                    fp.without_max_prominence();
+                },
+            ),
+            (
+                |fp| {
+                    fp.with_min_difference(1.5);
+                },
+                |fp| {
+                    fp.without_min_difference();
+                },
+            ),
+            (
+                |fp| {
+                    fp.with_max_difference(0.2);
+                },
+                |fp| {
+                    fp.without_max_difference();
+                },
+            ),
+            (
+                |fp| {
+                    fp.with_min_plateau_size(2);
+                },
+                |fp| {
+                    fp.without_min_plateau_size();
+                },
+            ),
+            (
+                |fp| {
+                    fp.with_max_plateau_size(1);
+                },
+                |fp| {
+                    fp.without_max_plateau_size();
+                },
+            ),
+            (
+                |fp| {
+                    fp.with_min_width_x(0.5);
+                },
+                |fp| {
+                    fp.without_min_width_x();
+                },
+            ),
+            (
+                |fp| {
+                    fp.with_max_width_x(0.5);
+                },
+                |fp| {
+                    fp.without_max_width_x();
+                },
+            ),
+            (
+                |fp| {
+                    fp.with_min_distance(4.);
+                },
+                |fp| {
+                    fp.without_min_distance();
+                },
+            ),
+            (
+                |fp| {
+                    fp.with_max_distance(1.);
+                },
+                |fp| {
+                    fp.without_max_distance();
+                },
+            ),
+        ];
+        for (set, unset) in &bounds {
+            set(&mut fp);
+            assert_ne!(fp.find_peaks(), all);
+            unset(&mut fp);
+            assert_eq!(fp.find_peaks(), all);
+        }
+
+        for (set, _) in &bounds[..4] {
+            set(&mut fp);
+        }
+        fp.with_x_range(2., 5.).with_left_difference_range(2., 3.);
+        assert!(fp.find_peaks().is_empty());
+        // the sort order and the edge peaks are kept
+        assert_eq!(fp.reset().find_peaks(), all);
+        assert_eq!(
+            fp.config().difference,
+            Limits {
+                lower: Some(0.),
+                upper: None
+            }
         );
+
+        // the default difference is the zero of `with_zero`
+        let y = [0u8, 3, 0];
+        let mut fp = PeakFinder::new(&y);
+        fp.with_zero(0).with_min_difference(5).reset();
+        assert_eq!(fp.config().difference.lower, Some(0));
+        assert_eq!(fp.find_peaks().len(), 1);
     }
 
     #[test]
-    fn empty_data() {
-        let y: Vec<u8> = vec![];
-        let ps = PeakFinder::new(&y).with_min_prominence(1).find_peaks();
-        let ps2 = PeakFinder::new(&y).with_min_distance(1).find_peaks();
-        assert_eq!(ps, Vec::new());
-        assert_eq!(ps2, Vec::new());
+    fn split_asymmetric_plateaus() {
+        let y = [0, 5, 6, 6, 6, 6, 6, 0, 9, 0, 0];
+        let positions = |ps: Vec<Peak<i32>>| ps.into_iter().map(|p| p.position).collect::<Vec<_>>();
+
+        let mut fp = PeakFinder::new(&y);
+        fp.with_sort_order(SortOrder::Position);
+        assert_eq!(positions(fp.find_peaks()), vec![2..7, 8..9]);
+        fp.with_split_asymmetric_plateaus(6.);
+        assert_eq!(positions(fp.find_peaks()), vec![2..7, 8..9]);
+        fp.with_split_asymmetric_plateaus(5.);
+        let ps = fp.find_peaks();
+        assert_eq!(positions(ps.clone()), vec![2..4, 4..7, 8..9]);
+        assert_eq!((ps[0].left_diff, ps[0].right_diff), (1, 0));
+        assert_eq!((ps[1].left_diff, ps[1].right_diff), (0, 6));
+
+        // the zero sides at the split fail a side threshold
+        fp.with_side_thresholds(Some(1), Some(1));
+        assert_eq!(positions(fp.find_peaks()), vec![8..9]);
+
+        // edge plateaus stay whole
+        let y = [4, 4, 0, 1];
+        let ps = PeakFinder::new(&y)
+            .with_edge_peaks(true)
+            .with_split_asymmetric_plateaus(1.)
+            .find_peaks();
+        assert_eq!(positions(ps), vec![0..2, 3..4]);
     }
 
     #[test]
-    fn single_point() {
-        let y: Vec<u32> = vec![1];
-        let ps = PeakFinder::new(&y).find_peaks();
+    fn bound_ranges() {
+        let y = [0., 1., 0., 3., 3., 0., 5., 0., 2., 0.];
+        let mut pairs = PeakFinder::new(&y);
+        pairs
+            .with_min_height(1.)
+            .with_max_height(4.)
+            .with_min_prominence(0.5)
+            .with_max_prominence(3.)
+            .with_min_difference(1.)
+            .with_max_difference(4.)
+            .with_min_plateau_size(1)
+            .with_max_plateau_size(2)
+            .with_min_distance(2)
+            .with_max_distance(8);
+        let mut ranges = PeakFinder::new(&y);
+        ranges
+            .with_height_range(1., 4.)
+            .with_prominence_range(0.5, 3.)
+            .with_difference_range(1., 4.)
+            .with_plateau_size_range(1, 2)
+            .with_distance_range(2, 8);
 
-        assert_eq!(ps, vec![]);
+        assert_eq!(ranges.config(), pairs.config());
+        assert_eq!(ranges.find_peaks(), pairs.find_peaks());
+        assert!(!ranges.find_peaks().is_empty());
+
+        // equal ends are a single value
+        ranges.with_height_range(3., 3.);
+        let ps = ranges.find_peaks();
+        assert_eq!(ps.len(), 1);
+        assert_eq!(ps[0].position, 3..5);
     }
 
     #[test]
-    fn two_points() {
-        let y: Vec<u32> = vec![2, 2];
-        let ps = PeakFinder::new(&y).find_peaks();
-        let ps2 = PeakFinder::new(&y)
-            .with_min_prominence(3)
-            .with_min_distance(1)
-            .find_peaks();
+    fn global_extrema() {
+        let y: [u8; 8] = [3, 9, 0, 9, 1, 0, 4, 2];
+        let mut fp = PeakFinder::new(&y);
+        fp.with_min_height(5);
 
-        assert_eq!(ps, vec![]);
-        assert_eq!(ps2, vec![]);
+        let (ps, extrema) = fp.find_peaks_with_extrema();
+        assert_eq!(ps, fp.find_peaks());
+        // the first of equal samples
+        assert_eq!(
+            extrema,
+            Some(GlobalExtrema {
+                max_index: 1,
+                max: 9,
+                min_index: 2,
+                min: 0,
+            })
+        );
+
+        let y = [f64::NAN, 2., f64::NAN, -1.];
+        let (_, extrema) = PeakFinder::new(&y).find_peaks_with_extrema();
+        let extrema = extrema.unwrap();
+        assert_eq!((extrema.max_index, extrema.min_index), (1, 3));
+
+        let y = [f64::NAN];
+        assert_eq!(PeakFinder::new(&y).find_peaks_with_extrema().1, None);
+        let y: [f64; 0] = [];
+        assert_eq!(PeakFinder::new(&y).find_peaks_with_extrema().1, None);
     }
 
     #[test]
-    fn three_points() {
-        // TODO unsigned subtraction may fail -> try with u32
-        let y: Vec<i32> = vec![2, 3, 2];
-        let ps = PeakFinder::new(&y).with_min_height(0).find_peaks();
-        let ps2 = PeakFinder::new(&y).with_min_prominence(2).find_peaks();
-        let ps3 = PeakFinder::new(&y)
-            .with_min_prominence(2)
-            .with_min_distance(1)
-            .find_peaks();
+    fn extrema_in_scan() {
+        // a long monotonic run, a plateau at the global maximum and a minimum at the end
+        let y: Vec<f64> = (0..40)
+            .map(|i| i as f64)
+            .chain([45., 45., 3., 50., 50., 20.])
+            .chain((0..20).map(|i| 30. - i as f64))
+            .chain([-1.])
+            .collect();
+        let expected = Some(GlobalExtrema {
+            max_index: 43,
+            max: 50.,
+            min_index: 66,
+            min: -1.,
+        });
+
+        let x: Vec<f64> = (0..y.len()).map(|i| i as f64).collect();
+        let reference = vec![0.; y.len()];
+        let finders = [
+            PeakFinder::new(&y),
+            PeakFinder::new_with_reference(&y, &reference),
+            PeakFinder::new(&y).with_edge_peaks(true).clone(),
+            PeakFinder::new(&y).with_hysteresis(10., 40.).clone(),
+            PeakFinder::new(&y).with_merge_plateau_tolerance(1.).clone(),
+            PeakFinder::new(&y).with_circular(true).clone(),
+            PeakFinder::new(&y)
+                .with_scan_direction(Direction::Reverse)
+                .clone(),
+            PeakFinder::new(&y).with_abs(true).clone(),
+        ];
+        for fp in &finders {
+            let (ps, extrema) = fp.find_peaks_with_extrema();
+            assert_eq!(ps, fp.find_peaks());
+            assert_eq!(extrema, expected);
+        }
+        let resampled = PeakFinder::new_with_x(&y, &x).with_resample(30).clone();
+        assert_eq!(resampled.find_peaks_with_extrema().1, expected);
 
+        #[cfg(feature = "simd")]
         assert_eq!(
-            ps,
-            vec![Peak {
-                position: 1..2,
-                left_diff: 1,
-                right_diff: 1,
-                height: Some(3),
-                prominence: None
-            },]
+            PeakFinder::new(&y).with_simd().find_peaks_with_extrema().1,
+            expected
+        );
+
+        let y = [2., 1.];
+        assert_eq!(
+            PeakFinder::new(&y).find_peaks_with_extrema().1,
+            Some(GlobalExtrema {
+                max_index: 0,
+                max: 2.,
+                min_index: 1,
+                min: 1.,
+            })
         );
-        assert_eq!(ps2, vec![]);
-        assert_eq!(ps3, vec![]);
     }
 }
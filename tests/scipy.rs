@@ -0,0 +1,140 @@
+//! Agreement of the positions, prominences and widths with scipy's `find_peaks`, run with
+//! `cargo test --features scipy-tests` (needs Python with scipy, like the examples).
+#![cfg(feature = "scipy-tests")]
+
+use find_peaks::{PeakFinder, PlateauCenter};
+
+use pyo3::prelude::*;
+use pyo3::types::{IntoPyDict, PyTuple};
+
+const TOLERANCE: f64 = 1e-9;
+
+/// The oil prices of `examples/oil.rs`.
+fn oil() -> Vec<f64> {
+    vec![
+        78.34, 79.12, 80.12, 80.36, 82.21, 81.43, 81.07, 83.87, 84.9, 84.26, 86.37, 86.51, 88.62,
+        87.81, 85.62, 87.98, 85.73, 86.99, 88.45, 88.55, 89.74, 89.67, 89.46, 89.08, 91.23, 93.06,
+        92.65, 90.98, 91.66, 91.13, 95.97, 95.76, 93.25, 92.5, 90.32, 91.62, 94.52, 93.53, 94.97,
+        97.45, 98.6, 98.5, 107.29, 115.59, 113.2, 127.9, 123.42, 129.9, 113.62, 110.93, 109.72,
+        104.08, 100.4, 99.38, 104.31, 107.33, 114.25, 111.93, 119.18, 114.03, 113.72, 107.35,
+        109.18, 110.38, 104.68, 103.47, 109.53, 105.46, 101.5, 101.36, 101.03, 99.0, 105.33,
+        108.14, 113.0, 112.73, 108.17, 107.54, 108.19, 104.79, 102.42, 105.46, 104.56, 107.0,
+        106.74,
+    ]
+}
+
+/// Gaussian lines on a drifting baseline with deterministic pseudo-random noise.
+fn spectrum() -> Vec<f64> {
+    let lines = [
+        (80., 3., 10.),
+        (200., 8., 4.),
+        (230., 2., 6.),
+        (410., 15., 2.5),
+    ];
+    let mut state: u32 = 12345;
+    (0..500)
+        .map(|i| {
+            let x = i as f64;
+            state = state.wrapping_mul(1_103_515_245).wrapping_add(12345);
+            let noise = (state >> 16) as f64 / 65536. - 0.5;
+            let signal: f64 = lines
+                .iter()
+                .map(|(center, sigma, amplitude)| {
+                    amplitude * (-0.5 * ((x - center) / sigma).powi(2)).exp()
+                })
+                .sum();
+            signal + 0.002 * x + 0.3 * noise
+        })
+        .collect()
+}
+
+/// scipy's electrocardiogram sample data.
+fn electrocardiogram(py: Python) -> PyResult<Vec<f64>> {
+    let datasets =
+        PyModule::import(py, "scipy.datasets").or_else(|_| PyModule::import(py, "scipy.misc"))?;
+    datasets.call0("electrocardiogram")?.extract()
+}
+
+/// Compare the peaks of prominence at least `prom` and at least `distance` samples apart in
+/// `data` with scipy's.
+fn assert_agrees(py: Python, data: &[f64], prom: f64, distance: usize) -> PyResult<()> {
+    let mut fp = PeakFinder::new(data);
+    fp.scipy_compatible()
+        .with_min_prominence(prom)
+        .with_min_distance(distance);
+    let peaks = fp.find_peaks();
+
+    let signal = PyModule::import(py, "scipy.signal")?;
+    // a zero minimum width makes scipy compute the widths at half prominence
+    let kwargs = vec![
+        ("prominence", prom.to_object(py)),
+        ("distance", distance.to_object(py)),
+        ("width", 0.to_object(py)),
+    ]
+    .into_py_dict(py);
+    let result = signal.call("find_peaks", (data.to_vec(),), Some(kwargs))?;
+    let result: &PyTuple = result.downcast()?;
+    let positions: Vec<usize> = result.get_item(0).extract()?;
+    let props = result.get_item(1);
+    let prominences: Vec<f64> = props.get_item("prominences")?.extract()?;
+    let widths: Vec<f64> = props.get_item("widths")?.extract()?;
+
+    // scipy takes the left one of the two middle samples of an even plateau
+    let centers: Vec<usize> = peaks
+        .iter()
+        .map(|p| p.center(PlateauCenter::Floor))
+        .collect();
+    assert_eq!(centers, positions);
+
+    for ((p, prominence), width) in peaks.iter().zip(prominences).zip(widths) {
+        let center = p.center(PlateauCenter::Floor);
+        assert!(
+            (p.prominence.unwrap() - prominence).abs() < TOLERANCE,
+            "prominence of the peak at {}: {:?} vs {}",
+            center,
+            p.prominence,
+            prominence
+        );
+        let total = p.left_width.unwrap() + p.right_width.unwrap();
+        assert!(
+            (total - width).abs() < TOLERANCE,
+            "width of the peak at {}: {} vs {}",
+            center,
+            total,
+            width
+        );
+    }
+    Ok(())
+}
+
+#[test]
+fn oil_prices() -> PyResult<()> {
+    Python::with_gil(|py| {
+        for &prom in &[0.1, 1., 5.] {
+            for &distance in &[1, 4] {
+                assert_agrees(py, &oil(), prom, distance)?;
+            }
+        }
+        Ok(())
+    })
+}
+
+#[test]
+fn noisy_spectrum() -> PyResult<()> {
+    Python::with_gil(|py| {
+        for &prom in &[0.2, 1., 3.] {
+            for &distance in &[1, 10] {
+                assert_agrees(py, &spectrum(), prom, distance)?;
+            }
+        }
+        Ok(())
+    })
+}
+
+#[test]
+fn ecg() -> PyResult<()> {
+    Python::with_gil(|py| {
+        let data = electrocardiogram(py)?;
+        assert_agrees(py, &data, 1000. / 400., 1)
+    })
+}